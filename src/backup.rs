@@ -0,0 +1,149 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+// Backups live under the workspace root, one directory per run named by its unix timestamp
+const BACKUP_DIR: &str = ".hyper-bindgen/backup";
+const MANIFEST_FILE: &str = "manifest.txt";
+const FILES_DIR: &str = "files";
+
+// Number of backups kept around before the oldest ones are pruned
+const MAX_BACKUPS: usize = 10;
+
+// Copy a file or directory tree to a new location
+fn copy_recursively(from: &Path, to: &Path) -> Result<()> {
+    if from.is_file() {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(from, to)
+            .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+        return Ok(());
+    }
+
+    for entry in WalkDir::new(from).into_iter().filter_map(Result::ok) {
+        let relative = entry.path().strip_prefix(from)?;
+        let target = to.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::copy(entry.path(), &target).with_context(|| {
+                format!("Failed to copy {} to {}", entry.path().display(), target.display())
+            })?;
+        }
+    }
+    Ok(())
+}
+
+// Remove a file or directory if it exists
+fn remove_path(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else if path.exists() {
+        fs::remove_file(path)
+    } else {
+        return Ok(());
+    }
+    .with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+// List existing backups, oldest first
+fn list_backups(base_dir: &Path) -> Result<Vec<PathBuf>> {
+    let backup_root = base_dir.join(BACKUP_DIR);
+    if !backup_root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<(u64, PathBuf)> = fs::read_dir(&backup_root)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let timestamp = entry.file_name().to_str()?.parse().ok()?;
+            Some((timestamp, entry.path()))
+        })
+        .collect();
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+// Directory the next backup will be written to
+pub fn next_backup_dir(base_dir: &Path) -> Result<PathBuf> {
+    let mut timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let backup_root = base_dir.join(BACKUP_DIR);
+    while backup_root.join(timestamp.to_string()).exists() {
+        timestamp += 1;
+    }
+    Ok(backup_root.join(timestamp.to_string()))
+}
+
+// Copy the given workspace paths into a new backup before a run mutates them.
+// Paths that don't exist yet are recorded too, so undo can delete what the run created.
+pub fn create(base_dir: &Path, paths: &[PathBuf]) -> Result<PathBuf> {
+    let backup_dir = next_backup_dir(base_dir)?;
+    let files_dir = backup_dir.join(FILES_DIR);
+    fs::create_dir_all(&files_dir)
+        .with_context(|| format!("Failed to create backup directory {}", backup_dir.display()))?;
+
+    let mut manifest = Vec::new();
+    for path in paths {
+        let relative = path
+            .strip_prefix(base_dir)
+            .with_context(|| format!("{} is outside the workspace", path.display()))?;
+        if path.exists() {
+            copy_recursively(path, &files_dir.join(relative))?;
+            manifest.push(format!("present {}", relative.display()));
+        } else {
+            manifest.push(format!("absent {}", relative.display()));
+        }
+    }
+
+    fs::write(backup_dir.join(MANIFEST_FILE), manifest.join("\n") + "\n")
+        .with_context(|| format!("Failed to write backup manifest in {}", backup_dir.display()))?;
+    log!("Backed up {} paths to {}", paths.len(), backup_dir.display());
+
+    // Drop the oldest backups so the directory doesn't grow without bound
+    let backups = list_backups(base_dir)?;
+    if backups.len() > MAX_BACKUPS {
+        for old in &backups[..backups.len() - MAX_BACKUPS] {
+            log!("Pruning old backup {}", old.display());
+            remove_path(old)?;
+        }
+    }
+
+    Ok(backup_dir)
+}
+
+// Restore the workspace to the state recorded by the most recent backup, then discard that backup
+pub fn undo(base_dir: &Path) -> Result<()> {
+    let Some(backup_dir) = list_backups(base_dir)?.pop() else {
+        bail!("No hyper-bindgen backups found in {}", base_dir.join(BACKUP_DIR).display());
+    };
+    log!("Restoring workspace from {}", backup_dir.display());
+
+    let manifest_path = backup_dir.join(MANIFEST_FILE);
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read backup manifest {}", manifest_path.display()))?;
+    let files_dir = backup_dir.join(FILES_DIR);
+
+    for line in manifest.lines().filter(|line| !line.trim().is_empty()) {
+        let Some((state, relative)) = line.split_once(' ') else {
+            bail!("Malformed line in backup manifest: {}", line);
+        };
+        let target = base_dir.join(relative);
+
+        remove_path(&target)?;
+        match state {
+            "present" => {
+                copy_recursively(&files_dir.join(relative), &target)?;
+                log!("  Restored {}", relative);
+            }
+            "absent" => log!("  Removed {}", relative),
+            _ => bail!("Unknown state '{}' in backup manifest", state),
+        }
+    }
+
+    remove_path(&backup_dir)?;
+    log!("Undo complete");
+    Ok(())
+}
@@ -1,75 +1,184 @@
 use anyhow::{Context, Result, bail};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use toml::Value;
+use toml_edit::{value, Array, Document, InlineTable, Item, Table};
 use walkdir::WalkDir;
 
-// Convert kebab-case to snake_case
-pub fn to_snake_case(s: &str) -> String {
-    s.replace('-', "_")
+use hyper_bindgen::case::{to_kebab_case, to_pascal_case, to_snake_case};
+
+use crate::changelog;
+use crate::config::{Config, Envelope, ProfileConfig, Stability, StubNames, StubStyle, TypeExtension, CONFIG_FILE};
+use crate::json_api;
+use crate::manifest;
+use crate::migration;
+use crate::output;
+use crate::paths;
+use crate::report::{self, SkipReason, Skipped};
+use crate::text;
+use crate::wit_source;
+
+// Rust keywords, which can't be used as plain identifiers
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+// Make an identifier legal in Rust: keywords get a trailing underscore (as wit-bindgen does)
+// and names starting with a digit a leading one
+fn legal_identifier(identifier: String) -> String {
+    if identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", identifier)
+    } else if RUST_KEYWORDS.contains(&identifier.as_str()) {
+        format!("{}_", identifier)
+    } else {
+        identifier
+    }
 }
 
-// Convert kebab-case to PascalCase
-pub fn to_pascal_case(s: &str) -> String {
-    let parts = s.split('-');
-    let mut result = String::new();
-    
-    for part in parts {
-        if !part.is_empty() {
-            let mut chars = part.chars();
-            if let Some(first_char) = chars.next() {
-                result.push(first_char.to_uppercase().next().unwrap());
-                result.extend(chars);
-            }
+// Convert a WIT field name to the snake_case Rust identifier used for it, e.g. `type` -> `type_`,
+// also when it is written escaped as `%type`
+pub fn to_rust_identifier(s: &str) -> String {
+    legal_identifier(to_snake_case(wit_source::unescape(s)))
+}
+
+// Names the bodies of stubs and their helpers use for their own values, which a parameter of the
+// same name would shadow: the target, the `send` and `call` functions and the locals of
+// retrying, caching and paginating stubs
+const STUB_LOCALS: &[&str] = &["target", "send", "call", "request", "key", "result", "attempt", "items"];
+
+// Rust identifier of a stub parameter: the WIT name made legal like a field name, with a trailing
+// underscore when it would shadow a name the stub's body uses
+fn param_identifier(name: &str) -> String {
+    let identifier = to_rust_identifier(name);
+    if STUB_LOCALS.contains(&identifier.as_str()) {
+        format!("{}_", identifier)
+    } else {
+        identifier
+    }
+}
+
+// Name of a function parameter among the fields of its signature: `target` and `returning` are
+// taken by the target and the response, and the request only carries the parameters' values
+fn param_field_name(name: String) -> String {
+    if name == "target" || name == "returning" {
+        format!("{}-arg", name)
+    } else {
+        name
+    }
+}
+
+// Names an interface module can't take at the root of the generated crate: the preamble imports
+// (`send`, `json`), the helper modules, the wit-bindgen modules and the crates generated code
+// refers to by path, which a glob-imported module of the same name would make ambiguous
+const RESERVED_MODULE_NAMES: &[&str] = &[
+    "send", "json", "prelude", "pipeline", "envelope", "bytes", "streams", "fake", "scope", "serial", "cache", "cli", "wit_sources", "hyperware", "exports",
+    "serde", "serde_json", "hyperware_app_common", "hyperware_process_lib", "process_macros",
+    "wit_bindgen", "std", "core", "alloc",
+];
+
+// Name of the module generated for an interface: its configured alias, or its snake_case name
+// with a `_interface` suffix when that is reserved
+fn module_name(config: &Config, interface_name: &str) -> String {
+    if let Some(alias) = config.interfaces.get(interface_name).and_then(|i| i.alias.as_ref()) {
+        return alias.clone();
+    }
+    let name = to_snake_case(interface_name);
+    if RESERVED_MODULE_NAMES.contains(&name.as_str()) {
+        format!("{}_interface", name)
+    } else {
+        legal_identifier(name)
+    }
+}
+
+// Modules the interface modules are nested in, from `module-path`
+fn module_path_segments(config: &Config) -> Result<Vec<&str>> {
+    let Some(path) = &config.module_path else {
+        return Ok(Vec::new());
+    };
+    let segments: Vec<&str> = path.split("::").collect();
+    for segment in &segments {
+        let valid = segment.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid || RUST_KEYWORDS.contains(segment) {
+            bail!("Invalid module-path `{}` in {}: it must be Rust identifiers separated by `::`", path, CONFIG_FILE);
         }
     }
-    
-    result
+    if RESERVED_MODULE_NAMES.contains(&segments[0]) {
+        bail!(
+            "Invalid module-path `{}` in {}: it can't start with one of {}",
+            path, CONFIG_FILE, RESERVED_MODULE_NAMES.join(", ")
+        );
+    }
+    Ok(segments)
 }
 
-// Find the world name in the world WIT file, prioritizing types-prefixed worlds
-fn find_world_name(api_dir: &Path) -> Result<String> {
-    let mut regular_world_name = None;
-    let mut types_world_name = None;
-    
-    // Look for world definition files
+// Path of the module generated for an interface from the crate root, e.g. `api::chat`
+pub fn module_path(config: &Config, interface_name: &str) -> String {
+    match &config.module_path {
+        Some(path) => format!("{}::{}", path, module_name(config, interface_name)),
+        None => module_name(config, interface_name),
+    }
+}
+
+// Worlds declared by the WIT files of the api directory
+fn declared_worlds(api_dir: &Path) -> Vec<wit_source::World> {
+    let mut worlds = Vec::new();
     for entry in WalkDir::new(api_dir)
         .max_depth(1)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(Result::ok)
     {
         let path = entry.path();
         
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "wit") {
-            if let Ok(content) = fs::read_to_string(path) {
-                if content.contains("world ") {
-                    println!("Analyzing world definition file: {}", path.display());
-                    
-                    // Extract the world name
-                    let lines: Vec<&str> = content.lines().collect();
-                    
-                    if let Some(world_line) = lines.iter().find(|line| line.trim().starts_with("world ")) {
-                        println!("World line: {}", world_line);
-                        
-                        if let Some(world_name) = world_line.trim().split_whitespace().nth(1) {
-                            let clean_name = world_name.trim_end_matches(" {");
-                            println!("Extracted world name: {}", clean_name);
-                            
-                            // Check if this is a types-prefixed world
-                            if clean_name.starts_with("types-") {
-                                types_world_name = Some(clean_name.to_string());
-                                println!("Found types world: {}", clean_name);
-                            } else {
-                                regular_world_name = Some(clean_name.to_string());
-                                println!("Found regular world: {}", clean_name);
-                            }
-                        }
-                    }
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "wit") {
+            if let Ok(content) = text::read_normalized(path) {
+                if wit_source::declares_world(&content) {
+                    log!("Analyzing world definition file: {}", path.display());
+                    worlds.extend(wit_source::worlds(&content));
                 }
             }
         }
     }
+    worlds
+}
+
+// Find the world name in the world WIT file, prioritizing types-prefixed worlds. Worlds other
+// worlds include are parts of a top-level world and never selected.
+fn find_world_name(api_dir: &Path) -> Result<String> {
+    let mut regular_world_name = None;
+    let mut types_world_name = None;
+    
+    let worlds = declared_worlds(api_dir);
+    let included: HashSet<&str> = worlds
+        .iter()
+        .flat_map(|world| world.includes.iter().map(String::as_str))
+        .collect();
+    
+    for world in &worlds {
+        let clean_name = world.name.clone();
+        log!("Extracted world name: {}", clean_name);
+        if included.contains(clean_name.as_str()) {
+            log!("Skipping world {}: other worlds include it", clean_name);
+            continue;
+        }
+        
+        // Check if this is a types-prefixed world
+        if clean_name.starts_with("types-") {
+            log!("Found types world: {}", clean_name);
+            types_world_name = Some(clean_name);
+        } else {
+            log!("Found regular world: {}", clean_name);
+            regular_world_name = Some(clean_name);
+        }
+    }
     
     // Prioritize types-prefixed world if found
     if let Some(types_name) = types_world_name {
@@ -82,13 +191,13 @@ fn find_world_name(api_dir: &Path) -> Result<String> {
         let types_name = format!("types-{}", regular_name);
         let types_file = api_dir.join(format!("{}.wit", types_name));
         
-        if types_file.exists() {
-            println!("Found types world from file: {}", types_name);
+        if types_file.exists() && !included.contains(types_name.as_str()) {
+            log!("Found types world from file: {}", types_name);
             return Ok(types_name);
         }
         
         // Fall back to regular world but print a warning
-        println!("Warning: No types- world found, using regular world: {}", regular_name);
+        log!("Warning: No types- world found, using regular world: {}", regular_name);
         return Ok(regular_name);
     }
     
@@ -96,8 +205,100 @@ fn find_world_name(api_dir: &Path) -> Result<String> {
     bail!("No world name found in any WIT file. Cannot generate caller-utils without a world name.")
 }
 
+// Worlds a crate is generated for: the selected ones, each replaced by its `types-` counterpart
+// when the api directory declares one, or the world `find_world_name` picks if none are selected
+fn select_worlds(api_dir: &Path, selected: &[String]) -> Result<Vec<String>> {
+    if selected.is_empty() {
+        return Ok(vec![find_world_name(api_dir)?]);
+    }
+    let declared: HashSet<String> = declared_worlds(api_dir).into_iter().map(|world| world.name).collect();
+    let mut worlds = Vec::new();
+    for name in selected {
+        if !declared.contains(name) {
+            bail!(
+                "World {} is selected with --world or generate-worlds, but no WIT file in {} declares it",
+                name,
+                api_dir.display()
+            );
+        }
+        let types_name = format!("types-{}", name);
+        let world = if declared.contains(&types_name) { types_name } else { name.clone() };
+        if !worlds.contains(&world) {
+            worlds.push(world);
+        }
+    }
+    Ok(worlds)
+}
+
+// World joining the worlds a crate is generated for, written next to the copied WIT files for
+// wit-bindgen, which generates bindings for a single world
+fn combined_world(name: &str, worlds: &[String]) -> String {
+    let includes: Vec<String> = worlds.iter().map(|world| format!("    include {};", wit_source::escape(world))).collect();
+    format!(
+        "// Generated by hyper-bindgen: combines the worlds {} into one crate\nworld {} {{\n{}\n}}\n",
+        worlds.join(", "),
+        name,
+        includes.join("\n")
+    )
+}
+
+// Type names `wit_type_to_rust` converts without a definition: primitives, generic types and
+// `address` from the standard interface
+const BUILTIN_TYPES: &[&str] = &[
+    "s8", "u8", "s16", "u16", "s32", "u32", "s64", "u64", "usize", "isize", "f32", "f64", "string", "str", "char", "bool",
+    "unit", "result", "address", "i8", "i16", "i32", "i64", "list", "option", "tuple", "map", "stream", "future", "own",
+    "borrow",
+];
+
+// WIT type names outside the WIT spec with a Rust counterpart: 128-bit integers, which protocols
+// declare in their WIT files as aliases like `type u128 = tuple<u64, u64>;`
+const EXTENDED_TYPES: &[(&str, &str)] = &[("u128", "u128"), ("s128", "i128")];
+
+// Rust type of a WIT type name outside the WIT spec, the configured `type-extensions` taking
+// precedence
+fn extended_type(wit_type: &str, extensions: &BTreeMap<String, TypeExtension>) -> Option<String> {
+    let name = wit_source::unescape(wit_type);
+    if let Some(extension) = extensions.get(name) {
+        return Some(extension.rust.clone());
+    }
+    EXTENDED_TYPES
+        .iter()
+        .find(|(extended, _)| *extended == name)
+        .map(|(_, rust)| rust.to_string())
+}
+
+// Default value of the Rust type of a `type-extensions` entry that sets one
+fn extension_default(rust_type: &str, extensions: &BTreeMap<String, TypeExtension>) -> Option<String> {
+    extensions
+        .values()
+        .find(|extension| extension.rust == rust_type)
+        .and_then(|extension| extension.default.clone())
+}
+
 // Convert WIT type to Rust type - IMPROVED with more Rust primitives
-fn wit_type_to_rust(wit_type: &str) -> String {
+fn wit_type_to_rust(wit_type: &str, extensions: &BTreeMap<String, TypeExtension>) -> String {
+    // Collection types with generics, converting each argument on its own so that nested ones
+    // keep their commas
+    if let Some((name, arguments)) = generic_arguments(wit_type) {
+        let rust_types: Vec<String> = arguments
+            .iter()
+            .map(|argument| if *argument == "_" { "()".to_string() } else { wit_type_to_rust(argument, extensions) })
+            .collect();
+        match (name, rust_types.as_slice()) {
+            ("list", [item]) => return format!("Vec<{}>", item),
+            ("option", [inner]) => return format!("Option<{}>", inner),
+            ("result", [ok]) => return format!("Result<{}, ()>", ok),
+            ("result", [ok, err]) => return format!("Result<{}, {}>", ok, err),
+            ("tuple", [single]) => return format!("({},)", single),
+            ("tuple", types) => return format!("({})", types.join(", ")),
+            ("map", [key, value]) => return format!("HashMap<{}, {}>", key, value),
+            // Fallback for a map without a key type
+            ("map", [value]) => return format!("HashMap<String, {}>", value),
+            // Only returned: signatures passing a stream get no stub
+            ("stream", [item]) => return format!("streams::ItemStream<{}>", item),
+            _ => {}
+        }
+    }
     match wit_type {
         // Integer types
         "s8" => "i8".to_string(),
@@ -120,6 +321,7 @@ fn wit_type_to_rust(wit_type: &str) -> String {
         "char" => "char".to_string(),
         "bool" => "bool".to_string(),
         "unit" => "()".to_string(),
+        "result" => "Result<(), ()>".to_string(),
         // Special types
         "address" => "WitAddress".to_string(),
         // Common primitives that might be written differently in WIT
@@ -127,55 +329,58 @@ fn wit_type_to_rust(wit_type: &str) -> String {
         "i16" => "i16".to_string(),
         "i32" => "i32".to_string(),
         "i64" => "i64".to_string(),
-        // Collection types with generics
-        t if t.starts_with("list<") => {
-            let inner_type = &t[5..t.len() - 1];
-            format!("Vec<{}>", wit_type_to_rust(inner_type))
-        },
-        t if t.starts_with("option<") => {
-            let inner_type = &t[7..t.len() - 1];
-            format!("Option<{}>", wit_type_to_rust(inner_type))
-        },
-        t if t.starts_with("result<") => {
-            let inner_part = &t[7..t.len() - 1];
-            if let Some(comma_pos) = inner_part.find(',') {
-                let ok_type = &inner_part[..comma_pos].trim();
-                let err_type = &inner_part[comma_pos + 1..].trim();
-                format!("Result<{}, {}>", wit_type_to_rust(ok_type), wit_type_to_rust(err_type))
-            } else {
-                format!("Result<{}, ()>", wit_type_to_rust(inner_part))
-            }
-        },
-        t if t.starts_with("tuple<") => {
-            let inner_types = &t[6..t.len() - 1];
-            let rust_types: Vec<String> = inner_types
-                .split(", ")
-                .map(|t| wit_type_to_rust(t))
-                .collect();
-            format!("({})", rust_types.join(", "))
-        },
-        // Handle map type if present
-        t if t.starts_with("map<") => {
-            let inner_part = &t[4..t.len() - 1];
-            if let Some(comma_pos) = inner_part.find(',') {
-                let key_type = &inner_part[..comma_pos].trim();
-                let value_type = &inner_part[comma_pos + 1..].trim();
-                format!("HashMap<{}, {}>", wit_type_to_rust(key_type), wit_type_to_rust(value_type))
-            } else {
-                // Fallback for malformed map type
-                format!("HashMap<String, {}>", wit_type_to_rust(inner_part))
-            }
-        },
-        // Custom types (in kebab-case) need to be converted to PascalCase
-        _ => to_pascal_case(wit_type).to_string(),
+        // Custom types (in kebab-case) need to be converted to PascalCase, `self` to `Self_`, and
+        // keywords are escaped, like `%result` for a type named `result`. Names of `type-extensions`
+        // and 128-bit integers map to their Rust type instead.
+        _ => extended_type(wit_type, extensions)
+            .unwrap_or_else(|| legal_identifier(to_pascal_case(wit_source::unescape(wit_type)))),
     }
 }
 
-// Generate default value for Rust type - IMPROVED with additional types
-fn generate_default_value(rust_type: &str) -> String {
+// Definitions of the parsed WIT types that default values are built from, keyed by Rust type name
+struct ExampleTypes {
+    // `(identifier, Rust type)` fields of each record
+    records: BTreeMap<String, Vec<(String, String)>>,
+    // Rust name of the first case of each enum, e.g. `Online` for `Status`
+    enum_cases: BTreeMap<String, String>,
+}
+
+// A record of a WIT file: its name and `(name, WIT type)` fields
+type WitRecord = (String, Vec<(String, String)>);
+
+// An enum of a WIT file: its name and cases
+type WitEnum = (String, Vec<String>);
+
+// Collect the records and enums of every parsed WIT file, keyed by Rust type name
+fn example_types<'a>(
+    records: impl IntoIterator<Item = &'a WitRecord>,
+    enums: impl IntoIterator<Item = &'a WitEnum>,
+    extensions: &BTreeMap<String, TypeExtension>,
+) -> ExampleTypes {
+    let records = records
+        .into_iter()
+        .map(|(name, fields)| {
+            let fields = fields
+                .iter()
+                .map(|(field, wit_type)| (to_rust_identifier(field), wit_type_to_rust(wit_type, extensions)))
+                .collect();
+            (to_pascal_case(name), fields)
+        })
+        .collect();
+    let enum_cases = enums
+        .into_iter()
+        .filter_map(|(name, cases)| Some((to_pascal_case(name), to_pascal_case(cases.first()?))))
+        .collect();
+    ExampleTypes { records, enum_cases }
+}
+
+// Generate default value for Rust type - IMPROVED with additional types.
+// Records whose definition was parsed get a struct literal with defaulted fields, and enums
+// their first case.
+fn generate_default_value(rust_type: &str, types: &ExampleTypes, extensions: &BTreeMap<String, TypeExtension>) -> String {
     match rust_type {
         // Integer types
-        "i8" | "u8" | "i16" | "u16" | "i32" | "u32" | "i64" | "u64" | "isize" | "usize" => "0".to_string(),
+        "i8" | "u8" | "i16" | "u16" | "i32" | "u32" | "i64" | "u64" | "i128" | "u128" | "isize" | "usize" => "0".to_string(),
         // Floating point types
         "f32" | "f64" => "0.0".to_string(),
         // String types
@@ -190,178 +395,1279 @@ fn generate_default_value(rust_type: &str) -> String {
         t if t.starts_with("Option<") => "None".to_string(),
         t if t.starts_with("Result<") => {
             // For Result, default to Ok with the default value of the success type
-            if let Some(success_type_end) = t.find(',') {
-                let success_type = &t[7..success_type_end];
-                format!("Ok({})", generate_default_value(success_type))
-            } else {
-                "Ok(())".to_string()
-            }
+            let inner = t.strip_prefix("Result<").and_then(|t| t.strip_suffix('>')).unwrap_or(t);
+            format!("Ok({})", generate_default_value(split_type_list(inner)[0], types, extensions))
         },
         t if t.starts_with("HashMap<") => "HashMap::new()".to_string(),
+        t if t.starts_with("streams::ItemStream<") => "streams::ItemStream::default()".to_string(),
         t if t.starts_with("(") => {
             // Generate default tuple with default values for each element
-            let inner_part = t.trim_start_matches('(').trim_end_matches(')');
-            let parts: Vec<_> = inner_part.split(", ").collect();
-            let default_values: Vec<_> = parts.iter()
-                .map(|part| generate_default_value(part))
+            let inner_part = t.strip_prefix('(').and_then(|t| t.strip_suffix(')')).unwrap_or(t);
+            let default_values: Vec<_> = split_type_list(inner_part)
+                .into_iter()
+                .filter(|part| !part.is_empty())
+                .map(|part| generate_default_value(part, types, extensions))
                 .collect();
-            format!("({})", default_values.join(", "))
+            match default_values.as_slice() {
+                [single] => format!("({},)", single),
+                _ => format!("({})", default_values.join(", ")),
+            }
+        },
+        // WIT types can't be recursive, so this terminates
+        t if types.records.contains_key(t) => {
+            let fields: Vec<String> = types.records[t]
+                .iter()
+                .map(|(name, field_type)| format!("{}: {}", name, generate_default_value(field_type, types, extensions)))
+                .collect();
+            format!("{} {{ {} }}", t, fields.join(", "))
         },
-        // For custom types, assume they implement Default
-        _ => format!("{}::default()", rust_type),
+        t if types.enum_cases.contains_key(t) => format!("{}::{}", t, types.enum_cases[t]),
+        // For other custom types, assume they implement Default unless their extension sets a value
+        _ => extension_default(rust_type, extensions).unwrap_or_else(|| format!("{}::default()", rust_type)),
     }
 }
 
 // Structure to represent a field in a WIT signature struct
-struct SignatureField {
+#[derive(Serialize)]
+pub struct SignatureField {
     name: String,
     wit_type: String,
 }
 
+// Location of a definition in a WIT file, relative to the workspace root
+#[derive(Serialize)]
+pub struct SourceLocation {
+    file: String,
+    line: usize,
+    column: usize,
+}
+
+impl SourceLocation {
+    // Position of the definition for diagnostics
+    fn position(&self) -> report::Position {
+        report::Position {
+            file: self.file.clone(),
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
 // Structure to represent a WIT signature struct
-struct SignatureStruct {
+#[derive(Serialize)]
+pub struct SignatureStruct {
+    #[serde(rename = "function")]
     function_name: String,
     attr_type: String,
     fields: Vec<SignatureField>,
+    source: SourceLocation,
+    // `/// @name value` annotations on the record, stored without the `@`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<String>,
+    // Lines of the record's or function's doc comment other than annotations
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    docs: Vec<String>,
+    // Name of the generated stub, see `stub_function_name`
+    #[serde(skip)]
+    stub_name: String,
 }
 
-// Find all interface imports in the world WIT file
-fn find_interfaces_in_world(api_dir: &Path) -> Result<Vec<String>> {
-    let mut interfaces = Vec::new();
+// Value of an annotation, or an empty string for annotations without a value
+fn find_annotation<'a>(annotations: &'a [String], name: &str) -> Option<&'a str> {
+    annotations.iter().find_map(|annotation| {
+        let (key, value) = annotation.split_once(' ').unwrap_or((annotation, ""));
+        (key == name).then_some(value.trim())
+    })
+}
+
+// Parse the `ttl=30s` argument of `@cacheable` into seconds; a bare number is in seconds
+fn parse_ttl(value: &str) -> Option<u64> {
+    let ttl = value.split_whitespace().find_map(|arg| arg.strip_prefix("ttl="))?;
+    let (number, unit) = match ttl.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => ttl.split_at(index),
+        None => (ttl, "s"),
+    };
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => return None,
+    };
+    number.parse::<u64>().ok().filter(|secs| *secs > 0).map(|secs| secs * multiplier)
+}
+
+// How a `list<u8>` parameter or return value declared with `@binary` is sent
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BinaryEncoding {
+    // A base64 string instead of an array of numbers
+    Base64,
+    // The blob of the message, leaving `null` in the request body
+    Blob,
+    // A file in the sender's VFS drive when above a size threshold, sent as a reference
+    Vfs,
+}
+
+// `(field, encoding)` pairs of a `@binary avatar=base64, thumbnail=blob` annotation, with no
+// encoding for unknown ones
+fn binary_encodings(value: &str) -> Vec<(&str, Option<BinaryEncoding>)> {
+    value
+        .split([',', ' '])
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (field, encoding) = item.split_once('=').unwrap_or((item, ""));
+            let encoding = match encoding {
+                "base64" => Some(BinaryEncoding::Base64),
+                "blob" => Some(BinaryEncoding::Blob),
+                "vfs" => Some(BinaryEncoding::Vfs),
+                _ => None,
+            };
+            (field, encoding)
+        })
+        .collect()
+}
+
+impl SignatureStruct {
+    fn annotation(&self, name: &str) -> Option<&str> {
+        find_annotation(&self.annotations, name)
+    }
+    
+    // The WIT doc comment as rustdoc lines, followed by an empty line when there is one
+    fn doc_comment(&self) -> String {
+        self.docs
+            .iter()
+            .map(|line| if line.is_empty() { "///\n".to_string() } else { format!("/// {}\n", line) })
+            .chain(std::iter::once("///\n".to_string()).filter(|_| !self.docs.is_empty()))
+            .collect()
+    }
+    
+    // Whether an HTTP endpoint is declared with `@auth required`
+    fn requires_auth(&self) -> bool {
+        self.annotation("auth") == Some("required")
+    }
+    
+    // Seconds the stub caches responses for, declared with `@cacheable ttl=<n>[s|m|h]`
+    fn cache_ttl_secs(&self) -> Option<u64> {
+        if self.attr_type == "http" {
+            return None;
+        }
+        parse_ttl(self.annotation("cacheable")?)
+    }
+    
+    // Whether the stub waits for its previous call to finish, declared with `@serialize-calls`
+    fn serializes_calls(&self) -> bool {
+        self.attr_type != "http" && self.annotation("serialize-calls").is_some()
+    }
+    
+    // Whether the stub resends a call that timed out or found the target offline, declared with
+    // `@idempotent`. `@mutating` wins over it, as resending could apply a change twice.
+    fn retries(&self) -> bool {
+        self.attr_type != "http" && self.annotation("idempotent").is_some() && self.annotation("mutating").is_none()
+    }
+    
+    // Encoding of a `list<u8>` parameter, or of the return value for `returning`, declared with
+    // `@binary <field>=base64|blob|vfs`. A message has one blob, which only carries a parameter.
+    fn binary_encoding(&self, field: &str) -> Option<BinaryEncoding> {
+        let value = self.annotation("binary")?;
+        let is_bytes = self.fields.iter().any(|f| f.name == field && f.wit_type == "list<u8>");
+        let encoding = binary_encodings(value)
+            .into_iter()
+            .find(|(name, _)| *name == field)
+            .and_then(|(_, encoding)| encoding)
+            .filter(|_| is_bytes)?;
+        if encoding == BinaryEncoding::Blob && self.blob_param() != Some(field) {
+            return None;
+        }
+        if encoding == BinaryEncoding::Vfs && field == "returning" {
+            return None;
+        }
+        Some(encoding)
+    }
+    
+    // The parameter sent as the message blob: the first `list<u8>` one declared `=blob`
+    fn blob_param(&self) -> Option<&str> {
+        let encodings = binary_encodings(self.annotation("binary")?);
+        self.fields
+            .iter()
+            .filter(|field| field.name != "target" && field.name != "returning" && field.wit_type == "list<u8>")
+            .map(|field| field.name.as_str())
+            .find(|name| encodings.contains(&(*name, Some(BinaryEncoding::Blob))))
+    }
+    
+    // Whether the stub returns a `stream<T>`, handed out through the `streams` module
+    fn returns_stream(&self) -> bool {
+        self.fields
+            .iter()
+            .any(|field| field.name == "returning" && streamed_item(&field.wit_type).is_some())
+    }
+    
+    // Whether the stub needs the `bytes` module
+    fn uses_binary(&self) -> bool {
+        self.fields.iter().any(|field| self.binary_encoding(&field.name).is_some())
+    }
+    
+    // Whether the stub hands a parameter off through the VFS, which only local stubs do
+    fn hands_off(&self) -> bool {
+        self.attr_type == "local"
+            && self.fields.iter().any(|field| self.binary_encoding(&field.name) == Some(BinaryEncoding::Vfs))
+    }
+    
+    // Stub names the function had under the names declared with `@renamed-from old-name`
+    fn former_stub_names(&self) -> Vec<String> {
+        let Some(value) = self.annotation("renamed-from").filter(|_| self.attr_type != "http") else {
+            return Vec::new();
+        };
+        let current = to_snake_case(&self.function_name);
+        value
+            .split([',', ' '])
+            .filter(|name| !name.is_empty())
+            .map(|name| self.stub_name.replacen(&current, &to_snake_case(name), 1))
+            .collect()
+    }
+    
+    // HTTP method an endpoint maps to: GET when it is `@idempotent`, POST when it is `@mutating`
+    fn http_method(&self) -> Option<&'static str> {
+        if self.annotation("mutating").is_some() {
+            Some("POST")
+        } else if self.annotation("idempotent").is_some() {
+            Some("GET")
+        } else {
+            None
+        }
+    }
+}
+
+// An interface parsed from a WIT file or JSON API description in the api directory
+#[derive(Serialize)]
+pub struct ParsedInterface {
+    name: String,
+    file: String,
+    // `/// @name value` annotations on the interface, stored without the `@`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<String>,
+    types: Vec<String>,
+    // Resources, whose handles only mean something in the process owning them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    resources: Vec<String>,
+    // Types holding resource handles, streams or futures, directly or through other types
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unsendable_types: Vec<String>,
+    // Names of the types each type definition refers to
+    #[serde(skip)]
+    references: BTreeMap<String, Vec<String>>,
+    // Types taken from other interfaces with `use`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    imports: Vec<wit_source::Import>,
+    signatures: Vec<SignatureStruct>,
+    // Type definitions of an interface described in JSON, which has no WIT for wit-bindgen to
+    // generate them from
+    #[serde(skip)]
+    json_types: Option<Vec<json_api::TypeDescription>>,
+}
+
+impl ParsedInterface {
+    // Canonical documentation URL declared with `@docs`
+    fn docs_url(&self) -> Option<&str> {
+        find_annotation(&self.annotations, "docs").filter(|url| !url.is_empty())
+    }
+    
+    // Timeout of the interface's stubs declared with `@timeout <seconds>`
+    fn timeout_secs(&self) -> Option<u64> {
+        let value = find_annotation(&self.annotations, "timeout")?;
+        match value.parse() {
+            Ok(secs) => Some(secs),
+            Err(_) => {
+                log!("Warning: {}: invalid @timeout `{}` on interface {}, expected a number of seconds", self.file, value, self.name);
+                None
+            }
+        }
+    }
+}
+
+// The parsed model of an api directory, as exposed by `hyper-bindgen parse`
+#[derive(Serialize)]
+pub struct ParsedApi {
+    world: Option<String>,
+    // Package the WIT files declare, e.g. `hyperware:my-app@0.1.0`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+    interfaces: Vec<ParsedInterface>,
+    skipped: Vec<Skipped>,
+    diagnostics: Vec<report::Diagnostic>,
+}
+
+// Parse the api directory into the model used for code generation
+pub fn parse_api(api_dir: &Path, base_dir: &Path) -> ParsedApi {
+    let world = match find_world_name(api_dir) {
+        Ok(world) => Some(world),
+        Err(e) => {
+            log!("Warning: {}", e);
+            None
+        }
+    };
+    
+    let package = match find_package(api_dir) {
+        Ok(package) => package.map(|package| package.to_string()),
+        Err(e) => {
+            log!("Warning: {}", e);
+            None
+        }
+    };
     
-    // Find world definition files
+    let interfaces = parse_interfaces(api_dir, base_dir);
+    ParsedApi {
+        world,
+        package,
+        interfaces,
+        skipped: report::take_skipped(),
+        diagnostics: report::take_diagnostics(),
+    }
+}
+
+impl std::fmt::Display for ParsedApi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "world: {}", self.world.as_deref().unwrap_or("<none>"))?;
+        if let Some(package) = &self.package {
+            writeln!(f, "package: {}", package)?;
+        }
+        for interface in &self.interfaces {
+            writeln!(f, "interface {} ({})", interface.name, interface.file)?;
+            for annotation in &interface.annotations {
+                writeln!(f, "  @{}", annotation)?;
+            }
+            for type_name in &interface.types {
+                writeln!(f, "  type {}", type_name)?;
+            }
+            for resource in &interface.resources {
+                writeln!(f, "  resource {}", resource)?;
+            }
+            for signature in &interface.signatures {
+                let fields: Vec<String> = signature
+                    .fields
+                    .iter()
+                    .map(|field| format!("{}: {}", field.name, field.wit_type))
+                    .collect();
+                writeln!(
+                    f,
+                    "  {} ({}) {{ {} }} at {}",
+                    signature.function_name,
+                    signature.attr_type,
+                    fields.join(", "),
+                    signature.source
+                )?;
+                for annotation in &signature.annotations {
+                    writeln!(f, "    @{}", annotation)?;
+                }
+            }
+        }
+        for skipped in &self.skipped {
+            writeln!(f, "skipped {}", skipped)?;
+        }
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+// Find the package the WIT files of the api directory declare, which they must agree on
+fn find_package(api_dir: &Path) -> Result<Option<wit_source::Package>> {
+    let mut declared: Option<(wit_source::Package, PathBuf)> = None;
     for entry in WalkDir::new(api_dir)
         .max_depth(1)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(Result::ok)
     {
         let path = entry.path();
-        
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "wit") {
-            if let Ok(content) = fs::read_to_string(path) {
-                if content.contains("world ") {
-                    println!("Analyzing world definition file: {}", path.display());
-                    
-                    // Extract import statements
-                    for line in content.lines() {
-                        let line = line.trim();
-                        if line.starts_with("import ") && line.ends_with(";") {
-                            let interface = line
-                                .trim_start_matches("import ")
-                                .trim_end_matches(";")
-                                .trim();
-                            
-                            interfaces.push(interface.to_string());
-                            println!("  Found interface import: {}", interface);
-                        }
-                    }
-                }
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "wit")) {
+            continue;
+        }
+        let content = text::read_normalized(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let Some(package) = wit_source::package_declaration(&content) else {
+            continue;
+        };
+        match &declared {
+            Some((first, first_path)) if *first != package => bail!(
+                "{} declares package {} but {} declares package {}; the WIT files of the api directory must be in one package",
+                first_path.display(),
+                first,
+                path.display(),
+                package
+            ),
+            Some(_) => {}
+            None => {
+                log!("Found package {} in {}", package, path.display());
+                declared = Some((package, path.to_path_buf()));
             }
         }
     }
-    
-    Ok(interfaces)
+    Ok(declared.map(|(package, _)| package))
 }
 
-// Parse WIT file to extract function signatures and type definitions
-fn parse_wit_file(file_path: &Path) -> Result<(Vec<SignatureStruct>, Vec<String>)> {
-    println!("Parsing WIT file: {}", file_path.display());
-    
-    let content = fs::read_to_string(file_path)
-        .with_context(|| format!("Failed to read WIT file: {}", file_path.display()))?;
-    
-    let mut signatures = Vec::new();
-    let mut type_names = Vec::new();
-    
-    // Simple parser for WIT files to extract record definitions and types
-    let lines: Vec<_> = content.lines().collect();
-    let mut i = 0;
+// Render a path relative to the workspace root with forward slashes, for use in generated comments
+fn display_relative_path(path: &Path, base_dir: &Path) -> String {
+    paths::to_slash(path.strip_prefix(base_dir).unwrap_or(path))
+}
+
+// Find the interfaces the world imports, directly or through the worlds of the api directory it
+// includes. Included worlds from elsewhere, like `process-v1`, are left to wit-bindgen.
+fn find_interfaces_in_world(api_dir: &Path, world_name: &str) -> Result<Vec<String>> {
+    let worlds: BTreeMap<String, wit_source::World> =
+        declared_worlds(api_dir).into_iter().map(|world| (world.name.clone(), world)).collect();
     
-    while i < lines.len() {
-        let line = lines[i].trim();
-        
-        // Look for record definitions that aren't signature structs
-        if line.starts_with("record ") && !line.contains("-signature-") {
-            let record_name = line.trim_start_matches("record ").trim_end_matches(" {").trim();
-            println!("  Found type: record {}", record_name);
-            type_names.push(record_name.to_string());
-        }
-        // Look for variant definitions (enums)
-        else if line.starts_with("variant ") {
-            let variant_name = line.trim_start_matches("variant ").trim_end_matches(" {").trim();
-            println!("  Found type: variant {}", variant_name);
-            type_names.push(variant_name.to_string());
+    let mut interfaces = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![world_name.to_string()];
+    while let Some(name) = pending.pop() {
+        if !visited.insert(name.clone()) {
+            continue;
         }
-        // Look for signature record definitions
-        else if line.starts_with("record ") && line.contains("-signature-") {
-            let record_name = line.trim_start_matches("record ").trim_end_matches(" {").trim();
-            println!("  Found record: {}", record_name);
-            
-            // Extract function name and attribute type
-            let parts: Vec<_> = record_name.split("-signature-").collect();
-            if parts.len() != 2 {
-                println!("    Unexpected record name format");
-                i += 1;
-                continue;
+        let Some(world) = worlds.get(&name) else {
+            log!("  World {} is not defined in the api directory", name);
+            continue;
+        };
+        for interface in &world.imports {
+            if !interfaces.contains(interface) {
+                interfaces.push(interface.clone());
+                log!("  Found interface import: {} (world {})", interface, name);
             }
-            
-            let function_name = parts[0].to_string();
-            let attr_type = parts[1].to_string();
-            
-            // Parse fields
-            let mut fields = Vec::new();
-            i += 1;
-            
-            while i < lines.len() && !lines[i].trim().starts_with("}") {
-                let field_line = lines[i].trim();
-                
-                // Skip comments and empty lines
-                if field_line.starts_with("//") || field_line.is_empty() {
-                    i += 1;
-                    continue;
-                }
-                
-                // Parse field definition
-                let field_parts: Vec<_> = field_line.split(':').collect();
-                if field_parts.len() == 2 {
-                    let field_name = field_parts[0].trim().to_string();
-                    let field_type = field_parts[1].trim().trim_end_matches(',').to_string();
-                    
-                    println!("    Field: {} -> {}", field_name, field_type);
-                    fields.push(SignatureField {
-                        name: field_name,
-                        wit_type: field_type,
-                    });
+        }
+        // Visit includes in order
+        pending.extend(world.includes.iter().rev().cloned());
+    }
+    
+    Ok(interfaces)
+}
+
+// Find all WIT interface files in the api directory, excluding world definitions
+pub fn find_interface_files(api_dir: &Path) -> Vec<PathBuf> {
+    let mut wit_files = Vec::new();
+    for entry in WalkDir::new(api_dir)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "wit") {
+            // Exclude world definition files
+            if let Ok(content) = text::read_normalized(path) {
+                if !wit_source::declares_world(&content) {
+                    wit_files.push(path.to_path_buf());
                 }
-                
-                i += 1;
             }
-            
-            signatures.push(SignatureStruct {
-                function_name,
-                attr_type,
-                fields,
-            });
         }
-        
-        i += 1;
     }
     
-    println!("Extracted {} signature structs and {} type definitions from {}", 
-             signatures.len(), type_names.len(), file_path.display());
-    Ok((signatures, type_names))
+    log!("Found {} WIT interface files", wit_files.len());
+    wit_files
 }
 
-// Generate a Rust async function from a signature struct
-fn generate_async_function(signature: &SignatureStruct) -> String {
-    // Convert function name from kebab-case to snake_case
-    let snake_function_name = to_snake_case(&signature.function_name);
+// Interfaces declared in the api directory with the file declaring them, leaving out files that
+// don't parse
+fn declared_interfaces(api_dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut declared = Vec::new();
+    for wit_file in find_interface_files(api_dir) {
+        let Ok(content) = text::read_normalized(&wit_file) else {
+            continue;
+        };
+        if let Ok(interfaces) = wit_source::parse(&paths::to_slash(&wit_file), &content) {
+            declared.extend(interfaces.into_iter().map(|interface| (interface.name, wit_file.clone())));
+        }
+    }
+    for description in json_api::find_descriptions(api_dir) {
+        if let Ok(interface) = json_api::parse(&description) {
+            declared.push((interface.name, description));
+        }
+    }
+    declared
+}
+
+// Names of the interfaces declared in the api directory, leaving out files that don't parse
+pub fn interface_names(api_dir: &Path) -> Vec<String> {
+    declared_interfaces(api_dir).into_iter().map(|(name, _)| name).collect()
+}
+
+// Resolve the interfaces the worlds import to the files declaring them. An interface declared by
+// several files, e.g. `chat.wit` and a `chat-v2.wit` copy still declaring `interface chat`, would
+// generate conflicting modules and WIT, so it is an error whether or not a world imports it.
+fn resolve_imports(api_dir: &Path, base_dir: &Path, imports: &[String]) -> Result<()> {
+    let mut files = BTreeMap::<String, Vec<PathBuf>>::new();
+    for (name, file) in declared_interfaces(api_dir) {
+        files.entry(name).or_default().push(file);
+    }
+
+    let ambiguous: Vec<String> = files
+        .iter()
+        .filter(|(_, declaring)| declaring.len() > 1)
+        .map(|(name, declaring)| {
+            let declaring: Vec<String> = declaring.iter().map(|file| display_relative_path(file, base_dir)).collect();
+            let imported = if imports.contains(name) { ", which the world imports," } else { "" };
+            format!("interface {}{} is declared by {}", name, imported, declaring.join(" and "))
+        })
+        .collect();
+    if !ambiguous.is_empty() {
+        bail!(
+            "The api directory declares interfaces in several files, so imports can't be resolved to one of them:\n  {}\n\
+             Rename the interface in all but one of the files, or remove the stale copies",
+            ambiguous.join("\n  ")
+        );
+    }
+
+    for import in imports {
+        match files.get(import) {
+            Some(declaring) => log!("  Resolved import {} to {}", import, display_relative_path(&declaring[0], base_dir)),
+            None => log!("  Import {} is not declared in the api directory", import),
+        }
+    }
+    Ok(())
+}
+
+// Parse all interface files in the api directory, skipping (and reporting) files that fail to parse
+pub fn parse_interfaces(api_dir: &Path, base_dir: &Path) -> Vec<ParsedInterface> {
+    let mut interfaces = Vec::new();
     
+    for wit_file in find_interface_files(api_dir) {
+        match parse_wit_file(&wit_file, base_dir) {
+            Ok(file_interfaces) => interfaces.extend(file_interfaces),
+            Err(e) => {
+                report::skip(SkipReason::UnreadableFile, display_relative_path(&wit_file, base_dir), format!("{:#}", e));
+            }
+        }
+    }
+    
+    for description in json_api::find_descriptions(api_dir) {
+        log!("Parsing JSON API description: {}", description.display());
+        let file = display_relative_path(&description, base_dir);
+        match json_api::parse(&description) {
+            Ok(interface) => interfaces.push(described_interface(interface, file)),
+            Err(e) => report::skip(SkipReason::UnreadableFile, file, format!("{:#}", e)),
+        }
+    }
+    
+    check_untranslatable_types(&interfaces);
+    skip_unsendable_signatures(&mut interfaces);
+    interfaces
+}
+
+// WIT types of values owned by a component's async runtime
+const ASYNC_TYPES: [&str; 2] = ["stream", "future"];
+
+// Constructs of newer WIT revisions hyper-bindgen parses but has no Rust mapping for yet, with why
+const UNTRANSLATABLE_TYPES: &[(&str, &str)] = &[(
+    "error-context",
+    "error contexts are handles into the component model's async runtime and have no JSON form",
+)];
+
+// Report the signatures using a construct hyper-bindgen can't translate yet as errors, instead of
+// generating a reference to a Rust type that doesn't exist
+fn check_untranslatable_types(interfaces: &[ParsedInterface]) {
+    for interface in interfaces {
+        for signature in &interface.signatures {
+            for field in &signature.fields {
+                let names = wit_source::type_names(&field.wit_type);
+                let Some((name, reason)) = UNTRANSLATABLE_TYPES.iter().find(|(name, _)| names.iter().any(|used| used == name)) else {
+                    continue;
+                };
+                report::error(
+                    signature.source.position(),
+                    format!(
+                        "`{}: {}` of {}.{} uses `{}`, which this version of hyper-bindgen can't translate yet: {}",
+                        field.name, field.wit_type, interface.name, signature.function_name, name, reason
+                    ),
+                );
+            }
+        }
+    }
+}
+
+// Resource handles only mean something in the process owning the resource, and streams and
+// futures in the component that was handed them, so none of them can be sent in a JSON request:
+// signatures passing one, directly or inside another type, get no stubs. The types holding them
+// are recorded, as the bindings can't derive serde traits for them.
+fn skip_unsendable_signatures(interfaces: &mut [ParsedInterface]) {
+    // `interface.type` of a name as an interface sees it, following `use` imports; `stream` and
+    // `future` are keywords, the same in every interface
+    let key = |interface: &ParsedInterface, name: &str| {
+        if ASYNC_TYPES.contains(&name) {
+            return name.to_string();
+        }
+        interface
+            .imports
+            .iter()
+            .find(|import| import.local == name)
+            .map_or_else(|| format!("{}.{}", interface.name, name), |import| format!("{}.{}", import.interface, import.name))
+    };
+    // The given keys and those of the types holding them
+    let holding = |mut keys: BTreeSet<String>| loop {
+        let holding: Vec<String> = interfaces
+            .iter()
+            .flat_map(|interface| {
+                interface
+                    .references
+                    .iter()
+                    .filter(|(_, names)| names.iter().any(|name| keys.contains(&key(interface, name))))
+                    .map(|(type_name, _)| format!("{}.{}", interface.name, type_name))
+            })
+            .filter(|type_key| !keys.contains(type_key))
+            .collect();
+        if holding.is_empty() {
+            return keys;
+        }
+        keys.extend(holding);
+    };
+    let handles = holding(
+        interfaces
+            .iter()
+            .flat_map(|interface| interface.resources.iter().map(|resource| format!("{}.{}", interface.name, resource)))
+            .collect(),
+    );
+    let async_values = holding(ASYNC_TYPES.iter().map(|name| name.to_string()).collect());
+    
+    for interface in interfaces.iter_mut() {
+        interface.unsendable_types = interface
+            .references
+            .keys()
+            .filter(|type_name| {
+                let type_key = format!("{}.{}", interface.name, type_name);
+                !interface.resources.contains(type_name) && (handles.contains(&type_key) || async_values.contains(&type_key))
+            })
+            .cloned()
+            .collect();
+        let signatures = std::mem::take(&mut interface.signatures);
+        for signature in signatures {
+            // A returned stream is sent as the list of its items, so only the items must be sendable
+            let names: Vec<String> = signature
+                .fields
+                .iter()
+                .flat_map(|field| match streamed_item(&field.wit_type).filter(|_| field.name == "returning") {
+                    Some(item) => wit_source::type_names(item),
+                    None => wit_source::type_names(&field.wit_type),
+                })
+                .collect();
+            let item = format!("function {}.{} ({})", interface.name, signature.function_name, signature.attr_type);
+            if let Some(handle) = names.iter().find(|name| handles.contains(&key(interface, name))) {
+                report::skip(
+                    SkipReason::ResourceHandle,
+                    item,
+                    format!("`{}` holds a resource handle, which can't be sent in a request", handle),
+                );
+            } else if let Some(name) = names.iter().find(|name| async_values.contains(&key(interface, name))) {
+                let passed = if ASYNC_TYPES.contains(&name.as_str()) {
+                    format!("a {}", name)
+                } else {
+                    format!("`{}`, which holds a stream or future,", name)
+                };
+                report::skip(
+                    SkipReason::StreamOrFuture,
+                    item,
+                    format!(
+                        "{}: the signature passes {} but requests only carry serializable values; \
+                         pass the data itself instead, paging through it if it is large",
+                        signature.source, passed
+                    ),
+                );
+            } else {
+                interface.signatures.push(signature);
+            }
+        }
+    }
+}
+
+// References to custom types that no interface of the api directory defines, which would become
+// names of Rust types that don't exist, as `<file>:<line>: <what> refers to unknown type <name>`.
+// Types imported from interfaces outside the api directory, like `standard`, are left to
+// wit-bindgen.
+fn unknown_types(interfaces: &[ParsedInterface]) -> Vec<String> {
+    let defined: BTreeMap<&str, BTreeSet<&str>> = interfaces
+        .iter()
+        .map(|interface| {
+            let names = interface
+                .types
+                .iter()
+                .chain(&interface.resources)
+                .chain(interface.references.keys())
+                .map(String::as_str)
+                .collect();
+            (interface.name.as_str(), names)
+        })
+        .collect();
+    let mut unknown = Vec::new();
+    for interface in interfaces {
+        let imported: BTreeSet<&str> = interface.imports.iter().map(|import| import.local.as_str()).collect();
+        for import in &interface.imports {
+            if defined.get(import.interface.as_str()).is_some_and(|names| !names.contains(import.name.as_str())) {
+                unknown.push(format!(
+                    "{}: `use {}.{{{}}}` refers to unknown type {}.{}",
+                    interface.file, import.interface, import.name, import.interface, import.name
+                ));
+            }
+        }
+        // `(location, what refers to it, WIT type)` of every type reference
+        let mut references: Vec<(String, String, &str)> = Vec::new();
+        for signature in &interface.signatures {
+            for field in &signature.fields {
+                references.push((
+                    signature.source.to_string(),
+                    format!("field `{}` of {}.{}", field.name, interface.name, signature.function_name),
+                    &field.wit_type,
+                ));
+            }
+        }
+        for definition in interface.json_types.iter().flatten() {
+            let members: Vec<(&str, &str)> = match &definition.kind {
+                json_api::TypeKind::Record(members) | json_api::TypeKind::Variant(members) => members
+                    .iter()
+                    .filter_map(|member| Some((member.name.as_str(), member.wit_type.as_deref()?)))
+                    .collect(),
+                json_api::TypeKind::Alias(wit_type) => vec![("alias", wit_type.as_str())],
+                json_api::TypeKind::Enum(_) => Vec::new(),
+            };
+            for (member, wit_type) in members {
+                references.push((
+                    interface.file.clone(),
+                    format!("`{}` of type {}.{}", member, interface.name, definition.name),
+                    wit_type,
+                ));
+            }
+        }
+        for (location, what, wit_type) in references {
+            for name in wit_source::type_names(wit_type) {
+                let known = BUILTIN_TYPES.contains(&name.as_str())
+                    || imported.contains(name.as_str())
+                    || defined[interface.name.as_str()].contains(name.as_str());
+                if !known {
+                    unknown.push(format!("{}: {} refers to unknown type {}", location, what, name));
+                }
+            }
+        }
+    }
+    unknown
+}
+
+// The signature records of an interface described in JSON, as if they had been parsed from WIT
+fn described_interface(interface: json_api::Interface, file: String) -> ParsedInterface {
+    let signatures = interface
+        .functions
+        .into_iter()
+        .map(|function| {
+            let signature = SignatureStruct {
+                stub_name: legal_identifier(format!("{}_{}_rpc", to_snake_case(&function.name), function.kind)),
+                function_name: function.name,
+                attr_type: function.kind,
+                fields: {
+                    let last = function.fields.len() - 1;
+                    function
+                        .fields
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, (name, wit_type))| SignatureField {
+                            // The first and last fields are the target and the response
+                            name: if index == 0 || index == last { name } else { param_field_name(name) },
+                            wit_type,
+                        })
+                        .collect()
+                },
+                source: SourceLocation {
+                    file: file.clone(),
+                    line: function.line,
+                    column: function.column,
+                },
+                annotations: function.annotations,
+                docs: Vec::new(),
+            };
+            warn_about_annotations(&signature);
+            signature
+        })
+        .collect();
+    ParsedInterface {
+        name: interface.name,
+        file,
+        annotations: interface.annotations,
+        types: interface.types.iter().map(|definition| definition.name.clone()).collect(),
+        resources: Vec::new(),
+        unsendable_types: Vec::new(),
+        references: BTreeMap::new(),
+        imports: Vec::new(),
+        signatures,
+        json_types: Some(interface.types),
+    }
+}
+
+// Parse WIT file to extract function signatures, type definitions, imported types and the
+// interface annotations of each interface it declares, whatever the file is named
+fn parse_wit_file(file_path: &Path, base_dir: &Path) -> Result<Vec<ParsedInterface>> {
+    log!("Parsing WIT file: {}", file_path.display());
+    
+    let source_file = display_relative_path(file_path, base_dir);
+    
+    let content = text::read_normalized(file_path)
+        .with_context(|| format!("Failed to read WIT file: {}", file_path.display()))?;
+    
+    // `/// @name value` doc comment lines, without the `@`
+    let annotations = |docs: &[String]| -> Vec<String> {
+        docs.iter()
+            .filter_map(|line| line.strip_prefix('@'))
+            .map(|annotation| annotation.trim().to_string())
+            .collect()
+    };
+    // The other doc comment lines, without the blank lines around them
+    let descriptions = |docs: &[String]| -> Vec<String> {
+        let lines: Vec<String> = docs.iter().filter(|line| !line.starts_with('@')).cloned().collect();
+        let start = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+        let end = lines.iter().rposition(|line| !line.is_empty()).map_or(start, |end| end + 1);
+        lines[start..end].to_vec()
+    };
+    
+    let mut interfaces = Vec::new();
+    for interface in wit_source::parse(&source_file, &content)? {
+        log!("  Found interface: {}", interface.name);
+        let mut signatures = Vec::new();
+        let mut type_names = Vec::new();
+        let mut resources = Vec::new();
+        let mut references = BTreeMap::new();
+        for import in &interface.imports {
+            log!("  Found import: {}.{} as {}", import.interface, import.name, import.local);
+        }
+        
+        for definition in interface.definitions {
+            let record_name = definition.name.as_str();
+            
+            // Look for record, variant, enum and alias definitions that aren't signature structs
+            if !record_name.contains("-signature-") || definition.keyword != "record" {
+                references.insert(record_name.to_string(), definition.references.clone());
+                if definition.keyword == "resource" {
+                    log!("  Found resource: {}", record_name);
+                    resources.push(record_name.to_string());
+                }
+                if matches!(definition.keyword, "record" | "variant" | "enum" | "type") {
+                    log!("  Found type: {} {}", definition.keyword, record_name);
+                    type_names.push(record_name.to_string());
+                }
+                continue;
+            }
+            log!("  Found record: {}", record_name);
+            
+            // Extract function name and attribute type
+            let source = SourceLocation {
+                file: source_file.clone(),
+                line: definition.line,
+                column: definition.column,
+            };
+            let parts: Vec<_> = record_name.split("-signature-").collect();
+            if parts.len() != 2 || parts[0].is_empty() {
+                report::error(
+                    source.position(),
+                    format!("signature record `{}` is not named <function>-signature-<kind>", record_name),
+                );
+                continue;
+            }
+            if !FUNCTION_KINDS.contains(&parts[1]) {
+                report::error(
+                    source.position(),
+                    format!(
+                        "signature record `{}` has kind `{}`, expected one of {}",
+                        record_name,
+                        parts[1],
+                        FUNCTION_KINDS.join(", ")
+                    ),
+                );
+                continue;
+            }
+            
+            let function_name = parts[0].to_string();
+            let attr_type = parts[1].to_string();
+            
+            let mut fields = Vec::new();
+            for (field_name, field_type) in &definition.fields {
+                log!("    Field: {} -> {}", field_name, field_type);
+                fields.push(SignatureField {
+                    name: field_name.clone(),
+                    wit_type: field_type.clone(),
+                });
+            }
+            
+            let signature = SignatureStruct {
+                stub_name: legal_identifier(format!("{}_{}_rpc", to_snake_case(&function_name), attr_type)),
+                function_name,
+                attr_type,
+                fields,
+                source,
+                annotations: annotations(&definition.docs),
+                docs: descriptions(&definition.docs),
+            };
+            
+            warn_about_annotations(&signature);
+            signatures.push(signature);
+        }
+        
+        // Functions declared with `func` become signatures of the kinds their annotations name
+        for function in interface.functions {
+            log!("  Found function: {}", function.name);
+            let function_annotations = annotations(&function.docs);
+            let kinds: Vec<&str> = function_annotations
+                .iter()
+                .map(String::as_str)
+                .filter(|annotation| FUNCTION_KINDS.contains(annotation))
+                .collect();
+            if kinds.is_empty() {
+                report::skip(
+                    SkipReason::UnmarkedFunction,
+                    format!("{}:{}", source_file, function.line),
+                    format!("`{}` has none of the @remote, @local or @http annotations", function.name),
+                );
+                continue;
+            }
+            let other_annotations: Vec<String> = function_annotations
+                .iter()
+                .filter(|annotation| !FUNCTION_KINDS.contains(&annotation.as_str()))
+                .cloned()
+                .collect();
+            for attr_type in kinds {
+                let target = if attr_type == "http" { "string" } else { "address" };
+                let fields = std::iter::once(("target".to_string(), target.to_string()))
+                    .chain(function.params.iter().map(|(name, wit_type)| (param_field_name(name.clone()), wit_type.clone())))
+                    .chain(std::iter::once((
+                        "returning".to_string(),
+                        function.result.clone().unwrap_or_else(|| "unit".to_string()),
+                    )))
+                    .map(|(name, wit_type)| SignatureField { name, wit_type })
+                    .collect();
+                let signature = SignatureStruct {
+                    stub_name: legal_identifier(format!("{}_{}_rpc", to_snake_case(&function.name), attr_type)),
+                    function_name: function.name.clone(),
+                    attr_type: attr_type.to_string(),
+                    fields,
+                    source: SourceLocation {
+                        file: source_file.clone(),
+                        line: function.line,
+                        column: function.column,
+                    },
+                    annotations: other_annotations.clone(),
+                    docs: descriptions(&function.docs),
+                };
+                warn_about_annotations(&signature);
+                signatures.push(signature);
+            }
+        }
+        
+        log!("Extracted {} signature structs and {} type definitions from interface {} in {}", 
+                 signatures.len(), type_names.len(), interface.name, file_path.display());
+        interfaces.push(ParsedInterface {
+            name: interface.name,
+            file: source_file.clone(),
+            annotations: annotations(&interface.docs),
+            types: type_names,
+            resources,
+            unsendable_types: Vec::new(),
+            references,
+            imports: interface.imports,
+            signatures,
+            json_types: None,
+        });
+    }
+    Ok(interfaces)
+}
+
+// Annotations marking the kinds of stubs a `func` declaration gets, like the attributes of
+// hyperprocess methods
+const FUNCTION_KINDS: [&str; 3] = ["remote", "local", "http"];
+
+// Warn about annotations of a signature that don't apply to it or have invalid values
+fn warn_about_annotations(signature: &SignatureStruct) {
+    let record_name = format!("{}-signature-{}", signature.function_name, signature.attr_type);
+    // Authentication requirements only apply to HTTP endpoints
+    match signature.annotation("auth") {
+        None => {}
+        Some(_) if signature.attr_type != "http" => {
+            report::warning(signature.source.position(), format!("@auth only applies to http endpoints, ignoring it on {}", record_name));
+        }
+        Some("required" | "optional") => {}
+        Some(value) => {
+            report::warning(signature.source.position(), format!("unknown @auth value `{}`, expected `required` or `optional`", value));
+        }
+    }
+    if let Some(value) = signature.annotation("cacheable") {
+        if signature.attr_type == "http" {
+            report::warning(signature.source.position(), format!("@cacheable has no effect on the commented-out stub of http endpoint {}", record_name));
+        } else if parse_ttl(value).is_none() {
+            report::warning(signature.source.position(), format!("invalid @cacheable `{}` on {}, expected `ttl=<n>` with an optional s, m or h unit; not caching", value, record_name));
+        }
+    }
+    if signature.annotation("idempotent").is_some() && signature.annotation("mutating").is_some() {
+        report::warning(signature.source.position(), format!("{} is annotated both @idempotent and @mutating; treating it as mutating", record_name));
+    }
+    if let Some(value) = signature.annotation("binary") {
+        let mut blobs = 0;
+        for (name, encoding) in binary_encodings(value) {
+            let field = signature.fields.iter().find(|field| field.name == name && field.name != "target");
+            match (field, encoding) {
+                (_, None) => report::warning(signature.source.position(), format!("unknown @binary encoding `{}` on {}, expected `<field>=base64`, `<field>=blob` or `<field>=vfs`", name, record_name)),
+                (None, _) => report::warning(signature.source.position(), format!("@binary names `{}`, which isn't a parameter of {} or `returning`", name, record_name)),
+                (Some(field), _) if field.wit_type != "list<u8>" => report::warning(signature.source.position(), format!("@binary only applies to list<u8>, ignoring it on {} of {}", name, record_name)),
+                (Some(_), Some(BinaryEncoding::Blob)) if name == "returning" => report::warning(signature.source.position(), format!("responses can't be sent as a blob, sending the response of {} as is", record_name)),
+                (Some(_), Some(BinaryEncoding::Blob)) => {
+                    blobs += 1;
+                    if blobs > 1 {
+                        report::warning(signature.source.position(), format!("a message has one blob, sending `{}` of {} as is", name, record_name));
+                    }
+                }
+                (Some(_), Some(BinaryEncoding::Vfs)) if name == "returning" => report::warning(signature.source.position(), format!("responses can't be handed off through the VFS, sending the response of {} as is", record_name)),
+                (Some(_), Some(BinaryEncoding::Base64 | BinaryEncoding::Vfs)) => {}
+            }
+        }
+    }
+    match signature.annotation("renamed-from") {
+        Some(_) if signature.attr_type == "http" => {
+            report::warning(signature.source.position(), format!("@renamed-from has no effect on the commented-out stub of http endpoint {}", record_name));
+        }
+        Some("") => report::warning(signature.source.position(), format!("@renamed-from on {} names no former function", record_name)),
+        _ => {}
+    }
+    if signature.attr_type == "http" && signature.annotation("serialize-calls").is_some() {
+        report::warning(signature.source.position(), format!("@serialize-calls has no effect on the commented-out stub of http endpoint {}", record_name));
+    }
+    if let Some(value) = signature.annotation("returns") {
+        let returning = signature.fields.iter().find(|field| field.name == "returning");
+        let elements = returning
+            .and_then(|field| field.wit_type.strip_prefix("tuple<")?.strip_suffix('>'))
+            .map(|list| split_type_list(list).len());
+        let names = value.split([',', ' ']).filter(|name| !name.is_empty()).count();
+        match elements {
+            None => report::warning(signature.source.position(), format!("@returns only applies to functions returning a tuple, ignoring it on {}", record_name)),
+            Some(count) if count != names => report::warning(signature.source.position(), format!("@returns names {} fields but {} returns a tuple of {}; naming them after their types", names, record_name, count)),
+            Some(_) => {}
+        }
+    }
+}
+
+// Name of the generated stub, e.g. `send_message_remote_rpc`, or `send_message` with
+// `stub-names = "short"`
+fn stub_function_name(signature: &SignatureStruct) -> String {
+    signature.stub_name.clone()
+}
+
+// Shorten the stub names of the interfaces to the snake_case function name, followed by the kind
+// for functions with stubs of several kinds under `stub-names = "short"`, where `"bare"` fails
+// instead. Names that would shadow an item the stubs use keep their full name.
+fn apply_stub_names(interfaces: &mut [ParsedInterface], naming: StubNames) -> Result<()> {
+    if naming == StubNames::Full {
+        return Ok(());
+    }
+    for interface in interfaces {
+        // Kinds of each function; an HTTP endpoint counts too, as its stub is only commented out
+        let mut kinds = BTreeMap::<String, Vec<&str>>::new();
+        for signature in &interface.signatures {
+            kinds.entry(signature.function_name.clone()).or_default().push(&signature.attr_type);
+        }
+        let conflicts: BTreeMap<String, Vec<String>> = kinds
+            .into_iter()
+            .filter(|(_, kinds)| kinds.len() > 1)
+            .map(|(function, kinds)| (function, kinds.into_iter().map(str::to_string).collect()))
+            .collect();
+        if naming == StubNames::Bare {
+            if let Some((function, kinds)) = conflicts.iter().next() {
+                bail!(
+                    "Function {}.{} has {} stubs, which `stub-names = \"bare\"` would all name `{}`; use \"short\" to add the kind to their names",
+                    interface.name, function, kinds.join(" and "), to_rust_identifier(function)
+                );
+            }
+        }
+        for signature in &mut interface.signatures {
+            let mut name = to_snake_case(&signature.function_name);
+            if conflicts.contains_key(&signature.function_name) {
+                name = format!("{}_{}", name, signature.attr_type);
+            }
+            let name = legal_identifier(name);
+            if name == "unwrap_request" || RESERVED_MODULE_NAMES.contains(&name.as_str()) {
+                log!(
+                    "Warning: the short stub name `{}` of {}.{} would shadow a generated item, keeping `{}`",
+                    name, interface.name, signature.function_name, signature.stub_name
+                );
+                continue;
+            }
+            signature.stub_name = name;
+        }
+        
+        // A kind suffix can still produce the name of another function, like `get_remote`
+        let mut names = HashSet::new();
+        for signature in &interface.signatures {
+            if !names.insert(&signature.stub_name) {
+                bail!(
+                    "Two functions of interface {} would get the stub name `{}`; use `stub-names = \"full\"`",
+                    interface.name, signature.stub_name
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// Top-level types of a comma-separated WIT or Rust type list, e.g. `u32, list<tuple<a, b>>` or
+// `(u8, u8), String`
+fn split_type_list(list: &str) -> Vec<&str> {
+    let mut types = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                types.push(list[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    types.push(list[start..].trim());
+    types
+}
+
+// Name and type arguments of a generic type, e.g. `result` and `list<u8>`, `string` for
+// `result<list<u8>, string>`
+fn generic_arguments(wit_type: &str) -> Option<(&str, Vec<&str>)> {
+    let (name, rest) = wit_type.split_once('<')?;
+    let arguments = rest.strip_suffix('>')?;
+    if arguments.trim().is_empty() {
+        return Some((name.trim(), Vec::new()));
+    }
+    Some((name.trim(), split_type_list(arguments)))
+}
+
+// Item type of a `stream<T>`
+fn streamed_item(wit_type: &str) -> Option<&str> {
+    match generic_arguments(wit_type)? {
+        ("stream", arguments) => match arguments.as_slice() {
+            [item] => Some(item),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Field name for a value of a WIT type: named types give their name, lists the plural of their
+// item's name and anything else `value`
+fn element_name(wit_type: &str, extensions: &BTreeMap<String, TypeExtension>) -> String {
+    if let Some(item) = wit_type.strip_prefix("list<").and_then(|t| t.strip_suffix('>')) {
+        let name = element_name(item, extensions);
+        return if name.ends_with(['s', 'x']) || name.ends_with("sh") || name.ends_with("ch") {
+            format!("{}es", name)
+        } else if name.ends_with('y') && !name.ends_with(['a', 'e', 'o', 'u']) {
+            format!("{}ies", &name[..name.len() - 1])
+        } else {
+            format!("{}s", name)
+        };
+    }
+    if let Some(inner) = wit_type.strip_prefix("option<").and_then(|t| t.strip_suffix('>')) {
+        return element_name(inner, extensions);
+    }
+    let is_named = !wit_type.contains('<') && wit_type_to_rust(wit_type, extensions) == to_pascal_case(wit_source::unescape(wit_type));
+    if is_named {
+        to_rust_identifier(wit_type)
+    } else {
+        "value".to_string()
+    }
+}
+
+// The named struct a stub returns in place of the `tuple<..>` of its signature, as the struct
+// name and its (field, Rust type) pairs. Fields are named by `@returns a, b` or after their
+// types. Paginated stubs keep their tuple, which the `_all` helper takes apart.
+fn response_struct(signature: &SignatureStruct, interface_types: &[String], extensions: &BTreeMap<String, TypeExtension>) -> Option<(String, Vec<(String, String)>)> {
+    if signature.attr_type == "http" || paginated_item_type(signature, extensions).is_some() {
+        return None;
+    }
+    let returning = signature.fields.iter().find(|field| field.name == "returning")?;
+    let elements = split_type_list(returning.wit_type.strip_prefix("tuple<")?.strip_suffix('>')?);
+    // A WIT type of the same name would be shadowed by the struct
+    let name = format!("{}-response", signature.function_name);
+    if interface_types.contains(&name) {
+        return None;
+    }
+
+    let mut names: Vec<String> = signature
+        .annotation("returns")
+        .map(|value| value.split([',', ' ']).filter(|name| !name.is_empty()).map(to_rust_identifier).collect())
+        .unwrap_or_default();
+    if names.len() != elements.len() {
+        names = elements.iter().map(|wit_type| element_name(wit_type, extensions)).collect();
+        // Repeated names are told apart by their position
+        let repeated: Vec<String> = names
+            .iter()
+            .filter(|name| names.iter().filter(|other| other == name).count() > 1)
+            .cloned()
+            .collect();
+        for (index, name) in names.iter_mut().enumerate() {
+            if repeated.contains(name) {
+                name.push_str(&(index + 1).to_string());
+            }
+        }
+    }
+    let fields = names
+        .into_iter()
+        .zip(elements)
+        .map(|(name, wit_type)| (name, wit_type_to_rust(wit_type, extensions)))
+        .collect();
+    Some((to_pascal_case(&name), fields))
+}
+
+// Generate the struct a stub returns instead of a tuple, converting from and to the tuple the
+// process sends
+fn generate_response_struct(signature: &SignatureStruct, name: &str, fields: &[(String, String)]) -> String {
+    let names: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+    let types: Vec<&str> = fields.iter().map(|(_, rust_type)| rust_type.as_str()).collect();
+    let tuple = format!("({})", types.join(", "));
+    let definitions: Vec<String> = fields
+        .iter()
+        .map(|(name, rust_type)| format!("    pub {}: {},", name, rust_type))
+        .collect();
+    format!(
+        "/// Response of `{function}`, the tuple it returns with named fields\n\
+         #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]\n\
+         #[serde(from = \"{tuple}\", into = \"{tuple}\")]\n\
+         pub struct {name} {{\n{definitions}\n}}\n\n\
+         impl From<{tuple}> for {name} {{\n\
+         \x20   fn from(({names}): {tuple}) -> Self {{\n\
+         \x20       Self {{ {names} }}\n\
+         \x20   }}\n\
+         }}\n\n\
+         impl From<{name}> for {tuple} {{\n\
+         \x20   fn from(response: {name}) -> Self {{\n\
+         \x20       ({fields})\n\
+         \x20   }}\n\
+         }}",
+        function = signature.function_name,
+        tuple = tuple,
+        name = name,
+        definitions = definitions.join("\n"),
+        names = names.join(", "),
+        fields = names.iter().map(|name| format!("response.{}", name)).collect::<Vec<_>>().join(", "),
+    )
+}
+
+// Generate a Rust async function from a signature struct
+#[allow(clippy::too_many_arguments)]
+fn generate_async_function(
+    signature: &SignatureStruct,
+    interface_name: &str,
+    envelope: Envelope,
+    style: StubStyle,
+    types: &ExampleTypes,
+    self_stub: bool,
+    interface_types: &[String],
+    extensions: &BTreeMap<String, TypeExtension>,
+) -> String {
     // Get pascal case version for the JSON request format
     let pascal_function_name = to_pascal_case(&signature.function_name);
     
     // Function full name with attribute type
-    let full_function_name = format!("{}_{}_rpc", snake_function_name, signature.attr_type);
+    let full_function_name = stub_function_name(signature);
     
     // Extract parameters and return type
     let mut params = Vec::new();
@@ -370,8 +1676,8 @@ fn generate_async_function(signature: &SignatureStruct) -> String {
     let mut target_param = "";
     
     for field in &signature.fields {
-        let field_name_snake = to_snake_case(&field.name);
-        let rust_type = wit_type_to_rust(&field.wit_type);
+        let field_name_snake = param_identifier(&field.name);
+        let rust_type = wit_type_to_rust(&field.wit_type, extensions);
         
         if field.name == "target" {
             if field.wit_type == "string" {
@@ -381,213 +1687,2791 @@ fn generate_async_function(signature: &SignatureStruct) -> String {
                 target_param = "&Address";
             }
         } else if field.name == "returning" {
-            return_type = rust_type;
+            return_type = stub_return_type(signature, &field.wit_type, interface_types, extensions);
+        } else {
+            params.push(format!("{}: {}", field_name_snake, rust_type));
+            param_names.push(field_name_snake);
+        }
+    }
+    
+    // First parameter is always target
+    let all_params = if target_param.is_empty() {
+        params.join(", ")
+    } else {
+        format!("target: {}{}", target_param, if params.is_empty() { "" } else { ", " }) + &params.join(", ")
+    };
+    
+    // Wrap the return type in SendResult
+    let wrapped_return_type = format!("SendResult<{}>", return_type);
+    
+    // For HTTP endpoints, generate commented-out implementation
+    if signature.attr_type == "http" {
+        let default_value = generate_default_value(&return_type, types, extensions);
+        
+        // Add underscore prefix to all parameters for HTTP stubs
+        let all_params_with_underscore = if target_param.is_empty() {
+            params.iter()
+                .map(|param| {
+                    let parts: Vec<&str> = param.split(':').collect();
+                    if parts.len() == 2 {
+                        format!("_{}: {}", parts[0], parts[1])
+                    } else {
+                        format!("_{}", param)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        } else {
+            let target_with_underscore = format!("_target: {}", target_param);
+            if params.is_empty() {
+                target_with_underscore
+            } else {
+                let params_with_underscore = params.iter()
+                    .map(|param| {
+                        let parts: Vec<&str> = param.split(':').collect();
+                        if parts.len() == 2 {
+                            format!("_{}: {}", parts[0], parts[1])
+                        } else {
+                            format!("_{}", param)
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("{}, {}", target_with_underscore, params_with_underscore)
+            }
+        };
+        
+        // Endpoints declared with `@auth required` take the caller's token
+        let (auth_doc, all_params_with_underscore) = if signature.requires_auth() {
+            (
+                "/// Requires authentication (`@auth required`): pass the caller's token\n",
+                format!("{}, _token: &str", all_params_with_underscore).trim_start_matches(", ").to_string(),
+            )
+        } else {
+            ("", all_params_with_underscore)
+        };
+        
+        // `@idempotent` and `@mutating` say which method the endpoint is served with
+        let method = match signature.http_method() {
+            Some("GET") => ", mapped to GET (`@idempotent`)",
+            Some(_) => ", mapped to POST (`@mutating`)",
+            None => "",
+        };
+        
+        return format!(
+            "// source: {}\n{}/// Generated stub for `{}` {} RPC call\n/// HTTP endpoint{} - uncomment to implement\n{}// pub async fn {}({}) -> {} {{\n//     // TODO: Implement HTTP endpoint\n//     SendResult::Success({})\n// }}",
+            signature.source,
+            signature.doc_comment(),
+            signature.function_name,
+            signature.attr_type,
+            method,
+            auth_doc,
+            full_function_name,
+            all_params_with_underscore,
+            wrapped_return_type,
+            default_value
+        );
+    }
+    
+    let json_params = request_expression(&pascal_function_name, &request_arguments(signature), interface_name, envelope);
+    
+    // Generate function with implementation using send
+    let mut header = format!(
+        "// source: {}\n{}/// Generated stub for `{}` {} RPC call",
+        signature.source, signature.doc_comment(), signature.function_name, signature.attr_type
+    );
+    if let Some(value) = signature.annotation("binary").filter(|_| signature.uses_binary()) {
+        header.push_str(&format!("\n///\n/// Binary fields are sent as declared with `@binary {}`", value));
+    }
+    let mut send = send_expression(signature, &return_type);
+    // Statements sending the request, the last one evaluating to the result
+    let mut statements = Vec::new();
+    
+    // Idempotent stubs resend calls that didn't get through; mutating ones are sent exactly once
+    let retries = signature.retries();
+    if retries {
+        header.push_str(
+            "\n///\n/// Idempotent (`@idempotent`): sent up to `IDEMPOTENT_ATTEMPTS` times while the call \
+             times out or the target is offline",
+        );
+        send = format!(
+            "loop {{\n    match {} {{\n        SendResult::Timeout | SendResult::Offline if attempt < IDEMPOTENT_ATTEMPTS => attempt += 1,\n        \
+             result => break result,\n    }}\n}}",
+            send
+        );
+    } else if signature.annotation("mutating").is_some() {
+        header.push_str("\n///\n/// Mutating (`@mutating`): sent once and never retried automatically");
+    }
+    
+    // Cacheable stubs answer from the cache while the response of an identical call is fresh
+    let cache_ttl_secs = signature.cache_ttl_secs();
+    let cache = format!("{}_CACHE", full_function_name.to_uppercase());
+    if let Some(ttl_secs) = cache_ttl_secs {
+        header = format!(
+            "thread_local! {{\n    /// Responses of `{}`, e.g. for `cache::clear`\n    \
+             pub static {}: cache::ResponseCache = Default::default();\n}}\n\n{}\n///\n\
+             /// Responses are cached for {} seconds per target and arguments (`@cacheable`)",
+            full_function_name, cache, header, ttl_secs
+        );
+        statements.push("let key = cache::key(target, &request);".to_string());
+        statements.push(format!(
+            "if let Some(value) = cache::get(&{}, &key) {{\n    return SendResult::Success(value);\n}}",
+            cache
+        ));
+    }
+    
+    // Serialized stubs hold the function's lock until the response arrives
+    if signature.serializes_calls() {
+        let lock = format!("{}_CALLS", full_function_name.to_uppercase());
+        header = format!(
+            "thread_local! {{\n    static {}: std::sync::Arc<serial::CallLock> = Default::default();\n}}\n\n{}\n///\n\
+             /// Calls are serialized (`@serialize-calls`): each waits until the previous one got its response",
+            lock, header
+        );
+        statements.push(format!("let _guard = serial::acquire(&{}).await;", lock));
+    }
+    
+    if retries {
+        statements.push("let mut attempt = 1;".to_string());
+    }
+    
+    if let Some(ttl_secs) = cache_ttl_secs {
+        statements.push(format!("let result = {};", send));
+        statements.push(format!(
+            "if let SendResult::Success(value) = &result {{\n    cache::put(&{}, key, value, {});\n}}",
+            cache, ttl_secs
+        ));
+        statements.push("result".to_string());
+    } else {
+        statements.push(send);
+    }
+    
+    // Short bodies of future-returning stubs stay on one line
+    let block = |indent: &str| {
+        format!("\n{}{}", indent, statements.join("\n").replace('\n', &format!("\n{}", indent)))
+    };
+    let future_body = if cache_ttl_secs.is_some() || retries {
+        format!("{}\n    ", block("        "))
+    } else {
+        format!(" {} ", statements.join(" "))
+    };
+    let stub = match style {
+        StubStyle::AsyncFn | StubStyle::MaybeAsync => format!(
+            "{}\n{}pub async fn {}({}) -> {} {{\n    let request = {};{}\n}}",
+            header,
+            maybe_async_attribute(style),
+            full_function_name,
+            all_params,
+            wrapped_return_type,
+            json_params,
+            block("    ")
+        ),
+        // The request is built before the future so it only borrows `target`
+        StubStyle::ImplFuture => format!(
+            "{}\npub fn {}({}) -> impl std::future::Future<Output = {}> + Send + '_ {{\n    let request = {};\n    async move {{{}}}\n}}",
+            header, full_function_name, all_params, wrapped_return_type, json_params, future_body
+        ),
+        StubStyle::Boxed => format!(
+            "{}\npub fn {}({}) -> StubFuture<'_, {}> {{\n    let request = {};\n    Box::pin(async move {{{}}})\n}}",
+            header, full_function_name, all_params, return_type, json_params, future_body
+        ),
+    };
+    if !self_stub || !has_self_stub(signature) {
+        return stub;
+    }
+    
+    // The self-call variant owns the address it sends to, so its future borrows nothing
+    let self_header = format!(
+        "/// Call `{}` ({}) on this process, e.g. to re-enter it from a timer or internal queue",
+        signature.function_name, signature.attr_type
+    );
+    let mut args = vec!["&hyperware_process_lib::our()".to_string()];
+    args.extend(param_names.iter().cloned());
+    let call = format!("{}({})", full_function_name, args.join(", "));
+    let self_params = params.join(", ");
+    let self_stub = match style {
+        StubStyle::AsyncFn | StubStyle::MaybeAsync => format!(
+            "{}\n{}pub async fn {}_self({}) -> {} {{\n    {}.await\n}}",
+            self_header, maybe_async_attribute(style), full_function_name, self_params, wrapped_return_type, call
+        ),
+        StubStyle::ImplFuture => format!(
+            "{}\npub fn {}_self({}) -> impl std::future::Future<Output = {}> + Send {{\n    async move {{ {}.await }}\n}}",
+            self_header, full_function_name, self_params, wrapped_return_type, call
+        ),
+        StubStyle::Boxed => format!(
+            "{}\npub fn {}_self({}) -> StubFuture<'static, {}> {{\n    Box::pin(async move {{ {}.await }})\n}}",
+            self_header, full_function_name, self_params, return_type, call
+        ),
+    };
+    format!("{}\n\n{}", stub, self_stub)
+}
+
+// Generate deprecated stubs under the former names of a function (`@renamed-from`), forwarding to
+// its stub so callers get a deprecation window instead of a compile error
+fn generate_renamed_stubs(signature: &SignatureStruct, former_names: &[String], style: StubStyle, interface_types: &[String], extensions: &BTreeMap<String, TypeExtension>) -> String {
+    let stub_name = stub_function_name(signature);
+    let mut params = vec!["target: &Address".to_string()];
+    let mut args = vec!["target".to_string()];
+    let mut return_type = "()".to_string();
+    for field in &signature.fields {
+        if field.name == "target" {
+            continue;
+        }
+        if field.name == "returning" {
+            return_type = stub_return_type(signature, &field.wit_type, interface_types, extensions);
+            continue;
+        }
+        let name = param_identifier(&field.name);
+        params.push(format!("{}: {}", name, wit_type_to_rust(&field.wit_type, extensions)));
+        args.push(name);
+    }
+    let (params, call) = (params.join(", "), format!("{}({})", stub_name, args.join(", ")));
+    
+    let stubs: Vec<String> = former_names
+        .iter()
+        .map(|former_name| {
+            let header = format!(
+                "/// Former name of `{}` (`@renamed-from`)
+#[deprecated(note = \"renamed to `{}`\")]",
+                stub_name, stub_name
+            );
+            match style {
+                StubStyle::AsyncFn | StubStyle::MaybeAsync => format!(
+                    "{}\n{}pub async fn {}({}) -> SendResult<{}> {{\n    {}.await\n}}",
+                    header, maybe_async_attribute(style), former_name, params, return_type, call
+                ),
+                StubStyle::ImplFuture => format!(
+                    "{}\npub fn {}({}) -> impl std::future::Future<Output = SendResult<{}>> + Send + '_ {{\n    {}\n}}",
+                    header, former_name, params, return_type, call
+                ),
+                StubStyle::Boxed => format!(
+                    "{}\npub fn {}({}) -> StubFuture<'_, {}> {{\n    {}\n}}",
+                    header, former_name, params, return_type, call
+                ),
+            }
+        })
+        .collect();
+    stubs.join("\n\n")
+}
+
+// Attribute making an async function blocking under the `sync` feature of maybe-async crates
+fn maybe_async_attribute(style: StubStyle) -> &'static str {
+    if style == StubStyle::MaybeAsync {
+        "#[maybe_async::maybe_async]\n"
+    } else {
+        ""
+    }
+}
+
+// The `json!` expression building the request of a function from its parameters
+fn request_expression(pascal_function_name: &str, param_names: &[String], interface_name: &str, envelope: Envelope) -> String {
+    // Format JSON parameters the way serde represents them
+    let request_body = if param_names.is_empty() {
+        // No parameters case: `{"Ping": {}}`
+        format!("{{\"{}\" : {{}}}}", pascal_function_name)
+    } else {
+        // Parameters are always sent as a tuple, which serde encodes as an array, including
+        // the single parameter case: `{"SetStatus": [status]}`, `{"SendMessage": [message, channel]}`
+        let trailing_comma = if param_names.len() == 1 { "," } else { "" };
+        format!("{{\"{}\": ({}{})}}", 
+                pascal_function_name, 
+                param_names.join(", "),
+                trailing_comma)
+    };
+    
+    // Wrap the body in the interface name or the traced envelope, as configured for the world
+    match envelope {
+        Envelope::Plain => format!("json!({})", request_body),
+        Envelope::Namespaced => format!("json!({{\"{}\": {}}})", interface_name, request_body),
+        Envelope::Traced => format!("crate::envelope::wrap_for(target, json!({}))", request_body),
+    }
+}
+
+// Arguments of the request tuple, with the `list<u8>` parameters declared with `@binary` sent as
+// base64, left to the message blob or handed off through the VFS
+fn request_arguments(signature: &SignatureStruct) -> Vec<String> {
+    signature
+        .fields
+        .iter()
+        .filter(|field| field.name != "target" && field.name != "returning")
+        .map(|field| {
+            let name = param_identifier(&field.name);
+            match signature.binary_encoding(&field.name) {
+                Some(BinaryEncoding::Base64) => format!("bytes::Base64({})", name),
+                Some(BinaryEncoding::Blob) => "()".to_string(),
+                // Files of the node's VFS can't be read by processes on other nodes
+                Some(BinaryEncoding::Vfs) if signature.attr_type == "local" => format!("bytes::handoff({})", name),
+                Some(BinaryEncoding::Vfs) => format!("bytes::Payload::Inline(bytes::Base64({}))", name),
+                None => name,
+            }
+        })
+        .collect()
+}
+
+// Expression sending the request of a stub and evaluating to its `SendResult`. A parameter sent
+// as the blob needs the process lib's request rather than `send`.
+fn send_expression(signature: &SignatureStruct, return_type: &str) -> String {
+    match signature.blob_param() {
+        Some(blob) => format!(
+            "bytes::send_with_blob::<{}>(&request, &{}, target, DEFAULT_TIMEOUT_SECS)",
+            return_type,
+            param_identifier(blob)
+        ),
+        None => format!("send::<{}>(&request, target, DEFAULT_TIMEOUT_SECS).await", return_type),
+    }
+}
+
+// Rust type a stub returns for its `returning` field: a response struct for named tuple fields,
+// `bytes::Base64` for bytes declared with `@binary returning=base64`
+fn stub_return_type(signature: &SignatureStruct, returning: &str, interface_types: &[String], extensions: &BTreeMap<String, TypeExtension>) -> String {
+    if signature.binary_encoding("returning") == Some(BinaryEncoding::Base64) {
+        return "bytes::Base64".to_string();
+    }
+    match response_struct(signature, interface_types, extensions) {
+        Some((name, _)) => name,
+        None => wit_type_to_rust(returning, extensions),
+    }
+}
+
+// Whether a signature gets a `_self` variant: stubs sending to a process `Address`
+fn has_self_stub(signature: &SignatureStruct) -> bool {
+    signature.attr_type != "http"
+        && signature
+            .fields
+            .iter()
+            .any(|field| field.name == "target" && field.wit_type != "string")
+}
+
+// Rust item type of a paginated signature: one taking `cursor: option<C>` and `limit` fields
+// and returning `tuple<list<T>, option<C>>`, the items of the page and the cursor of the next one
+fn paginated_item_type(signature: &SignatureStruct, extensions: &BTreeMap<String, TypeExtension>) -> Option<String> {
+    if signature.attr_type == "http" {
+        return None;
+    }
+    let field_type = |name: &str| {
+        signature
+            .fields
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| field.wit_type.as_str())
+    };
+    let cursor_type = field_type("cursor")?;
+    field_type("limit")?;
+    let returning = field_type("returning")?;
+
+    // Split bracket-aware, so item types that are generic themselves keep their commas
+    let (name, arguments) = generic_arguments(returning)?;
+    let ("tuple", [items, next_cursor]) = (name, arguments.as_slice()) else {
+        return None;
+    };
+    let (name, arguments) = generic_arguments(items)?;
+    let ("list", [item_type]) = (name, arguments.as_slice()) else {
+        return None;
+    };
+    (cursor_type.starts_with("option<") && *next_cursor == cursor_type)
+        .then(|| wit_type_to_rust(item_type, extensions))
+}
+
+// Generate a `*_all` helper that follows the cursor of a paginated stub until it is exhausted
+fn generate_paginated_helper(signature: &SignatureStruct, item_type: &str, style: StubStyle, extensions: &BTreeMap<String, TypeExtension>) -> String {
+    let stub_name = stub_function_name(signature);
+    let mut params = vec!["target: &Address".to_string()];
+    let mut args = vec!["target".to_string()];
+    for field in &signature.fields {
+        if field.name == "target" || field.name == "returning" {
+            continue;
+        }
+        let name = param_identifier(&field.name);
+        if field.name == "cursor" {
+            args.push(name);
+            continue;
+        }
+        let rust_type = wit_type_to_rust(&field.wit_type, extensions);
+        // Arguments are passed again for every page
+        let is_copy = matches!(
+            rust_type.as_str(),
+            "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128" | "f32" | "f64" | "bool" | "char"
+        );
+        args.push(if is_copy { name.clone() } else { format!("{}.clone()", name) });
+        params.push(format!("{}: {}", name, rust_type));
+    }
+
+    format!(
+        "// source: {source}\n\
+         /// Fetch every page of `{function}` by following its cursor, concatenating the items.\n\
+         /// Fails if the process is still returning cursors after `pipeline::MAX_PAGES` pages.\n\
+         {attribute}pub async fn {stub}_all({params}) -> Result<Vec<{item}>, pipeline::CallFailed> {{\n\
+         \x20   let mut items = Vec::new();\n\
+         \x20   let mut cursor = None;\n\
+         \x20   for _ in 0..pipeline::MAX_PAGES {{\n\
+         \x20       let (page, next) = pipeline::step(\"{function}\", {stub}({args}).await)?;\n\
+         \x20       items.extend(page);\n\
+         \x20       match next {{\n\
+         \x20           Some(next) => cursor = Some(next),\n\
+         \x20           None => return Ok(items),\n\
+         \x20       }}\n\
+         \x20   }}\n\
+         \x20   Err(pipeline::CallFailed {{\n\
+         \x20       step: \"{function}\",\n\
+         \x20       reason: format!(\"still paging after {{}} pages\", pipeline::MAX_PAGES),\n\
+         \x20   }})\n\
+         }}",
+        source = signature.source,
+        function = signature.function_name,
+        stub = stub_name,
+        params = params.join(", "),
+        item = item_type,
+        args = args.join(", "),
+        attribute = maybe_async_attribute(style),
+    )
+}
+
+// Generate the enum of requests an interface accepts, with conversions from HTTP and message
+// bodies, so processes exposing the same operations over both transports share the decoding
+fn generate_request_enum(interface: &ParsedInterface, envelope: Envelope, extensions: &BTreeMap<String, TypeExtension>) -> String {
+    let mut variants = Vec::new();
+    let mut seen = HashSet::new();
+    for signature in &interface.signatures {
+        // The same function is usually exposed with several attribute types
+        if !seen.insert(signature.function_name.as_str()) {
+            continue;
+        }
+        let params: Vec<String> = signature
+            .fields
+            .iter()
+            .filter(|field| field.name != "target" && field.name != "returning")
+            .map(|field| match signature.binary_encoding(&field.name) {
+                Some(BinaryEncoding::Base64) => "crate::bytes::Base64".to_string(),
+                // The bytes come in the blob, see `bytes::received_blob`
+                Some(BinaryEncoding::Blob) => "()".to_string(),
+                // Resolved to the bytes with `Payload::resolve`
+                Some(BinaryEncoding::Vfs) => "crate::bytes::Payload".to_string(),
+                None => wit_type_to_rust(&field.wit_type, extensions),
+            })
+            .collect();
+        // The stubs send the PascalCase function name, which needs a rename when it isn't a legal variant name
+        let wire_name = to_pascal_case(&signature.function_name);
+        let variant = legal_identifier(wire_name.clone());
+        if variant != wire_name {
+            variants.push(format!(
+                "    #[serde(rename = \"{}\")]\n    #[allow(non_camel_case_types)]",
+                wire_name
+            ));
+        }
+        // Match the stub encoding: `{"Ping": {}}`, `{"SetStatus": [status]}`, `{"SendMessage": [message, channel]}`
+        variants.push(match params.len() {
+            0 => format!("    {} {{}},", variant),
+            // A one-element tuple, since a newtype variant would expect the bare value
+            1 => format!("    {}(({},)),", variant, params[0]),
+            _ => format!("    {}({}),", variant, params.join(", ")),
+        });
+    }
+
+    let decode = match envelope {
+        Envelope::Plain => "serde_json::from_value(body)".to_string(),
+        Envelope::Namespaced => format!(
+            "let body = unwrap_request(body).ok_or_else(|| {{\n\
+             \x20           <serde_json::Error as serde::de::Error>::custom(\"request is not addressed to the {} interface\")\n\
+             \x20       }})?;\n\
+             \x20       serde_json::from_value(body)",
+            interface.name
+        ),
+        Envelope::Traced => "let body = crate::envelope::unwrap_or_plain(body).ok_or_else(|| {\n\
+             \x20           <serde_json::Error as serde::de::Error>::custom(\"request is in an envelope of another version\")\n\
+             \x20       })?;\n\
+             \x20       serde_json::from_value(body)"
+            .to_string(),
+    };
+
+    format!(
+        "/// Requests accepted by the {name} interface, decoded from the same JSON the stubs send\n\
+         #[derive(Debug, serde::Serialize, serde::Deserialize)]\n\
+         pub enum Request {{\n\
+         {variants}\n\
+         }}\n\
+         \n\
+         impl TryFrom<serde_json::Value> for Request {{\n\
+         \x20   type Error = serde_json::Error;\n\
+         \n\
+         \x20   fn try_from(body: serde_json::Value) -> Result<Self, Self::Error> {{\n\
+         \x20       {decode}\n\
+         \x20   }}\n\
+         }}\n\
+         \n\
+         /// Decode an HTTP request body or the body of a process message\n\
+         impl TryFrom<&[u8]> for Request {{\n\
+         \x20   type Error = serde_json::Error;\n\
+         \n\
+         \x20   fn try_from(body: &[u8]) -> Result<Self, Self::Error> {{\n\
+         \x20       Self::try_from(serde_json::from_slice::<serde_json::Value>(body)?)\n\
+         \x20   }}\n\
+         }}\n\
+         \n\
+         /// Serialize the return value of a handler as a response body for either transport\n\
+         pub fn response_body<T: serde::Serialize>(response: &T) -> serde_json::Result<Vec<u8>> {{\n\
+         \x20   serde_json::to_vec(response)\n\
+         }}",
+        name = interface.name,
+        variants = variants.join("\n"),
+        decode = decode,
+    )
+}
+
+// Generate the prelude of an interface module, re-exporting its stubs and response structs, its
+// WIT types and the common types needed to call them
+fn generate_interface_prelude(
+    interface: &ParsedInterface,
+    package: &wit_source::Package,
+    signatures: &[&SignatureStruct],
+    has_types: bool,
+    style: StubStyle,
+    self_stubs: bool,
+    extensions: &BTreeMap<String, TypeExtension>,
+) -> String {
+    // HTTP stubs are emitted commented out, so there is nothing to re-export for them
+    let mut stubs = Vec::new();
+    let mut former_stubs = Vec::new();
+    for signature in signatures.iter().filter(|signature| signature.attr_type != "http") {
+        stubs.push(stub_function_name(signature));
+        if self_stubs && has_self_stub(signature) {
+            stubs.push(format!("{}_self", stub_function_name(signature)));
+        }
+        if paginated_item_type(signature, extensions).is_some() {
+            stubs.push(format!("{}_all", stub_function_name(signature)));
+        }
+        if let Some((name, _)) = response_struct(signature, &interface.types, extensions) {
+            if !stubs.contains(&name) {
+                stubs.push(name);
+            }
+        }
+        former_stubs.extend(signature.former_stub_names());
+    }
+    // Names still taken by a stub don't get a renamed stub
+    former_stubs.retain(|name| !stubs.contains(name));
+
+    // Types of an interface described in JSON are defined in its module
+    if interface.json_types.is_some() {
+        stubs.extend(interface.types.iter().map(|type_name| to_pascal_case(type_name)));
+    }
+
+    let mut prelude = format!(
+        "/// Everything needed to call the {} interface with a single `use`\npub mod prelude {{\n",
+        interface.name
+    );
+    match stubs.len() {
+        0 => {}
+        1 => prelude.push_str(&format!("    pub use super::{};\n", stubs[0])),
+        _ => prelude.push_str(&format!("    pub use super::{{{}}};\n", stubs.join(", "))),
+    }
+    // Re-exporting a deprecated stub would warn in caller-utils itself rather than at its callers
+    match former_stubs.len() {
+        0 => {}
+        1 => prelude.push_str(&format!("    #[allow(deprecated)]\n    pub use super::{};\n", former_stubs[0])),
+        _ => prelude.push_str(&format!("    #[allow(deprecated)]\n    pub use super::{{{}}};\n", former_stubs.join(", "))),
+    }
+    if has_types {
+        prelude.push_str(&format!("    pub use {}::*;\n", package.module(&interface.name)));
+    }
+    prelude.push_str("    pub use crate::SendResult;\n");
+    if style == StubStyle::Boxed {
+        prelude.push_str("    pub use crate::StubFuture;\n");
+    }
+    prelude.push_str("    pub use hyperware_process_lib::Address;\n}");
+    prelude
+}
+
+// Generate the crate-level prelude combining the preludes of every interface module
+fn generate_crate_prelude(module_paths: &[String]) -> String {
+    let mut prelude = String::from(
+        "/// Everything needed to call any interface with a single `use caller_utils::prelude::*;`\npub mod prelude {\n    pub use crate::pipeline::CallFailed;\n",
+    );
+    for module_path in module_paths {
+        prelude.push_str(&format!("    pub use crate::{}::prelude::*;\n", module_path));
+    }
+    prelude.push_str("}\n\n");
+    prelude
+}
+
+// Generate the helpers a process uses to accept namespaced requests for an interface
+fn generate_envelope_helpers(interface_name: &str) -> String {
+    format!(
+        "/// Interface name used as the request envelope key\n\
+         pub const INTERFACE: &str = \"{}\";\n\
+         \n\
+         /// Strip the `{{\"{}\": ...}}` envelope from a namespaced request body, returning the\n\
+         /// `{{\"Function\": args}}` request the process handlers expect.\n\
+         /// Returns `None` if the request is addressed to another interface.\n\
+         pub fn unwrap_request(body: serde_json::Value) -> Option<serde_json::Value> {{\n\
+         \x20   match body {{\n\
+         \x20       serde_json::Value::Object(mut map) if map.len() == 1 => map.remove(INTERFACE),\n\
+         \x20       _ => None,\n\
+         \x20   }}\n\
+         }}",
+        interface_name, interface_name
+    )
+}
+
+// Versioned envelope of worlds with `envelope = "traced"`. The trace id and the versions of
+// targets are thread-local like the fake handlers, as processes handle one message at a time.
+// Version 0 is the plain encoding of processes that predate the envelope, which stubs downshift
+// to for targets registered with it during a rolling upgrade.
+const ENVELOPE_MODULE: &str = r#"/// Envelope the stubs wrap requests in: `{"v": 1, "trace": "...", "body": {"Function": args}}`.
+///
+/// The trace id of a request handled with `unwrap` (or the `Request` conversions) becomes the
+/// current one, so the calls made while handling it carry it on.
+///
+/// While a fleet is upgraded one process at a time, `register_version(target, PLAIN)` makes the
+/// stubs send plain `{"Function": args}` requests to a target that doesn't unwrap envelopes yet,
+/// and the `Request` conversions accept plain requests from callers that don't send them yet.
+pub mod envelope {
+    use hyperware_process_lib::Address;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    /// Version of the envelope the stubs send and `unwrap` accepts
+    pub const VERSION: u64 = 1;
+
+    /// Version of the plain `{"Function": args}` requests of processes that predate the envelope
+    pub const PLAIN: u64 = 0;
+
+    thread_local! {
+        static TRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+        static TARGET_VERSIONS: RefCell<HashMap<String, u64>> = RefCell::new(HashMap::new());
+    }
+
+    /// Record the envelope version a target understands, e.g. `PLAIN` for a process that wasn't
+    /// upgraded yet. Requests to it are then sent in the newest encoding both sides know.
+    pub fn register_version(target: &Address, version: u64) {
+        TARGET_VERSIONS.with(|versions| versions.borrow_mut().insert(target.to_string(), version));
+    }
+
+    /// Forget the version registered for a target once it's upgraded
+    pub fn forget_version(target: &Address) {
+        TARGET_VERSIONS.with(|versions| versions.borrow_mut().remove(&target.to_string()));
+    }
+
+    /// Envelope version requests to `target` are sent with: the registered one, at most `VERSION`
+    pub fn version_for(target: &Address) -> u64 {
+        TARGET_VERSIONS
+            .with(|versions| versions.borrow().get(&target.to_string()).copied())
+            .map_or(VERSION, |version| version.min(VERSION))
+    }
+
+    /// Encode a `{"Function": args}` request for `target`: plain for a target registered with
+    /// `PLAIN`, in the envelope otherwise
+    pub fn wrap_for(target: &Address, body: serde_json::Value) -> serde_json::Value {
+        match version_for(target) {
+            PLAIN => body,
+            _ => wrap(body),
+        }
+    }
+
+    /// Set the trace id sent with the requests that follow, `None` to send none
+    pub fn set_trace(trace: Option<String>) {
+        TRACE.with(|current| *current.borrow_mut() = trace);
+    }
+
+    /// Trace id sent with requests, as set by `set_trace` or taken from the last unwrapped request
+    pub fn trace() -> Option<String> {
+        TRACE.with(|current| current.borrow().clone())
+    }
+
+    /// Wrap a `{"Function": args}` request in the envelope with the current trace id
+    pub fn wrap(body: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({ "v": VERSION, "trace": trace(), "body": body })
+    }
+
+    /// Strip the envelope from a request body, returning the `{"Function": args}` request the
+    /// process handlers expect and making its trace id the current one.
+    /// Returns `None` if the body isn't a version 1 envelope.
+    pub fn unwrap(body: serde_json::Value) -> Option<serde_json::Value> {
+        let serde_json::Value::Object(mut map) = body else {
+            return None;
+        };
+        if map.get("v").and_then(serde_json::Value::as_u64) != Some(VERSION) {
+            return None;
+        }
+        let body = map.remove("body")?;
+        set_trace(map.remove("trace").and_then(|trace| trace.as_str().map(str::to_string)));
+        Some(body)
+    }
+
+    /// Like `unwrap`, but take a body without an envelope as the plain request of a caller that
+    /// predates it, which carries no trace id.
+    /// Returns `None` if the body is an envelope of another version.
+    pub fn unwrap_or_plain(body: serde_json::Value) -> Option<serde_json::Value> {
+        if body.get("v").is_some() {
+            return unwrap(body);
+        }
+        set_trace(None);
+        Some(body)
+    }
+}
+"#;
+
+// Attempts of a call to an `@idempotent` function, including the first one
+const IDEMPOTENT_ATTEMPTS: u32 = 3;
+
+// Combinators for chaining stubs, where each call depends on the result of the previous one.
+// Only `SendResult::Success` is matched so the module doesn't depend on the failure variants.
+const PIPELINE_MODULE: &str = r#"/// Helpers for chaining dependent stub calls with early exit on the first failed call.
+///
+/// ```ignore
+/// use caller_utils::pipeline::{self, CallFailed};
+///
+/// async fn rename_and_notify(target: &Address, id: u64) -> Result<bool, CallFailed> {
+///     let user = pipeline::step("get_user", get_user_remote_rpc(target, id).await)?;
+///     pipeline::then("notify", user, |user| notify_remote_rpc(target, user.name)).await
+/// }
+/// ```
+pub mod pipeline {
+    use crate::SendResult;
+    use std::future::Future;
+
+    /// Safety cap on the number of pages fetched by the generated `*_all` helpers
+    pub const MAX_PAGES: usize = 1000;
+
+    /// A call in a pipeline that did not succeed
+    #[derive(Debug, Clone)]
+    pub struct CallFailed {
+        /// Name of the step that failed
+        pub step: &'static str,
+        /// Debug rendering of the unsuccessful `SendResult`
+        pub reason: String,
+    }
+
+    impl std::fmt::Display for CallFailed {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "pipeline step `{}` failed: {}", self.step, self.reason)
+        }
+    }
+
+    impl std::error::Error for CallFailed {}
+
+    /// Unwrap the result of a call, turning anything but `Success` into a `CallFailed` for `?`
+    pub fn step<T: std::fmt::Debug>(step: &'static str, result: SendResult<T>) -> Result<T, CallFailed> {
+        match result {
+            SendResult::Success(value) => Ok(value),
+            other => Err(CallFailed {
+                step,
+                reason: format!("{:?}", other),
+            }),
+        }
+    }
+
+    /// Feed the value of a previous step into the next call and unwrap its result
+    pub async fn then<T, U, F, Fut>(step_name: &'static str, value: T, next: F) -> Result<U, CallFailed>
+    where
+        U: std::fmt::Debug,
+        F: FnOnce(T) -> Fut,
+        Fut: Future<Output = SendResult<U>>,
+    {
+        step(step_name, next(value).await)
+    }
+}
+"#;
+
+// Scope tracking the calls a process state makes, generated with `scoped-client = true`. Like the
+// serial locks it is built on `Rc` and `Cell`, as processes are single threaded.
+const SCOPE_MODULE: &str = r#"/// Structured concurrency for stub calls: a [`ScopedClient`] counts the calls made through it
+/// and can cancel and await all of them when the state that made them goes away.
+///
+/// ```ignore
+/// let scope = caller_utils::scope::ScopedClient::new();
+/// let reply = scope.call(chat::send_message_remote_rpc(&target, message, channel)).await;
+/// // On a state transition: calls still in flight resolve to `None`
+/// scope.shutdown().await;
+/// ```
+pub mod scope {
+    use std::cell::{Cell, RefCell};
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::rc::Rc;
+    use std::task::{Context, Poll, Waker};
+
+    #[derive(Default)]
+    struct State {
+        in_flight: Cell<usize>,
+        cancelled: Cell<bool>,
+        // Calls and drains waiting for the scope to change
+        wakers: RefCell<Vec<Waker>>,
+    }
+
+    impl State {
+        fn register(&self, waker: &Waker) {
+            let mut wakers = self.wakers.borrow_mut();
+            if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+                wakers.push(waker.clone());
+            }
+        }
+
+        fn wake_all(&self) {
+            for waker in self.wakers.borrow_mut().drain(..) {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Tracks the stub calls made through it. Clones share the same scope.
+    #[derive(Clone, Default)]
+    pub struct ScopedClient {
+        state: Rc<State>,
+    }
+
+    impl ScopedClient {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Run a stub call in the scope. It counts as in flight until it completes or is
+        /// dropped, and resolves to `None` without finishing once the scope is cancelled.
+        pub fn call<F: Future>(&self, call: F) -> ScopedCall<F> {
+            self.state.in_flight.set(self.state.in_flight.get() + 1);
+            ScopedCall {
+                call: Box::pin(call),
+                state: self.state.clone(),
+                done: false,
+            }
+        }
+
+        /// Number of calls started through the scope that haven't completed
+        pub fn in_flight(&self) -> usize {
+            self.state.in_flight.get()
+        }
+
+        /// Whether [`cancel`](Self::cancel) was called
+        pub fn is_cancelled(&self) -> bool {
+            self.state.cancelled.get()
+        }
+
+        /// Cancel every call in flight and every later call of the scope
+        pub fn cancel(&self) {
+            self.state.cancelled.set(true);
+            self.state.wake_all();
+        }
+
+        /// Wait until no call of the scope is in flight
+        pub fn drain(&self) -> Drain {
+            Drain {
+                state: self.state.clone(),
+            }
+        }
+
+        /// Cancel the calls in flight and wait for all of them to be dropped
+        pub async fn shutdown(&self) {
+            self.cancel();
+            self.drain().await;
+        }
+    }
+
+    /// A call made through a [`ScopedClient`]
+    pub struct ScopedCall<F: Future> {
+        call: Pin<Box<F>>,
+        state: Rc<State>,
+        done: bool,
+    }
+
+    impl<F: Future> ScopedCall<F> {
+        fn finish(&mut self) {
+            if !self.done {
+                self.done = true;
+                self.state.in_flight.set(self.state.in_flight.get() - 1);
+                self.state.wake_all();
+            }
+        }
+    }
+
+    impl<F: Future> Future for ScopedCall<F> {
+        type Output = Option<F::Output>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            if self.done {
+                return Poll::Ready(None);
+            }
+            if self.state.cancelled.get() {
+                self.finish();
+                return Poll::Ready(None);
+            }
+            match self.call.as_mut().poll(cx) {
+                Poll::Ready(output) => {
+                    self.finish();
+                    Poll::Ready(Some(output))
+                }
+                Poll::Pending => {
+                    self.state.register(cx.waker());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    impl<F: Future> Drop for ScopedCall<F> {
+        fn drop(&mut self) {
+            self.finish();
+        }
+    }
+
+    /// Future of [`ScopedClient::drain`]
+    pub struct Drain {
+        state: Rc<State>,
+    }
+
+    impl Future for Drain {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.state.in_flight.get() == 0 {
+                return Poll::Ready(());
+            }
+            self.state.register(cx.waker());
+            Poll::Pending
+        }
+    }
+}
+"#;
+
+// Async lock for the stubs of functions annotated with `@serialize-calls`. Built on std's `Arc`
+// and `Mutex` rather than an async runtime's mutex, and `Send` so the future-returning stub styles
+// can promise `Send` futures.
+const SERIAL_MODULE: &str = r#"/// Locks held by the stubs of `@serialize-calls` functions while their request is in flight,
+/// so a process never has two overlapping calls to such a function
+pub mod serial {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use std::task::{Context, Poll, Waker};
+
+    #[derive(Default)]
+    struct State {
+        locked: bool,
+        waiters: Vec<Waker>,
+    }
+
+    /// Lock of one function's calls
+    #[derive(Default)]
+    pub struct CallLock(Mutex<State>);
+
+    impl CallLock {
+        fn state(&self) -> MutexGuard<'_, State> {
+            self.0.lock().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+
+    /// Held for the duration of a call, letting the waiting calls retry when dropped
+    pub struct CallGuard(Arc<CallLock>);
+
+    impl Drop for CallGuard {
+        fn drop(&mut self) {
+            let waiters = {
+                let mut state = self.0.state();
+                state.locked = false;
+                std::mem::take(&mut state.waiters)
+            };
+            // Wake every waiter rather than one, so a waiter dropped before it polls again
+            // can't leave the others waiting forever
+            for waker in waiters {
+                waker.wake();
+            }
+        }
+    }
+
+    struct Acquire(Arc<CallLock>);
+
+    impl Future for Acquire {
+        type Output = CallGuard;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<CallGuard> {
+            let mut state = self.0.state();
+            if state.locked {
+                state.waiters.push(cx.waker().clone());
+                Poll::Pending
+            } else {
+                state.locked = true;
+                Poll::Ready(CallGuard(self.0.clone()))
+            }
+        }
+    }
+
+    /// Wait until no other call holds `lock`, then hold it until the guard is dropped
+    pub fn acquire(lock: &'static std::thread::LocalKey<Arc<CallLock>>) -> impl Future<Output = CallGuard> + Send {
+        Acquire(lock.with(Arc::clone))
+    }
+}
+"#;
+
+// Response cache for the stubs of functions annotated with `@cacheable ttl=...`. Responses are
+// stored as JSON so one cache type serves every return type.
+const CACHE_MODULE: &str = r#"/// Caches of the responses of `@cacheable` functions, keyed by target and request
+pub mod cache {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::thread::LocalKey;
+    use std::time::{Duration, Instant};
+
+    /// Key of a call: its target and serialized request
+    pub type CacheKey = (String, String);
+
+    /// Fresh responses of one function with the time they expire
+    #[derive(Default)]
+    pub struct ResponseCache {
+        entries: RefCell<HashMap<CacheKey, (Instant, serde_json::Value)>>,
+    }
+
+    /// Key of a call to `target`
+    pub fn key(target: &impl std::fmt::Display, request: &serde_json::Value) -> CacheKey {
+        (target.to_string(), request.to_string())
+    }
+
+    /// The cached response of a call, if it hasn't expired
+    pub fn get<T: serde::de::DeserializeOwned>(cache: &'static LocalKey<ResponseCache>, key: &CacheKey) -> Option<T> {
+        cache.with(|cache| {
+            let entries = cache.entries.borrow();
+            let (expires, value) = entries.get(key)?;
+            if *expires <= Instant::now() {
+                return None;
+            }
+            serde_json::from_value(value.clone()).ok()
+        })
+    }
+
+    /// Cache the response of a call for `ttl_secs`, dropping the expired ones
+    pub fn put<T: serde::Serialize>(cache: &'static LocalKey<ResponseCache>, key: CacheKey, value: &T, ttl_secs: u64) {
+        let Ok(value) = serde_json::to_value(value) else {
+            return;
+        };
+        let now = Instant::now();
+        cache.with(|cache| {
+            let mut entries = cache.entries.borrow_mut();
+            entries.retain(|_, (expires, _)| *expires > now);
+            entries.insert(key, (now + Duration::from_secs(ttl_secs), value));
+        });
+    }
+
+    /// Forget every cached response of a function, e.g. after a call that changes what it returns
+    pub fn clear(cache: &'static LocalKey<ResponseCache>) {
+        cache.with(|cache| cache.entries.borrow_mut().clear());
+    }
+}
+"#;
+
+// Inner attributes excluding a generated crate root from coverage under `coverage_nightly`, the
+// cfg cargo-llvm-cov sets on nightly toolchains, where the `coverage` attribute is available
+const COVERAGE_OFF: &str = "#![cfg_attr(coverage_nightly, feature(coverage_attribute))]\n#![cfg_attr(coverage_nightly, coverage(off))]\n\n";
+
+// File in a generated crate with the `--ignore-filename-regex` of `cargo llvm-cov` matching its
+// sources, for stable toolchains
+const COVERAGE_IGNORE_FILE: &str = "coverage-ignore-regex";
+
+// Blocking `send` the maybe-async stubs call once the `sync` feature strips their `.await`
+const SYNC_SEND: &str = r#"/// Blocking counterpart of `hyperware_app_common::send`, used by the stubs under the `sync` feature
+#[cfg(feature = "sync")]
+pub fn send<R: serde::de::DeserializeOwned>(request: &serde_json::Value, target: &Address, timeout: u64) -> SendResult<R> {
+    let body = match serde_json::to_vec(request) {
+        Ok(body) => body,
+        Err(e) => return SendResult::DeserializationError(e.to_string()),
+    };
+    match hyperware_process_lib::Request::to(target.clone()).body(body).send_and_await_response(timeout) {
+        Ok(Ok(response)) => match serde_json::from_slice(response.body()) {
+            Ok(value) => SendResult::Success(value),
+            Err(e) => SendResult::DeserializationError(e.to_string()),
+        },
+        Ok(Err(e)) if matches!(e.kind, hyperware_process_lib::SendErrorKind::Timeout) => SendResult::Timeout,
+        Ok(Err(_)) => SendResult::Offline,
+        Err(e) => SendResult::DeserializationError(e.to_string()),
+    }
+}
+
+"#;
+
+// Returned `stream<T>` values, generated when a stub returns one
+const STREAMS_MODULE: &str = r#"/// The `stream<T>` values functions return
+pub mod streams {
+    use std::collections::VecDeque;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// Items of a returned `stream<T>`, handed out as a [`futures::Stream`]. Processes answer with
+    /// the items as a JSON list, so callers written against the stream keep working unchanged once
+    /// responses arrive in parts.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ItemStream<T> {
+        items: VecDeque<T>,
+    }
+
+    impl<T> ItemStream<T> {
+        /// Number of items left
+        pub fn len(&self) -> usize {
+            self.items.len()
+        }
+
+        /// Whether every item was taken
+        pub fn is_empty(&self) -> bool {
+            self.items.is_empty()
+        }
+
+        /// The items left, without polling for them
+        pub fn into_vec(self) -> Vec<T> {
+            self.items.into()
+        }
+    }
+
+    impl<T> Default for ItemStream<T> {
+        fn default() -> Self {
+            Self { items: VecDeque::new() }
+        }
+    }
+
+    impl<T> From<Vec<T>> for ItemStream<T> {
+        fn from(items: Vec<T>) -> Self {
+            Self { items: items.into() }
+        }
+    }
+
+    impl<T: Unpin> futures::Stream for ItemStream<T> {
+        type Item = T;
+
+        fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<T>> {
+            Poll::Ready(self.get_mut().items.pop_front())
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.items.len(), Some(self.items.len()))
+        }
+    }
+
+    impl<T: serde::Serialize> serde::Serialize for ItemStream<T> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(&self.items)
+        }
+    }
+
+    impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for ItemStream<T> {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Vec::<T>::deserialize(deserializer).map(Self::from)
+        }
+    }
+}
+"#;
+
+// Encodings of `list<u8>` fields declared with `@binary`, generated when a stub uses one. Base64
+// is implemented here rather than pulling in a dependency for two small functions.
+const BYTES_MODULE: &str = r#"/// Encodings of the `list<u8>` parameters and return values declared with `@binary`
+pub mod bytes {
+    use crate::SendResult;
+    use hyperware_process_lib::Address;
+
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    /// Bytes (de)serialized as a padded standard base64 string instead of an array of numbers
+    /// (`@binary <field>=base64`)
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct Base64(pub Vec<u8>);
+
+    impl serde::Serialize for Base64 {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&encode(&self.0))
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for Base64 {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let text = String::deserialize(deserializer)?;
+            decode(&text).map(Base64).map_err(serde::de::Error::custom)
+        }
+    }
+
+    /// Encode bytes as padded standard base64
+    pub fn encode(bytes: &[u8]) -> String {
+        let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let bits = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |bits, (i, byte)| bits | (*byte as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    text.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    text.push('=');
+                }
+            }
+        }
+        text
+    }
+
+    /// Decode padded standard base64
+    pub fn decode(text: &str) -> Result<Vec<u8>, String> {
+        if text.len() % 4 != 0 {
+            return Err(format!("base64 length {} is not a multiple of 4", text.len()));
+        }
+        let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+        for chunk in text.as_bytes().chunks(4) {
+            let padding = chunk.iter().rev().take_while(|c| **c == b'=').count();
+            if padding > 2 {
+                return Err("invalid base64 padding".to_string());
+            }
+            let mut bits = 0u32;
+            for (i, c) in chunk[..4 - padding].iter().enumerate() {
+                let value = ALPHABET
+                    .iter()
+                    .position(|a| a == c)
+                    .ok_or_else(|| format!("invalid base64 character `{}`", *c as char))?;
+                bits |= (value as u32) << (18 - 6 * i);
+            }
+            bytes.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+        }
+        Ok(bytes)
+    }
+
+    /// Send a request with `blob` as the message blob (`@binary <field>=blob`) and wait for the
+    /// response. This blocks the process until the response arrives, as the blob needs the
+    /// process lib's request.
+    pub fn send_with_blob<R: serde::de::DeserializeOwned>(
+        request: &serde_json::Value,
+        blob: &[u8],
+        target: &Address,
+        timeout: u64,
+    ) -> SendResult<R> {
+        let body = match serde_json::to_vec(request) {
+            Ok(body) => body,
+            Err(e) => return SendResult::DeserializationError(e.to_string()),
+        };
+        let request = hyperware_process_lib::Request::to(target.clone())
+            .body(body)
+            .blob_bytes(blob.to_vec());
+        match request.send_and_await_response(timeout) {
+            Ok(Ok(response)) => match serde_json::from_slice(response.body()) {
+                Ok(value) => SendResult::Success(value),
+                Err(e) => SendResult::DeserializationError(e.to_string()),
+            },
+            Ok(Err(e)) if matches!(e.kind, hyperware_process_lib::SendErrorKind::Timeout) => SendResult::Timeout,
+            Ok(Err(_)) => SendResult::Offline,
+            Err(e) => SendResult::DeserializationError(e.to_string()),
+        }
+    }
+
+    /// Bytes of the blob of the message being handled, where a `@binary <field>=blob` parameter
+    /// arrives while the request body has `null` in its place
+    pub fn received_blob() -> Option<Vec<u8>> {
+        hyperware_process_lib::get_blob().map(|blob| blob.bytes)
+    }
+
+    /// Size from which `@binary <field>=vfs` parameters are handed off through the VFS
+    pub const HANDOFF_THRESHOLD: usize = 64 * 1024;
+    /// Drive of the sending package the handed-off files are written to
+    pub const HANDOFF_DRIVE: &str = "handoff";
+    const HANDOFF_TIMEOUT_SECS: u64 = 5;
+
+    /// A `@binary <field>=vfs` parameter: the bytes themselves when small, or a file in the
+    /// sender's VFS drive, which the receiver reads with [`Payload::resolve`]
+    #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    pub enum Payload {
+        Inline(Base64),
+        Vfs { path: String, len: u64 },
+    }
+
+    impl Payload {
+        /// The bytes of the payload, reading and then removing a handed-off file. The
+        /// receiver must be allowed to read the sender's drive, e.g. by being in its package.
+        pub fn resolve(self) -> Result<Vec<u8>, String> {
+            match self {
+                Payload::Inline(bytes) => Ok(bytes.0),
+                Payload::Vfs { path, len } => {
+                    let file = hyperware_process_lib::vfs::open_file(&path, false, Some(HANDOFF_TIMEOUT_SECS))
+                        .map_err(|e| format!("failed to open {}: {:?}", path, e))?;
+                    let bytes = file.read().map_err(|e| format!("failed to read {}: {:?}", path, e))?;
+                    if bytes.len() as u64 != len {
+                        return Err(format!("{} has {} bytes, expected {}", path, bytes.len(), len));
+                    }
+                    let _ = hyperware_process_lib::vfs::remove_file(&path, Some(HANDOFF_TIMEOUT_SECS));
+                    Ok(bytes)
+                }
+            }
+        }
+    }
+
+    thread_local! {
+        static HANDOFFS: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+    }
+
+    /// Write bytes above [`HANDOFF_THRESHOLD`] to a new file of the sender's VFS drive and refer
+    /// to it, keeping them inline when they are small or the file can't be written
+    pub fn handoff(bytes: Vec<u8>) -> Payload {
+        if bytes.len() < HANDOFF_THRESHOLD {
+            return Payload::Inline(Base64(bytes));
+        }
+        let our = hyperware_process_lib::our();
+        let Ok(drive) = hyperware_process_lib::vfs::create_drive(our.package_id(), HANDOFF_DRIVE, Some(HANDOFF_TIMEOUT_SECS)) else {
+            return Payload::Inline(Base64(bytes));
+        };
+        let count = HANDOFFS.with(|count| {
+            count.set(count.get() + 1);
+            count.get()
+        });
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos());
+        let path = format!("{}/{}-{}-{}", drive, our.process(), nanos, count);
+        let written = hyperware_process_lib::vfs::create_file(&path, Some(HANDOFF_TIMEOUT_SECS))
+            .and_then(|file| file.write(&bytes));
+        match written {
+            Ok(()) => Payload::Vfs { path, len: bytes.len() as u64 },
+            Err(_) => Payload::Inline(Base64(bytes)),
+        }
+    }
+}
+"#;
+
+// Helpers of the `cli` module behind the debugging binary, generated with `cli = true`.
+// Requests are sent synchronously with the process lib so the binary needs no async runtime.
+const CLI_HELPERS: &str = r#"
+    /// Parse the JSON array of a function's arguments
+    fn arguments<T: serde::de::DeserializeOwned>(json: &str) -> Result<T, String> {
+        serde_json::from_str(json).map_err(|e| format!("invalid --json arguments: {}", e))
+    }
+
+    /// Send a request, wait for the response and render it as pretty JSON
+    fn call<R>(target: &Address, request: serde_json::Value, timeout_secs: u64) -> Result<String, String>
+    where
+        R: serde::de::DeserializeOwned + serde::Serialize,
+    {
+        let body = serde_json::to_vec(&request).map_err(|e| e.to_string())?;
+        let response = hyperware_process_lib::Request::to(target.clone())
+            .body(body)
+            .send_and_await_response(timeout_secs)
+            .map_err(|e| format!("failed to send the request: {:?}", e))?
+            .map_err(|e| format!("no response: {:?}", e))?;
+        let value: R = serde_json::from_slice(response.body())
+            .map_err(|e| format!("unexpected response: {}", e))?;
+        serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+    }
+}
+"#;
+
+// In-memory transport for tests, enabled by `fake-send = true`. Calls are routed to closures
+// registered per (interface, function), so downstream tests can exercise code calling stubs.
+// A feature is needed besides cfg(test) because dependencies aren't built with cfg(test).
+const FAKE_MODULE: &str = r#"/// In-memory transport the stubs use under `cfg(test)` or the `fake-send` feature
+/// (enable it from the `[dev-dependencies]` of the crate under test).
+///
+/// ```ignore
+/// caller_utils::fake::register("chat", "Ping", |_args| serde_json::json!("pong"));
+/// ```
+#[cfg(any(test, feature = "fake-send"))]
+pub mod fake {
+    use crate::SendResult;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    type Handler = Box<dyn FnMut(serde_json::Value) -> serde_json::Value>;
+
+    thread_local! {
+        static HANDLERS: RefCell<HashMap<(String, String), Handler>> = RefCell::new(HashMap::new());
+    }
+
+    /// Route calls to `function` (the PascalCase request variant) of `interface` to `handler`,
+    /// which receives the JSON arguments and returns the JSON response
+    pub fn register<F>(interface: &str, function: &str, handler: F)
+    where
+        F: FnMut(serde_json::Value) -> serde_json::Value + 'static,
+    {
+        HANDLERS.with(|handlers| {
+            handlers
+                .borrow_mut()
+                .insert((interface.to_string(), function.to_string()), Box::new(handler));
+        });
+    }
+
+    /// Remove every registered handler
+    pub fn clear() {
+        HANDLERS.with(|handlers| handlers.borrow_mut().clear());
+    }
+
+    /// Answer a stub call with the registered handler. Panics if no handler is registered
+    /// or its response doesn't deserialize, so a misconfigured test fails loudly.
+    pub fn dispatch<R: serde::de::DeserializeOwned>(interface: &str, request: &serde_json::Value) -> SendResult<R> {
+        let mut request = request.clone();
+        // Strip a traced envelope, then a namespaced one
+        if let (Some(_), Some(body)) = (request.get("v"), request.get("body")) {
+            request = body.clone();
+        }
+        if let Some(inner) = request.get(interface) {
+            request = inner.clone();
+        }
+        let (function, args) = match request {
+            serde_json::Value::Object(map) if map.len() == 1 => map.into_iter().next().unwrap(),
+            other => panic!("fake send: malformed request for {}: {}", interface, other),
+        };
+
+        let key = (interface.to_string(), function);
+        let mut handler = HANDLERS
+            .with(|handlers| handlers.borrow_mut().remove(&key))
+            .unwrap_or_else(|| panic!("fake send: no handler registered for {}.{}", key.0, key.1));
+        let response = handler(args);
+        // Put the handler back unless it registered a replacement for itself
+        HANDLERS.with(|handlers| {
+            handlers.borrow_mut().entry(key.clone()).or_insert(handler);
+        });
+
+        match serde_json::from_value(response) {
+            Ok(value) => SendResult::Success(value),
+            Err(e) => panic!("fake send: invalid response from {}.{} handler: {}", key.0, key.1, e),
+        }
+    }
+}
+"#;
+
+// Condition under which the stubs use the fake transport: tests of the crate itself and the
+// `fake-send` feature, and with `dual-target` every build that isn't for the wasm32 component
+fn fake_cfg(config: &Config) -> &'static str {
+    if config.dual_target {
+        "any(test, feature = \"fake-send\", not(target_arch = \"wasm32\"))"
+    } else {
+        "any(test, feature = \"fake-send\")"
+    }
+}
+
+// The `fake` module, used under the given cfg condition
+fn generate_fake_module(cfg: &str) -> String {
+    let module = FAKE_MODULE.replace("any(test, feature = \"fake-send\")", cfg);
+    if cfg.contains("wasm32") {
+        module.replace(
+            "under `cfg(test)` or the `fake-send` feature\n/// (enable it from the `[dev-dependencies]` of the crate under test)",
+            "in native builds, for host-side tests,\n/// and under `cfg(test)` or the `fake-send` feature",
+        )
+    } else {
+        module
+    }
+}
+
+// Generate the module-local `send` that shadows the real one in an interface module when the
+// fake transport is enabled
+fn generate_fake_send(interface_name: &str, style: StubStyle, cfg: &str) -> String {
+    format!(
+        "#[cfg({})]\n\
+         {}async fn send<R: serde::de::DeserializeOwned>(request: &serde_json::Value, _target: &Address, _timeout: u64) -> SendResult<R> {{\n\
+         \x20   crate::fake::dispatch(\"{}\", request)\n\
+         }}",
+        cfg,
+        maybe_async_attribute(style),
+        interface_name
+    )
+}
+
+// Generate the `pub use` lines bringing the types an interface takes from other interfaces of
+// the api directory into its module. Interfaces outside of it, like `standard`, are left to the
+// crate-level imports.
+fn generate_type_imports(interface: &ParsedInterface, interfaces: &[ParsedInterface], package: &wit_source::Package) -> String {
+    let mut imports = BTreeMap::<&str, Vec<String>>::new();
+    for import in &interface.imports {
+        let in_api = interfaces
+            .iter()
+            .any(|other| other.name == import.interface && other.json_types.is_none());
+        if !in_api {
+            continue;
+        }
+        let name = to_pascal_case(&import.name);
+        let local = to_pascal_case(&import.local);
+        imports.entry(import.interface.as_str()).or_default().push(if name == local {
+            name
+        } else {
+            format!("{} as {}", name, local)
+        });
+    }
+    
+    let mut code = String::new();
+    for (interface_name, names) in &imports {
+        let names = match names.as_slice() {
+            [name] if !name.contains(" as ") => name.clone(),
+            _ => format!("{{{}}}", names.join(", ")),
+        };
+        code.push_str(&format!(
+            "// Types used from the {} interface
+pub use {}::{};
+",
+            interface_name,
+            package.module(interface_name),
+            names
+        ));
+    }
+    if !code.is_empty() {
+        code.push('\n');
+    }
+    code
+}
+
+// Generate the module-local `bytes` that shadows the crate's when the fake transport is enabled, so
+// stubs sending a blob are answered by the fake handlers too, without the blob, and VFS payloads
+// stay inline off the node
+fn generate_fake_bytes(interface_name: &str, blob: bool, vfs: bool, cfg: &str) -> String {
+    let mut module = format!("#[cfg({})]\nmod bytes {{\n    pub use crate::bytes::*;\n", cfg);
+    if blob {
+        module.push_str(&format!(
+            "\n\
+             \x20   pub fn send_with_blob<R: serde::de::DeserializeOwned>(request: &serde_json::Value, _blob: &[u8], _target: &hyperware_process_lib::Address, _timeout: u64) -> crate::SendResult<R> {{\n\
+             \x20       crate::fake::dispatch(\"{}\", request)\n\
+             \x20   }}\n",
+            interface_name
+        ));
+    }
+    if vfs {
+        module.push_str(
+            "\n\
+             \x20   pub fn handoff(bytes: Vec<u8>) -> Payload {\n\
+             \x20       Payload::Inline(Base64(bytes))\n\
+             \x20   }\n",
+        );
+    }
+    module.push('}');
+    module
+}
+
+// Records and enums of an interface described in JSON, as they'd be parsed from its WIT
+fn described_records_and_enums(types: &[json_api::TypeDescription]) -> (Vec<WitRecord>, Vec<WitEnum>) {
+    let mut records = Vec::new();
+    let mut enums = Vec::new();
+    for definition in types {
+        match &definition.kind {
+            json_api::TypeKind::Record(fields) => records.push((
+                definition.name.clone(),
+                fields
+                    .iter()
+                    .map(|field| (field.name.clone(), field.wit_type.clone().unwrap_or_default()))
+                    .collect(),
+            )),
+            json_api::TypeKind::Enum(cases) => enums.push((definition.name.clone(), cases.clone())),
+            json_api::TypeKind::Variant(_) | json_api::TypeKind::Alias(_) => {}
+        }
+    }
+    (records, enums)
+}
+
+// Generate the Rust types of an interface described in JSON. They are what wit-bindgen would
+// generate from the same WIT definitions, so they serialize the same way.
+fn generate_described_types(interface_name: &str, types: &[json_api::TypeDescription], extensions: &BTreeMap<String, TypeExtension>) -> String {
+    let mut code = String::new();
+    for definition in types {
+        let kind = match &definition.kind {
+            json_api::TypeKind::Record(_) => "record",
+            json_api::TypeKind::Variant(_) => "variant",
+            json_api::TypeKind::Enum(_) => "enum",
+            json_api::TypeKind::Alias(wit_type) => {
+                code.push_str(&format!(
+                    "/// `{}` type of the {} interface, described in JSON\npub type {} = {};\n\n",
+                    definition.name,
+                    interface_name,
+                    to_pascal_case(&definition.name),
+                    wit_type_to_rust(wit_type, extensions)
+                ));
+                continue;
+            }
+        };
+        code.push_str(&format!(
+            "/// `{}` {} of the {} interface, described in JSON\n\
+             #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, process_macros::SerdeJsonInto)]\n",
+            definition.name, kind, interface_name
+        ));
+        let name = to_pascal_case(&definition.name);
+        match &definition.kind {
+            json_api::TypeKind::Record(fields) => {
+                code.push_str(&format!("pub struct {} {{\n", name));
+                for field in fields {
+                    code.push_str(&format!(
+                        "    pub {}: {},\n",
+                        to_rust_identifier(&field.name),
+                        wit_type_to_rust(field.wit_type.as_deref().unwrap_or_default(), extensions)
+                    ));
+                }
+            }
+            json_api::TypeKind::Variant(cases) => {
+                code.push_str(&format!("pub enum {} {{\n", name));
+                for case in cases {
+                    let case_name = legal_identifier(to_pascal_case(&case.name));
+                    match &case.wit_type {
+                        Some(wit_type) => code.push_str(&format!("    {}({}),\n", case_name, wit_type_to_rust(wit_type, extensions))),
+                        None => code.push_str(&format!("    {},\n", case_name)),
+                    }
+                }
+            }
+            json_api::TypeKind::Enum(cases) => {
+                code.push_str(&format!("pub enum {} {{\n", name));
+                for case in cases {
+                    code.push_str(&format!("    {},\n", legal_identifier(to_pascal_case(case))));
+                }
+            }
+            json_api::TypeKind::Alias(_) => {}
+        }
+        code.push_str("}\n\n");
+    }
+    code
+}
+
+// Enums of a WIT file with their cases
+fn parse_enums(source_name: &str, content: &str) -> Result<Vec<WitEnum>> {
+    Ok(wit_source::parse(source_name, content)?
+        .into_iter()
+        .flat_map(|interface| interface.definitions)
+        .filter(|definition| definition.keyword == "enum")
+        .map(|definition| (definition.name, definition.cases))
+        .collect())
+}
+
+// Records of a WIT file with their `(name, type)` fields, leaving out signature records
+fn parse_records(source_name: &str, content: &str) -> Result<Vec<WitRecord>> {
+    Ok(wit_source::parse(source_name, content)?
+        .into_iter()
+        .flat_map(|interface| interface.definitions)
+        .filter(|definition| definition.keyword == "record" && !definition.name.contains("-signature-"))
+        .map(|definition| (definition.name, definition.fields))
+        .collect())
+}
+
+// Replace the names of the given types in a Rust type with their path in `module`,
+// e.g. `Vec<Vec<ChatMessage>>` -> `Vec<Vec<crate::hyperware::process::chat::ChatMessage>>`
+fn qualify_types(rust_type: &str, names: &HashSet<String>, module: &str) -> String {
+    let mut qualified = String::new();
+    let mut word = String::new();
+    for c in rust_type.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        if names.contains(&word) {
+            qualified.push_str(module);
+            qualified.push_str("::");
+        }
+        qualified.push_str(&std::mem::take(&mut word));
+        qualified.push(c);
+    }
+    qualified.pop();
+    qualified
+}
+
+// Generate a `camel` module with a camelCase copy of every record of an interface, converting
+// to and from the WIT type, for JSON exchanged with frontends. Fields holding another record of
+// the interface directly, in a list or in an option use its camelCase copy; other fields keep
+// the WIT type.
+fn generate_camel_case_types(interface_name: &str, records: &[WitRecord], package: &wit_source::Package, extensions: &BTreeMap<String, TypeExtension>) -> String {
+    let wit_module = package.module(interface_name);
+    let record_names: HashSet<String> = records.iter().map(|(name, _)| to_pascal_case(name)).collect();
+    let is_record = |wit_type: &str| record_names.contains(&to_pascal_case(wit_type));
+
+    let mut module = format!(
+        "/// camelCase copies of the {} records, for JSON payloads exchanged with frontends;\n\
+         /// convert with `From`/`Into` at the boundary\n\
+         pub mod camel {{\n    use crate::*;\n",
+        interface_name
+    );
+    for (name, fields) in records {
+        let type_name = to_pascal_case(name);
+        let mut definitions = Vec::new();
+        let mut conversions = Vec::new();
+        for (field, wit_type) in fields {
+            let ident = to_rust_identifier(field);
+            let nested = wit_type
+                .strip_prefix("list<")
+                .or_else(|| wit_type.strip_prefix("option<"))
+                .and_then(|inner| inner.strip_suffix('>'))
+                .filter(|inner| is_record(inner));
+            let (rust_type, convert) = if is_record(wit_type) {
+                (to_pascal_case(wit_type), format!("value.{}.into()", ident))
+            } else if nested.is_some() && wit_type.starts_with("list<") {
+                (wit_type_to_rust(wit_type, extensions), format!("value.{}.into_iter().map(Into::into).collect()", ident))
+            } else if nested.is_some() {
+                (wit_type_to_rust(wit_type, extensions), format!("value.{}.map(Into::into)", ident))
+            } else {
+                // The local copies shadow the WIT records, so other uses of them are spelled out
+                (qualify_types(&wit_type_to_rust(wit_type, extensions), &record_names, &wit_module), format!("value.{}", ident))
+            };
+            // Escaped identifiers like `type_` need the WIT name spelled out
+            if ident != to_snake_case(field) {
+                let mut camel = to_pascal_case(field);
+                camel.replace_range(..1, &camel[..1].to_lowercase());
+                definitions.push(format!("        #[serde(rename = \"{}\")]", camel));
+            }
+            definitions.push(format!("        pub {}: {},", ident, rust_type));
+            conversions.push(format!("                {}: {},", ident, convert));
+        }
+        module.push_str(&format!(
+            "\n    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]\n\
+             \x20   #[serde(rename_all = \"camelCase\")]\n\
+             \x20   pub struct {name} {{\n{definitions}\n    }}\n\n\
+             \x20   impl From<{wit}::{name}> for {name} {{\n\
+             \x20       fn from(value: {wit}::{name}) -> Self {{\n\
+             \x20           Self {{\n{conversions}\n            }}\n\
+             \x20       }}\n\
+             \x20   }}\n\n\
+             \x20   impl From<{name}> for {wit}::{name} {{\n\
+             \x20       fn from(value: {name}) -> Self {{\n\
+             \x20           Self {{\n{conversions}\n            }}\n\
+             \x20       }}\n\
+             \x20   }}\n",
+            name = type_name,
+            wit = wit_module,
+            definitions = definitions.join("\n"),
+            conversions = conversions.join("\n"),
+        ));
+    }
+    module.push('}');
+    module
+}
+
+// Derives wit_bindgen::generate! gives every record, which a replacement definition needs too
+const RECORD_DERIVES: &[&str] = &[
+    "Debug", "Clone", "serde::Deserialize", "serde::Serialize", "process_macros::SerdeJsonInto",
+];
+
+// Generate the records configured under `[types]` with their extra derives and attributes, in a
+// `custom_types` module that `wit_bindgen::generate!` uses in place of its own definitions.
+// Returns the `with` entries mapping the WIT types to them and the module, or None when no
+// types are configured.
+fn generate_custom_types(
+    config: &Config,
+    api_dir: &Path,
+    package: &wit_source::Package,
+) -> Result<Option<(Vec<String>, String)>> {
+    if config.types.is_empty() {
+        return Ok(None);
+    }
+    
+    // (interface, record, fields, doc comment) of every record in the api directory
+    let mut all_records = Vec::new();
+    for wit_file in find_interface_files(api_dir) {
+        let content = text::read_normalized(&wit_file)
+            .with_context(|| format!("Failed to read {}", wit_file.display()))?;
+        // Files that don't parse were already reported as skipped
+        let Ok(interfaces) = wit_source::parse(&paths::to_slash(&wit_file), &content) else {
+            continue;
+        };
+        for interface in interfaces {
+            for definition in interface.definitions {
+                if definition.keyword == "record" && !definition.name.contains("-signature-") {
+                    all_records.push((interface.name.clone(), definition.name, definition.fields, definition.docs));
+                }
+            }
+        }
+    }
+    
+    let mut with = Vec::new();
+    let mut module = String::from(
+        "/// WIT records with the derives and attributes configured under [types] in hyper-bindgen.toml,\n\
+         /// used by wit_bindgen::generate! in place of its own definitions\n\
+         pub mod custom_types {\n    use crate::*;\n",
+    );
+    for (key, type_config) in &config.types {
+        let (interface, name) = match key.split_once('.') {
+            Some((interface, name)) => (Some(interface), name),
+            None => (None, key.as_str()),
+        };
+        let matches: Vec<_> = all_records
+            .iter()
+            .filter(|(iface, record, _, _)| record == name && interface.is_none_or(|i| i == iface))
+            .collect();
+        let (interface, name, fields, docs) = match matches[..] {
+            [record] => record,
+            [] => bail!("[types.\"{}\"] in {} matches no record in the api directory", key, CONFIG_FILE),
+            _ => bail!(
+                "[types.\"{}\"] in {} matches records in several interfaces, use \"<interface>.{}\"",
+                key, CONFIG_FILE, name
+            ),
+        };
+        if let Some(attr) = type_config.attrs.iter().find(|attr| !attr.starts_with("#[")) {
+            bail!("Attribute `{}` for [types.\"{}\"] in {} must be written as `#[...]`", attr, key, CONFIG_FILE);
+        }
+        
+        let mut derives: Vec<&str> = RECORD_DERIVES.to_vec();
+        for derive in &type_config.derives {
+            if !derives.contains(&derive.as_str()) {
+                derives.push(derive);
+            }
+        }
+        let type_name = to_pascal_case(name);
+        log!("  Generating {} with derives {}", type_name, derives.join(", "));
+        
+        // The record keeps the documentation wit_bindgen::generate! would have given it
+        module.push('\n');
+        for line in docs.iter().filter(|line| !line.starts_with('@')) {
+            module.push_str(&format!("    ///{}{}\n", if line.is_empty() { "" } else { " " }, line));
+        }
+        module.push_str(&format!("    #[derive({})]\n", derives.join(", ")));
+        for attr in &type_config.attrs {
+            module.push_str(&format!("    {}\n", attr));
+        }
+        module.push_str(&format!("    pub struct {} {{\n", type_name));
+        for (field, wit_type) in fields {
+            module.push_str(&format!("        pub {}: {},\n", to_rust_identifier(field), wit_type_to_rust(wit_type, &config.type_extensions)));
+        }
+        module.push_str("    }\n");
+        with.push(format!(
+            "\"{}/{}\": crate::custom_types::{}",
+            package.qualify(interface),
+            name,
+            type_name
+        ));
+    }
+    module.push_str("}\n\n");
+    Ok(Some((with, module)))
+}
+
+// Where the WIT files are copied to, and the path to that directory from the generated crate's
+// manifest as passed to `wit_bindgen::generate!`. Defaults to `wit` inside the crate so the
+// build doesn't depend on where cargo puts its target directory.
+pub fn wit_location(base_dir: &Path, crate_dir: &Path, config: &Config) -> Result<(PathBuf, String)> {
+    let Some(target_dir) = &config.target_dir else {
+        return Ok((crate_dir.join("wit"), "wit".to_string()));
+    };
+    if config.publish.enabled {
+        bail!("A target directory can't be used in publish mode: the published crate must contain its WIT files");
+    }
+
+    // Relative target directories are resolved from the workspace root, like CARGO_TARGET_DIR
+    let wit_dir = base_dir.join(target_dir).join("wit");
+    let wit_path = paths::relative_path(crate_dir, &wit_dir);
+    Ok((wit_dir, wit_path))
+}
+
+// A Cargo feature of a full generated crate
+struct CrateFeature {
+    name: &'static str,
+    // Features and dependency features it enables
+    enables: &'static [&'static str],
+    description: &'static str,
+}
+
+// Features of a full generated crate, written to its Cargo.toml with their descriptions and
+// listed in its README
+fn crate_features(config: &Config) -> Vec<CrateFeature> {
+    let mut features = Vec::new();
+    if config.fake_transport() {
+        features.push(CrateFeature {
+            name: "fake-send",
+            enables: &[],
+            description: "Route stub calls to handlers registered with `fake::register` instead of sending them",
+        });
+    }
+    if config.stub_style == StubStyle::MaybeAsync {
+        features.push(CrateFeature {
+            name: "sync",
+            enables: &["maybe-async/is_sync"],
+            description: "Make the stubs blocking functions instead of async ones",
+        });
+    }
+    features
+}
+
+// Render the Cargo.toml of a generated crate, with registry-ready metadata in publish mode.
+// Keys are written in a fixed order, and dependencies sorted by name, so that enabling an option
+// only adds its own lines to the file.
+fn render_cargo_toml(config: &Config, crate_name: &str, profile: &ProfileConfig) -> Result<String> {
+    let publish = &config.publish;
+    let mut package = Table::new();
+    package.insert("name", value(crate_name));
+    if publish.enabled {
+        let mut required = vec![("description", &publish.description), ("license", &publish.license)];
+        // The git dependency on hyperware_app_common is rejected by registries
+        if !profile.types_only {
+            required.push(("app-common-version", &publish.app_common_version));
+        }
+        let missing: Vec<&str> = required
+            .iter()
+            .filter(|(_, value)| value.is_none())
+            .map(|(key, _)| *key)
+            .collect();
+        if !missing.is_empty() {
+            bail!(
+                "Publishing {} requires these keys in the [publish] section of {}: {}",
+                crate_name,
+                CONFIG_FILE,
+                missing.join(", ")
+            );
+        }
+
+        package.insert("version", value(publish.version.as_deref().unwrap_or("0.1.0")));
+        package.insert("edition", value("2021"));
+        for (key, field) in [
+            ("description", &publish.description),
+            ("license", &publish.license),
+            ("repository", &publish.repository),
+        ] {
+            if let Some(field) = field {
+                package.insert(key, value(field.as_str()));
+            }
+        }
+    } else {
+        package.insert("version", value("0.1.0"));
+        package.insert("edition", value("2021"));
+        package.insert("publish", value(false));
+    }
+
+    // (name, version or inline table) of each dependency; types-only crates just need what the
+    // generate! block and its derives use
+    let mut dependencies: BTreeMap<&str, Item> = BTreeMap::new();
+    dependencies.insert("process_macros", value("0.1.0"));
+    dependencies.insert("serde", dependency("1.0", &[("features", Array::from_iter(["derive"]).into())]));
+    dependencies.insert("serde_json", value("1.0"));
+    dependencies.insert("wit-bindgen", value("0.41.0"));
+    let features = if profile.types_only { Vec::new() } else { crate_features(config) };
+    if !profile.types_only {
+        dependencies.insert("anyhow", value("1.0"));
+        dependencies.insert(
+            "hyperware_process_lib",
+            dependency("1.0.4", &[("features", Array::from_iter(["logging"]).into())]),
+        );
+        dependencies.insert("futures-util", value("0.3"));
+        dependencies.insert("futures", value("0.3"));
+        dependencies.insert("once_cell", value("1.20.2"));
+        dependencies.insert("uuid", dependency("1.0", &[]));
+        dependencies.insert(
+            "hyperware_app_common",
+            match &publish.app_common_version {
+                Some(version) if publish.enabled => value(version.as_str()),
+                _ => {
+                    let mut git = InlineTable::new();
+                    git.insert("git", "https://github.com/hyperware-ai/hyperprocess-macro".into());
+                    value(git)
+                }
+            },
+        );
+        if config.stub_style == StubStyle::MaybeAsync {
+            dependencies.insert("maybe-async", value("0.2"));
+        }
+        for extension in config.type_extensions.values() {
+            for (name, version) in &extension.dependencies {
+                dependencies.insert(name, value(version.as_str()));
+            }
+        }
+
+        // docs.rs builds default features only; document the optional modules, but keep the async
+        // stubs `sync` would turn blocking
+        let documented: Vec<&str> = features
+            .iter()
+            .map(|feature| feature.name)
+            .filter(|name| *name != "sync")
+            .collect();
+        if publish.enabled && !documented.is_empty() {
+            let mut docs_rs = Table::new();
+            docs_rs.insert("features", value(Array::from_iter(documented)));
+            package.insert("metadata", implicit_table([("docs", implicit_table([("rs", Item::Table(docs_rs))]))]));
+        }
+    }
+
+    let mut manifest = Document::new();
+    manifest.insert("package", Item::Table(package));
+    let mut dependency_table = Table::new();
+    for (name, item) in dependencies {
+        dependency_table.insert(name, item);
+    }
+    manifest.insert("dependencies", Item::Table(dependency_table));
+    if !features.is_empty() {
+        let mut table = Table::new();
+        for feature in &features {
+            table.insert(feature.name, value(Array::from_iter(feature.enables.iter().copied())));
+            if let Some(decor) = table.key_decor_mut(feature.name) {
+                decor.set_prefix(format!("# {}\n", feature.description));
+            }
+        }
+        manifest.insert("features", Item::Table(table));
+    }
+    let mut lib = Table::new();
+    lib.insert("crate-type", value(Array::from_iter(["cdylib", "lib"])));
+    manifest.insert("lib", Item::Table(lib));
+
+    // The cfg cargo-llvm-cov sets is unknown to cargo, which would warn about it in every build
+    if config.coverage_off {
+        let mut unexpected_cfgs = InlineTable::new();
+        unexpected_cfgs.insert("level", "warn".into());
+        unexpected_cfgs.insert("check-cfg", Array::from_iter(["cfg(coverage_nightly)"]).into());
+        let mut rust = Table::new();
+        rust.insert("unexpected_cfgs", value(unexpected_cfgs));
+        manifest.insert("lints", implicit_table([("rust", Item::Table(rust))]));
+    }
+
+    // Tables are separated by a blank line
+    for (index, (_, item)) in manifest.iter_mut().enumerate() {
+        if let (Some(table), true) = (item.as_table_mut(), index > 0) {
+            table.decor_mut().set_prefix("\n");
+        }
+    }
+    Ok(manifest.to_string())
+}
+
+// Escape the characters of a path that are special in a regex
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// A dependency as an inline table of its version and other keys
+fn dependency(version: &str, keys: &[(&str, toml_edit::Value)]) -> Item {
+    let mut table = InlineTable::new();
+    table.insert("version", version.into());
+    for (key, key_value) in keys {
+        table.insert(*key, key_value.clone());
+    }
+    value(table)
+}
+
+// A table written only through its subtables, like `package` in `[package.metadata.docs.rs]`
+fn implicit_table<const N: usize>(items: [(&str, Item); N]) -> Item {
+    let mut table = Table::new();
+    table.set_implicit(true);
+    for (key, item) in items {
+        table.insert(key, item);
+    }
+    Item::Table(table)
+}
+
+// Check that cargo accepts the generated crate as a package.
+// The build step is skipped since the crate only compiles for the wasm target.
+fn verify_package(crate_dir: &Path) -> Result<()> {
+    log!("Verifying {} with `cargo package`", crate_dir.display());
+    let output = Command::new("cargo")
+        .args(["package", "--allow-dirty", "--no-verify"])
+        .current_dir(crate_dir)
+        .output()
+        .with_context(|| "Failed to run `cargo package`")?;
+
+    if !output.status.success() {
+        bail!(
+            "`cargo package` failed for {}:\n{}",
+            crate_dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    log!("{} is ready to publish", crate_dir.display());
+    Ok(())
+}
+
+// Generate the match arm of the `cli` module invoking a function, keyed `interface.function`
+fn generate_cli_arm(signature: &SignatureStruct, interface_name: &str, module: &str, envelope: Envelope, extensions: &BTreeMap<String, TypeExtension>) -> String {
+    let mut param_names = Vec::new();
+    let mut param_types = Vec::new();
+    let mut return_type = "()".to_string();
+    for field in &signature.fields {
+        match field.name.as_str() {
+            "target" => {}
+            "returning" if signature.binary_encoding("returning") == Some(BinaryEncoding::Base64) => {
+                return_type = "bytes::Base64".to_string();
+            }
+            "returning" => return_type = wit_type_to_rust(&field.wit_type, extensions),
+            _ => {
+                param_names.push(param_identifier(&field.name));
+                param_types.push(wit_type_to_rust(&field.wit_type, extensions));
+            }
+        }
+    }
+    
+    let request = request_expression(&to_pascal_case(&signature.function_name), &request_arguments(signature), interface_name, envelope);
+    let call = format!("call::<{}>(target, {}, {}::DEFAULT_TIMEOUT_SECS)", return_type, request, module);
+    if param_names.is_empty() {
+        return format!("            \"{}.{}\" => {},\n", interface_name, signature.function_name, call);
+    }
+    // Arguments are deserialized as a tuple, so `--json` is an array even for one argument
+    let trailing_comma = if param_names.len() == 1 { "," } else { "" };
+    format!(
+        "            \"{}.{}\" => {{\n                let ({}{}): ({}{}) = arguments(json)?;\n                {}\n            }}\n",
+        interface_name,
+        signature.function_name,
+        param_names.join(", "),
+        trailing_comma,
+        param_types.join(", "),
+        trailing_comma,
+        call
+    )
+}
+
+// Generate the `cli` module the debugging binary calls, dispatching on `interface.function`
+fn generate_cli_module(crate_name: &str, arms: &BTreeMap<String, String>) -> String {
+    let usage = format!(
+        "usage: {}-cli --function <interface>.<function> --target <node>@<process:package:publisher> [--json <arguments>]",
+        crate_name
+    );
+    let functions: Vec<String> = arms.keys().map(|key| format!("\"{}\"", key)).collect();
+    let mut module = format!(
+        "/// Invoke any stub by name, for the `{crate_name}-cli` debugging binary\n\
+         pub mod cli {{\n    use crate::*;\n\n    \
+         /// Command line of the debugging binary\n    \
+         pub const USAGE: &str = \"{usage}\";\n\n    \
+         /// Functions `run` can invoke, as `interface.function`\n    \
+         pub const FUNCTIONS: &[&str] = &[{functions}];\n\n    \
+         /// Parse the arguments of the binary, send the request and render the response as pretty JSON.\n    \
+         /// `--json` takes the arguments of the function as a JSON array, `[]` when omitted.\n    \
+         pub fn run(args: &[String]) -> Result<String, String> {{\n        \
+         let (mut function, mut target, mut json) = (None, None, None);\n        \
+         let mut args = args.iter();\n        \
+         while let Some(arg) = args.next() {{\n            \
+         let value = args.next().ok_or_else(|| format!(\"missing value for {{}}\\n{{}}\", arg, USAGE));\n            \
+         match arg.as_str() {{\n                \
+         \"--function\" => function = Some(value?),\n                \
+         \"--target\" => target = Some(value?),\n                \
+         \"--json\" => json = Some(value?),\n                \
+         _ => return Err(format!(\"unknown argument {{}}\\n{{}}\", arg, USAGE)),\n            \
+         }}\n        }}\n        \
+         let function = function.ok_or_else(|| USAGE.to_string())?;\n        \
+         let target: Address = target\n            \
+         .ok_or_else(|| USAGE.to_string())?\n            \
+         .parse()\n            \
+         .map_err(|e| format!(\"invalid --target: {{:?}}\", e))?;\n        \
+         let target = &target;\n        \
+         let json = json.map_or(\"[]\", String::as_str);\n        \
+         match function.as_str() {{\n",
+        crate_name = crate_name,
+        usage = usage,
+        functions = functions.join(", "),
+    );
+    for arm in arms.values() {
+        module.push_str(arm);
+    }
+    module.push_str(
+        "            _ => Err(format!(\"unknown function {}, expected one of: {}\", function, FUNCTIONS.join(\", \"))),\n        }\n    }\n",
+    );
+    module.push_str(CLI_HELPERS);
+    module.push('\n');
+    module
+}
+
+// Generate the source of the debugging binary, a thin wrapper around the `cli` module
+fn generate_cli_bin(crate_name: &str, coverage_off: bool) -> String {
+    format!(
+        "{}//! Debugging binary invoking any stub of {crate_name}:\n\
+         //! `{crate_name}-cli --function <interface>.<function> --target <address> --json '[<arguments>]'`\n\
+         //! It sends with the process lib, so it has to run inside a node.\n\n\
+         {}fn main() {{\n    \
+         let args: Vec<String> = std::env::args().skip(1).collect();\n    \
+         match {crate_ident}::cli::run(&args) {{\n        \
+         Ok(response) => println!(\"{{}}\", response),\n        \
+         Err(error) => {{\n            \
+         eprintln!(\"{{}}\", error);\n            \
+         std::process::exit(1);\n        \
+         }}\n    }}\n}}\n",
+        migration::render_header(),
+        if coverage_off { COVERAGE_OFF } else { "" },
+        crate_name = crate_name,
+        crate_ident = crate_name.replace('-', "_"),
+    )
+}
+
+// Doc lines under the heading of an interface module, and whether the crate prelude re-exports it
+struct ModuleHeader {
+    docs: Vec<String>,
+    in_prelude: bool,
+}
+
+// Header of an interface's module: its documentation URL, ownership and stability. Internal
+// interfaces are left out of the crate prelude, so using them takes naming their module.
+fn module_header(config: &Config, interface: &ParsedInterface) -> ModuleHeader {
+    let mut docs = Vec::new();
+    if let Some(url) = interface.docs_url() {
+        docs.push(format!("Documentation: <{}>", url));
+    }
+    let settings = config.interfaces.get(&interface.name);
+    if let Some(owner) = settings.and_then(|settings| settings.owner.as_deref()) {
+        docs.push(format!("Owner: {}", owner));
+    }
+    let stability = config.stability_for(&interface.name);
+    match stability {
+        Some(Stability::Internal) => docs.push(
+            "Stability: internal. Only for the processes allowed to import it in hyper-bindgen.toml".to_string(),
+        ),
+        Some(stability) => docs.push(format!("Stability: {}", stability)),
+        None => {}
+    }
+    ModuleHeader {
+        docs,
+        in_prelude: stability != Some(Stability::Internal),
+    }
+}
+
+// Generate the body of a full lib.rs: runtime imports, helper modules and one module of stubs per interface
+fn generate_stubs_lib(
+    config: &Config,
+    interface_use_statements: &[String],
+    module_contents: &BTreeMap<String, String>,
+    nesting: &[&str],
+    module_headers: &BTreeMap<String, ModuleHeader>,
+    serialized_calls: bool,
+    cached_calls: bool,
+) -> String {
+    let mut lib = String::from("/// Generated caller utilities for RPC function stubs\n\n");
+    
+    // Add global imports
+    lib.push_str("pub use hyperware_app_common::SendResult;\n");
+    if config.stub_style == StubStyle::MaybeAsync {
+        lib.push_str("#[cfg(not(feature = \"sync\"))]\n");
+        lib.push_str("pub use hyperware_app_common::send;\n");
+    } else {
+        lib.push_str("pub use hyperware_app_common::send;\n");
+    }
+    lib.push_str("use hyperware_process_lib::Address;\n");
+    lib.push_str("use serde_json::json;\n\n");
+    if config.stub_style == StubStyle::MaybeAsync {
+        lib.push_str(SYNC_SEND);
+    }
+    
+    // Boxed stubs return a named future type so callers can store them in collections
+    if config.stub_style == StubStyle::Boxed {
+        lib.push_str("/// Future returned by the generated stubs\n");
+        lib.push_str("pub type StubFuture<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = SendResult<T>> + Send + 'a>>;\n\n");
+    }
+    
+    // Add interface use statements
+    if !interface_use_statements.is_empty() {
+        lib.push_str("// Import types from each interface\n");
+        for use_stmt in interface_use_statements {
+            lib.push_str(&format!("{}\n", use_stmt));
+        }
+        lib.push('\n');
+    }
+    
+    lib.push_str(PIPELINE_MODULE);
+    lib.push('\n');
+    
+    if serialized_calls {
+        lib.push_str(SERIAL_MODULE);
+        lib.push('\n');
+    }
+    
+    if cached_calls {
+        lib.push_str(CACHE_MODULE);
+        lib.push('\n');
+    }
+    
+    if config.fake_transport() {
+        lib.push_str(&generate_fake_module(fake_cfg(config)));
+        lib.push('\n');
+    }
+    
+    if config.scoped_client {
+        lib.push_str(SCOPE_MODULE);
+        lib.push('\n');
+    }
+    
+    let module_paths: Vec<String> = module_contents
+        .keys()
+        .filter(|module_name| module_headers.get(*module_name).is_none_or(|header| header.in_prelude))
+        .map(|module_name| nesting.iter().copied().chain([module_name.as_str()]).collect::<Vec<_>>().join("::"))
+        .collect();
+    lib.push_str(&generate_crate_prelude(&module_paths));
+    
+    // Add all modules with their content
+    let mut modules = String::new();
+    for (module_name, module_content) in module_contents {
+        modules.push_str(&format!("/// Generated RPC stubs for the {} interface\n", module_name));
+        if let Some(header) = module_headers.get(module_name).filter(|header| !header.docs.is_empty()) {
+            modules.push_str("///\n");
+            for line in &header.docs {
+                modules.push_str(&format!("/// {}\n", line));
+            }
+        }
+        modules.push_str(&format!("pub mod {} {{\n", module_name));
+        modules.push_str("    use crate::*;\n\n");
+        modules.push_str(&format!("    {}\n", module_content.replace("\n", "\n    ")));
+        modules.push_str("}\n\n");
+    }
+    
+    // Nest them in the modules of `module-path`, innermost first
+    for (depth, segment) in nesting.iter().enumerate().rev() {
+        let indented: Vec<String> = modules
+            .trim_end()
+            .lines()
+            .map(|line| if line.is_empty() { String::new() } else { format!("    {}", line) })
+            .collect();
+        let doc = if depth == 0 { "/// Generated RPC stubs, one module per interface\n" } else { "" };
+        modules = format!("{}pub mod {} {{\n{}\n}}\n\n", doc, segment, indented.join("\n"));
+    }
+    lib.push_str(&modules);
+    
+    lib
+}
+
+// Generate the body of a types-only lib.rs, re-exporting the WIT types without any stubs
+fn generate_types_only_lib(interface_use_statements: &[String]) -> String {
+    let mut lib = String::from("/// Generated WIT types, without RPC stubs\n\n");
+    for use_stmt in interface_use_statements {
+        lib.push_str(&format!("{}\n", use_stmt));
+    }
+    lib
+}
+
+// Whether a function is excluded from stub generation with `@no-stub` or the configuration
+fn is_no_stub(config: &Config, interface: &ParsedInterface, signature: &SignatureStruct) -> bool {
+    signature.annotation("no-stub").is_some() || config.is_no_stub(&interface.name, &signature.function_name)
+}
+
+// Generate the README of a generated crate: its modules and stubs with links to their WIT
+// sources, how targets are addressed and the wire format the stubs use
+#[allow(clippy::too_many_arguments)]
+fn generate_crate_readme(
+    config: &Config,
+    crate_dir: &Path,
+    base_dir: &Path,
+    crate_name: &str,
+    worlds: &[String],
+    package: &wit_source::Package,
+    interfaces: &[ParsedInterface],
+    interface_imports: &[String],
+) -> String {
+    let envelope = config.envelope_for(&worlds[0]);
+    let link = |file: &str| paths::relative_path(crate_dir, &base_dir.join(file));
+    let crate_ident = to_snake_case(crate_name);
+
+    let mut readme = format!(
+        "# {}\n\n\
+         Generated by hyper-bindgen {} from the WIT files of the {} {}. \
+         Do not edit this crate by hand: change the processes or WIT files and run hyper-bindgen again.\n\n",
+        crate_name,
+        migration::GENERATOR_VERSION,
+        worlds.iter().map(|world| format!("`{}`", world)).collect::<Vec<_>>().join(", "),
+        if worlds.len() == 1 { "world" } else { "worlds" }
+    );
+    if *package != wit_source::Package::host() {
+        readme.push_str(&format!("The WIT files declare the `{}` package", package));
+        if package.version.is_some() {
+            readme.push_str(", whose version is exported as `PACKAGE_VERSION` for compatibility checks");
+        }
+        readme.push_str(".\n\n");
+    }
+    readme.push_str("## Modules\n");
+
+    if interfaces.is_empty() {
+        readme.push_str("\nThis crate only contains the types of the interfaces, without stubs:\n\n");
+        for interface in interface_imports {
+            readme.push_str(&format!(
+                "- `{}`: `{}`\n",
+                interface,
+                package.module(interface).replacen("crate", &crate_ident, 1)
+            ));
+        }
+        return readme;
+    }
+
+    for interface in interfaces {
+        let module = module_path(config, &interface.name);
+        readme.push_str(&format!(
+            "\n### `{}`\n\nStubs for the `{}` interface, generated from [{}]({}).\n",
+            module,
+            interface.name,
+            interface.file,
+            link(&interface.file)
+        ));
+        if let Some(url) = interface.docs_url() {
+            readme.push_str(&format!("Documentation: <{}>\n", url));
+        }
+        let settings = config.interfaces.get(&interface.name);
+        if let Some(owner) = settings.and_then(|settings| settings.owner.as_deref()) {
+            readme.push_str(&format!("Owner: {}\n", owner));
+        }
+        if let Some(stability) = config.stability_for(&interface.name) {
+            readme.push_str(&format!("Stability: **{}**\n", stability));
+        }
+        if let Some(settings) = settings.filter(|_| config.stability_for(&interface.name) == Some(Stability::Internal)) {
+            let importers = match settings.allowed_importers.as_slice() {
+                [] => "no process".to_string(),
+                importers => importers.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", "),
+            };
+            readme.push_str(&format!(
+                "\nThis interface is internal: it is not in the crate prelude, and only {} may use it.\n",
+                importers
+            ));
+        }
+        let stubs: Vec<String> = interface
+            .signatures
+            .iter()
+            .filter(|signature| signature.attr_type != "http" && !is_no_stub(config, interface, signature))
+            .map(|signature| {
+                format!(
+                    "| `{}` ({}) | `{}::{}` | [{}]({}#L{}) |\n",
+                    signature.function_name,
+                    signature.attr_type,
+                    module,
+                    stub_function_name(signature),
+                    signature.source,
+                    link(&signature.source.file),
+                    signature.source.line
+                )
+            })
+            .collect();
+        if stubs.is_empty() {
+            readme.push_str("\nIt has no callable stubs.\n");
         } else {
-            params.push(format!("{}: {}", field_name_snake, rust_type));
-            param_names.push(field_name_snake);
+            readme.push_str("\n| Function | Stub | Source |\n|---|---|---|\n");
+            readme.push_str(&stubs.concat());
         }
     }
-    
-    // First parameter is always target
-    let all_params = if target_param.is_empty() {
-        params.join(", ")
-    } else {
-        format!("target: {}{}", target_param, if params.is_empty() { "" } else { ", " }) + &params.join(", ")
+
+    readme.push_str(&format!(
+        "\nEvery module also has a `Request` enum decoding the requests it accepts and a `prelude`; \
+         `use {}::prelude::*;` brings every stub and type into scope.\n\n\
+         ## Calling a process\n\n\
+         Each stub takes the `Address` of the process to call as its first argument. An address is \
+         `<node>@<process>:<package>:<publisher>`, e.g. `our-node.os@chat:chat:sys`, and can be parsed \
+         with `str::parse::<Address>()` or built with `Address::new(node, (process, package, publisher))`. \
+         Stubs send the request and wait up to their module's `DEFAULT_TIMEOUT_SECS` for the response, returning a `SendResult` \
+         that is `SendResult::Success` with the decoded response when the call succeeded.\n\n\
+         ## Wire format\n\n",
+        crate_ident
+    ));
+    let example = match envelope {
+        Envelope::Plain => "{\"SendMessage\": [message, channel]}".to_string(),
+        Envelope::Namespaced => "{\"chat\": {\"SendMessage\": [message, channel]}}".to_string(),
+        Envelope::Traced => "{\"v\": 1, \"trace\": \"...\", \"body\": {\"SendMessage\": [message, channel]}}".to_string(),
     };
+    readme.push_str(&format!(
+        "Requests are JSON objects keyed by the PascalCase function name ({} envelope): `{}`. \
+         Parameters are sent as an array in declaration order, also when there is only one \
+         (`{{\"SetStatus\": [status]}}`), and functions without parameters send an empty object \
+         (`{{\"Ping\": {{}}}}`). Responses are the JSON encoding of the return value.\n",
+        match envelope {
+            Envelope::Plain => "plain",
+            Envelope::Namespaced => "namespaced, wrapped in the interface name",
+            Envelope::Traced => "traced, wrapped with a version and trace id",
+        },
+        example
+    ));
     
-    // Wrap the return type in SendResult
-    let wrapped_return_type = format!("SendResult<{}>", return_type);
-    
-    // For HTTP endpoints, generate commented-out implementation
-    if signature.attr_type == "http" {
-        let default_value = generate_default_value(&return_type);
-        
-        // Add underscore prefix to all parameters for HTTP stubs
-        let all_params_with_underscore = if target_param.is_empty() {
-            params.iter()
-                .map(|param| {
-                    let parts: Vec<&str> = param.split(':').collect();
-                    if parts.len() == 2 {
-                        format!("_{}: {}", parts[0], parts[1])
-                    } else {
-                        format!("_{}", param)
-                    }
-                })
-                .collect::<Vec<String>>()
-                .join(", ")
-        } else {
-            let target_with_underscore = format!("_target: {}", target_param);
-            if params.is_empty() {
-                target_with_underscore
-            } else {
-                let params_with_underscore = params.iter()
-                    .map(|param| {
-                        let parts: Vec<&str> = param.split(':').collect();
-                        if parts.len() == 2 {
-                            format!("_{}: {}", parts[0], parts[1])
-                        } else {
-                            format!("_{}", param)
-                        }
-                    })
-                    .collect::<Vec<String>>()
-                    .join(", ");
-                format!("{}, {}", target_with_underscore, params_with_underscore)
-            }
-        };
-        
-        return format!(
-            "/// Generated stub for `{}` {} RPC call\n/// HTTP endpoint - uncomment to implement\n// pub async fn {}({}) -> {} {{\n//     // TODO: Implement HTTP endpoint\n//     SendResult::Success({})\n// }}",
-            signature.function_name,
-            signature.attr_type,
-            full_function_name,
-            all_params_with_underscore,
-            wrapped_return_type,
-            default_value
-        );
+    let features = crate_features(config);
+    if !features.is_empty() {
+        readme.push_str("\n## Features\n\n| Feature | Effect |\n|---|---|\n");
+        for feature in features {
+            readme.push_str(&format!("| `{}` | {} |\n", feature.name, feature.description));
+        }
+    }
+    readme
+}
+
+// Generate the `wit_sources` module embedding the WIT files the crate was generated from,
+// keyed by file name without extension (the interface or world name)
+fn generate_wit_sources(src_dir: &Path, wit_dir: &Path, api_dir: &Path) -> String {
+    let mut sources = Vec::new();
+    for entry in WalkDir::new(api_dir)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "wit") {
+            let file_name = path.file_name().unwrap();
+            let name = path.file_stem().unwrap().to_string_lossy();
+            // Include the normalized copy next to the crate rather than the api directory itself
+            let include_path = paths::relative_path(src_dir, &wit_dir.join(file_name));
+            sources.push(format!("        (\"{}\", include_str!(\"{}\")),", name, include_path));
+        }
     }
     
-    // Format JSON parameters correctly
-    let json_params = if param_names.is_empty() {
-        // No parameters case
-        format!("json!({{\"{}\" : {{}}}})", pascal_function_name)
-    } else if param_names.len() == 1 {
-        // Single parameter case
-        format!("json!({{\"{}\": {}}})", pascal_function_name, param_names[0])
-    } else {
-        // Multiple parameters case - use tuple format
-        format!("json!({{\"{}\": ({})}})", 
-                pascal_function_name, 
-                param_names.join(", "))
-    };
-    
-    // Generate function with implementation using send
     format!(
-        "/// Generated stub for `{}` {} RPC call\npub async fn {}({}) -> {} {{\n    let request = {};\n    send::<{}>(&request, target, 30).await\n}}",
-        signature.function_name,
-        signature.attr_type,
-        full_function_name,
-        all_params,
-        wrapped_return_type,
-        json_params,
-        return_type
+        "/// The WIT files this crate was generated from, so a process can describe its API at runtime\n\
+         pub mod wit_sources {{\n\
+         \x20   /// WIT text of every interface and world file, keyed by its name without `.wit`\n\
+         \x20   pub const SOURCES: &[(&str, &str)] = &[\n\
+         {}\n\
+         \x20   ];\n\
+         \n\
+         \x20   /// WIT text of an interface or world\n\
+         \x20   pub fn wit_for(name: &str) -> Option<&'static str> {{\n\
+         \x20       SOURCES.iter().find(|(source, _)| *source == name).map(|(_, wit)| *wit)\n\
+         \x20   }}\n\
+         }}\n",
+        sources.join("\n")
     )
 }
 
-// Create the caller-utils crate with a single lib.rs file
-fn create_caller_utils_crate(api_dir: &Path, base_dir: &Path) -> Result<()> {
+// Create a generated crate with a single lib.rs file
+fn create_caller_utils_crate(
+    api_dir: &Path,
+    base_dir: &Path,
+    config: &Config,
+    crate_name: &str,
+    profile: &ProfileConfig,
+) -> Result<()> {
     // Path to the new crate
-    let caller_utils_dir = base_dir.join("caller-utils");
-    println!("Creating caller-utils crate at {}", caller_utils_dir.display());
+    let caller_utils_dir = base_dir.join(crate_name);
+    log!("Creating {} crate at {}", crate_name, caller_utils_dir.display());
+    
+    // Make sure an existing crate from an older hyper-bindgen doesn't get mixed with the new layout
+    migration::prepare_existing_crate(&caller_utils_dir)?;
     
     // Create directories
     fs::create_dir_all(&caller_utils_dir)?;
     fs::create_dir_all(caller_utils_dir.join("src"))?;
-    println!("Created project directory structure");
-    
-    // Create Cargo.toml with updated dependencies
-    let cargo_toml = r#"[package]
-name = "caller-utils"
-version = "0.1.0"
-edition = "2021"
-publish = false
-
-[dependencies]
-anyhow = "1.0"
-hyperware_process_lib = { version = "1.0.4", features = ["logging"] }
-process_macros = "0.1.0"
-futures-util = "0.3"
-serde = { version = "1.0", features = ["derive"] }
-serde_json = "1.0"
-hyperware_app_common = { git = "https://github.com/hyperware-ai/hyperprocess-macro" }
-once_cell = "1.20.2"
-futures = "0.3"
-uuid = { version = "1.0" }
-wit-bindgen = "0.41.0"
-
-[lib]
-crate-type = ["cdylib", "lib"]
-"#;
+    log!("Created project directory structure");
     
-    fs::write(caller_utils_dir.join("Cargo.toml"), cargo_toml)
-        .with_context(|| "Failed to write caller-utils Cargo.toml")?;
+    // Render Cargo.toml up front so invalid publish settings fail before anything is written
+    let cargo_toml = render_cargo_toml(config, crate_name, profile)?;
     
-    println!("Created Cargo.toml for caller-utils");
+    // Get the world names (preferably the types- versions); several worlds are joined in one
+    // named after the crate
+    let worlds = select_worlds(api_dir, &profile.worlds)?;
+    let world_name = match worlds.as_slice() {
+        [world] => world.clone(),
+        _ => format!("{}-worlds", to_kebab_case(crate_name)),
+    };
+    log!("Using world name for code generation: {}", world_name);
     
-    // Get the world name (preferably the types- version)
-    let world_name = find_world_name(api_dir)?;
-    println!("Using world name for code generation: {}", world_name);
+    // Interfaces live in the package the api files declare, hyperware:process by default
+    let declared_package = find_package(api_dir)?;
+    let package = declared_package.clone().unwrap_or_else(wit_source::Package::host);
     
-    // Get all interfaces from the world file
-    let interface_imports = find_interfaces_in_world(api_dir)?;
+    // Get all interfaces from the world files
+    let mut interface_imports = Vec::new();
+    for world in &worlds {
+        for interface in find_interfaces_in_world(api_dir, world)? {
+            if !interface_imports.contains(&interface) {
+                interface_imports.push(interface);
+            }
+        }
+    }
     
-    // Store all types from each interface
-    let mut interface_types: HashMap<String, Vec<String>> = HashMap::new();
+    resolve_imports(api_dir, base_dir, &interface_imports)?;
     
-    // Find all WIT files in the api directory to generate stubs
-    let mut wit_files = Vec::new();
-    for entry in WalkDir::new(api_dir)
-        .max_depth(1)
-        .into_iter()
-        .filter_map(Result::ok)
-    {
-        let path = entry.path();
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "wit") {
-            // Exclude world definition files
-            if let Ok(content) = fs::read_to_string(path) {
-                if !content.contains("world ") {
-                    wit_files.push(path.to_path_buf());
-                }
-            }
+    // Parse every interface file in the api directory; types-only crates have no stubs to generate
+    let mut interfaces = if profile.types_only {
+        Vec::new()
+    } else {
+        parse_interfaces(api_dir, base_dir)
+    };
+    report::check_diagnostics()?;
+    apply_stub_names(&mut interfaces, config.stub_names)?;
+    if config.strict_types {
+        let unknown = unknown_types(&interfaces);
+        for reference in &unknown {
+            log!("Error: {}", reference);
+        }
+        if !unknown.is_empty() {
+            bail!("{} references to types no interface defines (--strict-types)", unknown.len());
         }
     }
+    let nesting = module_path_segments(config)?;
+    
+    // Stubs of every world share the crate's support modules, so combined worlds need the same envelope
+    let envelope = config.envelope_for(&worlds[0]);
+    if let Some(world) = worlds.iter().find(|world| config.envelope_for(world) != envelope) {
+        bail!(
+            "Worlds {} and {} are generated into {} but use different envelopes; generate them into separate [profiles] with their own `worlds`",
+            worlds[0], world, crate_name
+        );
+    }
+    log!("Using {:?} request envelope for world {}", envelope, world_name);
     
-    println!("Found {} WIT interface files", wit_files.len());
+    // Record definitions, for the camelCase copies and the default values of HTTP stub examples,
+    // and enum definitions for the latter
+    let mut interface_records = BTreeMap::new();
+    let mut enums = Vec::new();
+    for interface in &interfaces {
+        if let Some(json_types) = &interface.json_types {
+            let (records, described_enums) = described_records_and_enums(json_types);
+            interface_records.insert(interface.name.as_str(), records);
+            enums.extend(described_enums);
+            continue;
+        }
+        let content = text::read_normalized(&base_dir.join(&interface.file))
+            .with_context(|| format!("Failed to read {}", interface.file))?;
+        interface_records.insert(interface.name.as_str(), parse_records(&interface.file, &content)?);
+        enums.extend(parse_enums(&interface.file, &content)?);
+    }
+    let types = example_types(interface_records.values().flatten(), &enums, &config.type_extensions);
     
-    // Generate content for each module and collect types
-    let mut module_contents = HashMap::<String, String>::new();
+    // Generate content for each module
+    let mut module_contents = BTreeMap::<String, String>::new();
+    let mut module_headers = BTreeMap::<String, ModuleHeader>::new();
+    // Match arms of the debugging binary, keyed `interface.function`
+    let mut cli_arms = BTreeMap::<String, String>::new();
     
-    for wit_file in &wit_files {
-        // Extract the interface name from the file name
-        let interface_name = wit_file.file_stem().unwrap().to_string_lossy();
-        let snake_interface_name = to_snake_case(&interface_name);
+    for interface in &interfaces {
+        let snake_interface_name = module_name(config, &interface.name);
+        log!("Processing interface: {} -> {}", interface.name, snake_interface_name);
+        if let Some(alias) = config.interfaces.get(&interface.name).and_then(|i| i.alias.as_ref()) {
+            let valid = alias.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid || RUST_KEYWORDS.contains(&alias.as_str()) || RESERVED_MODULE_NAMES.contains(&alias.as_str()) {
+                bail!(
+                    "Invalid alias `{}` for interface {} in {}: it must be a Rust identifier that isn't a keyword or one of {}",
+                    alias, interface.name, CONFIG_FILE, RESERVED_MODULE_NAMES.join(", ")
+                );
+            }
+        } else if snake_interface_name != to_snake_case(&interface.name) {
+            log!(
+                "Warning: interface {} would generate a module colliding with a generated item or keyword, naming it `{}` instead",
+                interface.name, snake_interface_name
+            );
+        }
+        
+        if interface.signatures.is_empty() {
+            report::skip(SkipReason::NoSignatures, format!("interface {}", interface.name), format!("no signature records in {}", interface.file));
+            continue;
+        }
+        if module_contents.contains_key(&snake_interface_name) {
+            bail!(
+                "Interface {} would generate module `{}`, which another interface already uses; set a different alias in {}",
+                interface.name, snake_interface_name, CONFIG_FILE
+            );
+        }
         
-        println!("Processing interface: {} -> {}", interface_name, snake_interface_name);
+        // Generate module content
+        let mut mod_content = generate_type_imports(interface, &interfaces, &package);
         
-        // Parse the WIT file to extract signature structs and types
-        match parse_wit_file(wit_file) {
-            Ok((signatures, types)) => {
-                // Store types for this interface
-                interface_types.insert(interface_name.to_string(), types);
-                
-                if signatures.is_empty() {
-                    println!("No signatures found in {}", wit_file.display());
-                    continue;
+        if let Some(json_types) = &interface.json_types {
+            mod_content.push_str(&generate_described_types(&interface.name, json_types, &config.type_extensions));
+        }
+        
+        // Stubs reference the interface's timeout by name so it can be audited in one place
+        let (timeout_secs, timeout_source) = config.timeout_for(&interface.name, interface.timeout_secs());
+        mod_content.push_str(&format!(
+            "/// Seconds the stubs of this interface wait for a response (set by {})\npub const DEFAULT_TIMEOUT_SECS: u64 = {};\n\n",
+            timeout_source, timeout_secs
+        ));
+        
+        if interface.signatures.iter().any(|signature| signature.retries() && !is_no_stub(config, interface, signature)) {
+            mod_content.push_str(&format!(
+                "/// Attempts `@idempotent` stubs make before returning a timeout or offline result\npub const IDEMPOTENT_ATTEMPTS: u32 = {};\n\n",
+                IDEMPOTENT_ATTEMPTS
+            ));
+        }
+        
+        // Namespaced requests need a way for the receiving process to strip the envelope
+        if envelope == Envelope::Namespaced {
+            mod_content.push_str(&generate_envelope_helpers(&interface.name));
+            mod_content.push_str("\n\n");
+        }
+        
+        if config.fake_transport() {
+            mod_content.push_str(&generate_fake_send(&interface.name, config.stub_style, fake_cfg(config)));
+            mod_content.push_str("\n\n");
+            let blob = interface.signatures.iter().any(|signature| signature.blob_param().is_some());
+            let vfs = interface.signatures.iter().any(SignatureStruct::hands_off);
+            if blob || vfs {
+                mod_content.push_str(&generate_fake_bytes(&interface.name, blob, vfs, fake_cfg(config)));
+                mod_content.push_str("\n\n");
+            }
+        }
+        
+        let self_stubs = config.self_stubs_for(&interface.name);
+        
+        // Functions marked internal stay callable by hand but get no public stub
+        let signatures: Vec<&SignatureStruct> = interface
+            .signatures
+            .iter()
+            .filter(|signature| {
+                let excluded = is_no_stub(config, interface, signature);
+                if excluded {
+                    report::skip(
+                        SkipReason::NoStub,
+                        format!("function {}.{} ({})", interface.name, signature.function_name, signature.attr_type),
+                        "marked no-stub",
+                    );
                 }
-                
-                // Generate module content
-                let mut mod_content = String::new();
-                
-                // Add function implementations
-                for signature in &signatures {
-                    let function_impl = generate_async_function(signature);
-                    mod_content.push_str(&function_impl);
+                !excluded
+            })
+            .collect();
+        
+        // Add function implementations
+        let mut response_structs = HashSet::new();
+        let stub_names: HashSet<String> = signatures.iter().map(|signature| stub_function_name(signature)).collect();
+        for signature in &signatures {
+            if signature.attr_type == "http" {
+                report::skip(
+                    SkipReason::HttpStub,
+                    format!("function {}.{} (http)", interface.name, signature.function_name),
+                    "HTTP stubs are generated commented out",
+                );
+            }
+            // Remote and local stubs of a function return the same struct
+            if let Some((name, fields)) = response_struct(signature, &interface.types, &config.type_extensions) {
+                if response_structs.insert(name.clone()) {
+                    mod_content.push_str(&generate_response_struct(signature, &name, &fields));
                     mod_content.push_str("\n\n");
                 }
-                
-                // Store the module content
-                module_contents.insert(snake_interface_name, mod_content);
-                
-                println!("Generated module content with {} function stubs", signatures.len());
-            },
-            Err(e) => {
-                println!("Error parsing WIT file {}: {}", wit_file.display(), e);
             }
+            let function_impl = generate_async_function(
+                signature,
+                &interface.name,
+                envelope,
+                config.stub_style,
+                &types,
+                self_stubs,
+                &interface.types,
+                &config.type_extensions,
+            );
+            mod_content.push_str(&function_impl);
+            mod_content.push_str("\n\n");
+            
+            let former_names: Vec<String> = signature
+                .former_stub_names()
+                .into_iter()
+                .filter(|name| {
+                    let taken = stub_names.contains(name);
+                    if taken {
+                        report::warning(
+                            signature.source.position(),
+                            format!("@renamed-from names `{}`, which is still a stub of {}; not generating it again", name, interface.name),
+                        );
+                    }
+                    !taken
+                })
+                .collect();
+            if !former_names.is_empty() {
+                mod_content.push_str(&generate_renamed_stubs(signature, &former_names, config.stub_style, &interface.types, &config.type_extensions));
+                mod_content.push_str("\n\n");
+            }
+            
+            // Remote and local stubs send the same request, so the binary has one entry for both
+            // The binary can't attach a blob to its requests
+            if config.cli && signature.attr_type != "http" && signature.blob_param().is_none() {
+                cli_arms
+                    .entry(format!("{}.{}", interface.name, signature.function_name))
+                    .or_insert_with(|| generate_cli_arm(signature, &interface.name, &module_path(config, &interface.name), envelope, &config.type_extensions));
+            }
+            
+            if let Some(item_type) = paginated_item_type(signature, &config.type_extensions) {
+                log!("  Generating auto-paging helper for {}", signature.function_name);
+                mod_content.push_str(&generate_paginated_helper(signature, &item_type, config.stub_style, &config.type_extensions));
+                mod_content.push_str("\n\n");
+            }
+        }
+        
+        // A WIT type named `request` would be shadowed by the generated enum
+        if interface.types.iter().any(|type_name| type_name == "request") {
+            log!("  Not generating Request enum for {}: it defines a `request` type", interface.name);
+        } else if interface.imports.iter().any(|import| import.local == "request") {
+            log!("  Not generating Request enum for {}: it uses a `request` type", interface.name);
+        } else {
+            mod_content.push_str(&generate_request_enum(interface, envelope, &config.type_extensions));
+            mod_content.push_str("\n\n");
+        }
+        
+        let has_types = interface_imports.contains(&interface.name);
+        // Copies of records holding resource handles, streams or futures couldn't be serialized either
+        let own_records: Vec<WitRecord> = interface_records[interface.name.as_str()]
+            .iter()
+            .filter(|(name, _)| !interface.unsendable_types.contains(name))
+            .cloned()
+            .collect();
+        if has_types && config.camel_case_types_for(&interface.name) && !own_records.is_empty() {
+            log!("  Generating camelCase copies of {} records", own_records.len());
+            mod_content.push_str(&generate_camel_case_types(&interface.name, &own_records, &package, &config.type_extensions));
+            mod_content.push_str("\n\n");
         }
+        
+        mod_content.push_str(&generate_interface_prelude(interface, &package, &signatures, has_types, config.stub_style, self_stubs, &config.type_extensions));
+        mod_content.push('\n');
+        
+        // Store the module content
+        module_headers.insert(snake_interface_name.clone(), module_header(config, interface));
+        module_contents.insert(snake_interface_name, mod_content);
+        
+        log!("Generated module content with {} function stubs", interface.signatures.len());
     }
     
-    // Create import statements for each interface using "hyperware::process::{interface_name}::*"
+    // Create import statements for each interface using "<package module>::{interface_name}::*"
     // Use a HashSet to track which interfaces we've already processed to avoid duplicates
-    let mut processed_interfaces = std::collections::HashSet::new();
+    let mut processed_interfaces = HashSet::new();
     let mut interface_use_statements = Vec::new();
     
     for interface_name in &interface_imports {
@@ -598,95 +4482,234 @@ crate-type = ["cdylib", "lib"]
         if processed_interfaces.insert(snake_interface_name.clone()) {
             // Create wildcard import for this interface
             interface_use_statements.push(
-                format!("pub use crate::hyperware::process::{}::*;", snake_interface_name)
+                format!("pub use {}::*;", package.module(interface_name))
             );
         }
     }
     
+    let (target_wit_dir, wit_path) = wit_location(base_dir, &caller_utils_dir, config)?;
+    
     // Create single lib.rs with all modules inline
-    let mut lib_rs = String::new();
+    let mut lib_rs = migration::render_header();
+    if config.coverage_off {
+        lib_rs.push_str(COVERAGE_OFF);
+    }
     
     // Updated wit_bindgen usage with explicit world name - FIXED: Removed unused imports
     lib_rs.push_str("wit_bindgen::generate!({\n");
-    lib_rs.push_str("    path: \"target/wit\",\n");
-    lib_rs.push_str(&format!("    world: \"{}\",\n", world_name));
+    lib_rs.push_str(&format!("    path: \"{}\",\n", wit_path));
+    // A world of a declared package is qualified with it, so wit-bindgen picks its version
+    let world_spec = match &declared_package {
+        Some(package) => package.qualify(&world_name),
+        None => world_name.clone(),
+    };
+    lib_rs.push_str(&format!("    world: \"{}\",\n", world_spec));
     lib_rs.push_str("    generate_unused_types: true,\n");
     lib_rs.push_str("    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],\n");
+    // Resource handles, streams and futures can't be serialized, nor can the types holding them
+    let unsendable_types: BTreeSet<&str> = interfaces
+        .iter()
+        .flat_map(|interface| interface.unsendable_types.iter().map(String::as_str))
+        .collect();
+    if !unsendable_types.is_empty() {
+        let names: Vec<String> = unsendable_types.iter().map(|name| format!("\"{}\"", name)).collect();
+        lib_rs.push_str(&format!("    additional_derives_ignore: [{}],\n", names.join(", ")));
+    }
+    let custom_types = generate_custom_types(config, api_dir, &package)?;
+    if let Some((with, _)) = &custom_types {
+        lib_rs.push_str("    with: {\n");
+        for entry in with {
+            lib_rs.push_str(&format!("        {},\n", entry));
+        }
+        lib_rs.push_str("    },\n");
+    }
     lib_rs.push_str("});\n\n");
+    if let Some((_, module)) = &custom_types {
+        lib_rs.push_str(module);
+    }
+    if let Some(version) = declared_package.as_ref().and_then(|package| package.version.as_ref()) {
+        lib_rs.push_str(&format!(
+            "/// Version of the `{}:{}` WIT package this crate was generated from\npub const PACKAGE_VERSION: &str = \"{}\";\n\n",
+            package.namespace, package.name, version
+        ));
+    }
     
-    lib_rs.push_str("/// Generated caller utilities for RPC function stubs\n\n");
-    
-    // Add global imports
-    lib_rs.push_str("pub use hyperware_app_common::SendResult;\n");
-    lib_rs.push_str("pub use hyperware_app_common::send;\n");
-    lib_rs.push_str("use hyperware_process_lib::Address;\n");
-    lib_rs.push_str("use serde_json::json;\n\n");
-    
-    // Add interface use statements
-    if !interface_use_statements.is_empty() {
-        lib_rs.push_str("// Import types from each interface\n");
-        for use_stmt in interface_use_statements {
-            lib_rs.push_str(&format!("{}\n", use_stmt));
+    if profile.types_only {
+        lib_rs.push_str(&generate_types_only_lib(&interface_use_statements));
+    } else {
+        // Support modules are only generated when a stub uses them
+        let uses = |used: fn(&SignatureStruct) -> bool| {
+            interfaces.iter().any(|interface| {
+                interface
+                    .signatures
+                    .iter()
+                    .any(|signature| used(signature) && !is_no_stub(config, interface, signature))
+            })
+        };
+        lib_rs.push_str(&generate_stubs_lib(
+            config,
+            &interface_use_statements,
+            &module_contents,
+            &nesting,
+            &module_headers,
+            uses(SignatureStruct::serializes_calls),
+            uses(|signature| signature.cache_ttl_secs().is_some()),
+        ));
+        if envelope == Envelope::Traced {
+            lib_rs.push_str(ENVELOPE_MODULE);
+            lib_rs.push('\n');
+        }
+        if uses(SignatureStruct::uses_binary) {
+            lib_rs.push_str(BYTES_MODULE);
+            lib_rs.push('\n');
+        }
+        if uses(SignatureStruct::returns_stream) {
+            lib_rs.push_str(STREAMS_MODULE);
+            lib_rs.push('\n');
+        }
+        if config.cli {
+            lib_rs.push_str(&generate_cli_module(crate_name, &cli_arms));
         }
-        lib_rs.push_str("\n");
     }
     
-    // Add all modules with their content
-    for (module_name, module_content) in module_contents {
-        lib_rs.push_str(&format!("/// Generated RPC stubs for the {} interface\n", module_name));
-        lib_rs.push_str(&format!("pub mod {} {{\n", module_name));
-        lib_rs.push_str("    use crate::*;\n\n");
-        lib_rs.push_str(&format!("    {}\n", module_content.replace("\n", "\n    ")));
-        lib_rs.push_str("}\n\n");
+    if config.wit_sources {
+        lib_rs.push_str(&generate_wit_sources(&caller_utils_dir.join("src"), &target_wit_dir, api_dir));
     }
     
     // Write lib.rs
     let lib_rs_path = caller_utils_dir.join("src").join("lib.rs");
-    println!("Writing lib.rs to {}", lib_rs_path.display());
+    log!("Writing lib.rs to {}", lib_rs_path.display());
+    
+    // Compare the stubs and types of the previous run with the new ones for the changelog
+    let previous_surface = fs::read_to_string(&lib_rs_path)
+        .ok()
+        .map(|previous| changelog::Surface::read(&previous, &target_wit_dir));
+    let surface = changelog::Surface::read(&lib_rs, api_dir);
     
-    fs::write(&lib_rs_path, lib_rs)
+    output::write_if_changed(&lib_rs_path, lib_rs)
         .with_context(|| format!("Failed to write lib.rs: {}", lib_rs_path.display()))?;
     
-    println!("Created single lib.rs file with all modules inline");
+    log!("Created single lib.rs file with all modules inline");
     
-    // Create target/wit directory and copy all WIT files
-    let target_wit_dir = caller_utils_dir.join("target").join("wit");
-    println!("Creating directory: {}", target_wit_dir.display());
     
-    // Remove the directory if it exists to ensure clean state
-    if target_wit_dir.exists() {
-        println!("Removing existing target/wit directory");
-        fs::remove_dir_all(&target_wit_dir)?;
-    }
+    // Create the WIT directory and copy all WIT files
+    log!("Creating directory: {}", target_wit_dir.display());
     
     fs::create_dir_all(&target_wit_dir)?;
     
-    // Copy all WIT files to target/wit
+    // Files this run generates, recorded in the crate's manifest
+    let mut generated_files = vec![lib_rs_path.clone()];
+    
+    if config.cli && !profile.types_only {
+        let bin_path = caller_utils_dir.join("src").join("bin").join(format!("{}-cli.rs", crate_name));
+        log!("Writing debugging binary to {}", bin_path.display());
+        fs::create_dir_all(caller_utils_dir.join("src").join("bin"))?;
+        output::write_if_changed(&bin_path, generate_cli_bin(crate_name, config.coverage_off))?;
+        generated_files.push(bin_path);
+    }
+    
+    if config.coverage_off {
+        let ignore_path = caller_utils_dir.join(COVERAGE_IGNORE_FILE);
+        let sources = paths::relative_path(base_dir, &caller_utils_dir.join("src"));
+        output::write_if_changed(&ignore_path, format!("(^|/){}/\n", escape_regex(&sources)))?;
+        log!(
+            "Excluding {} from coverage; on stable toolchains run `cargo llvm-cov --ignore-filename-regex \"$(cat {})\"`",
+            crate_name,
+            paths::relative_path(base_dir, &ignore_path)
+        );
+        generated_files.push(ignore_path);
+    }
+    
+    // Copy all WIT files, leaving unchanged copies untouched
+    let mut copied_files = HashSet::new();
     for entry in WalkDir::new(api_dir)
         .max_depth(1)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(Result::ok)
     {
         let path = entry.path();
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "wit") {
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "wit") {
             let file_name = path.file_name().unwrap();
             let target_path = target_wit_dir.join(file_name);
-            fs::copy(path, &target_path)
-                .with_context(|| format!("Failed to copy {} to {}", path.display(), target_path.display()))?;
-            println!("Copied {} to target/wit directory", file_name.to_string_lossy());
+            let content = text::read_normalized(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            if output::write_if_changed(&target_path, content)? {
+                log!("Copied {} to {}", file_name.to_string_lossy(), target_wit_dir.display());
+            }
+            copied_files.insert(file_name.to_os_string());
+            generated_files.push(target_path);
+        }
+    }
+    if worlds.len() > 1 {
+        let file_name = format!("{}.wit", world_name);
+        let target_path = target_wit_dir.join(&file_name);
+        if output::write_if_changed(&target_path, combined_world(&world_name, &worlds))? {
+            log!("Wrote world {} joining {} to {}", world_name, worlds.join(", "), target_wit_dir.display());
+        }
+        copied_files.insert(file_name.into());
+        generated_files.push(target_path);
+    }
+    
+    // Remove copies of WIT files that no longer exist in the api directory
+    for entry in fs::read_dir(&target_wit_dir)? {
+        let path = entry?.path();
+        let is_stale = path
+            .file_name()
+            .is_some_and(|name| !copied_files.contains(name));
+        if is_stale {
+            log!("Removing stale {} from {}", path.display(), target_wit_dir.display());
+            if path.is_dir() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
         }
     }
     
+    let readme = generate_crate_readme(
+        config,
+        &caller_utils_dir,
+        base_dir,
+        crate_name,
+        &worlds,
+        &package,
+        &interfaces,
+        &interface_imports,
+    );
+    output::write_if_changed(&caller_utils_dir.join("README.md"), readme)
+        .with_context(|| format!("Failed to write {} README.md", crate_name))?;
+    
+    // Add the changes to the --summary-md table, then summarize them in the changelog unless this
+    // is the first run
+    changelog::summarize(crate_name, previous_surface.as_ref(), &surface);
+    if let Some(previous_surface) = previous_surface {
+        changelog::record(&caller_utils_dir, &previous_surface, &surface)?;
+    }
+    
+    // The manifest goes last, once the crate contents it describes are in place
+    output::write_if_changed(&caller_utils_dir.join("Cargo.toml"), cargo_toml)
+        .with_context(|| format!("Failed to write {} Cargo.toml", crate_name))?;
+    
+    log!("Created Cargo.toml for {}", crate_name);
+    
+    generated_files.extend([caller_utils_dir.join("README.md"), caller_utils_dir.join("Cargo.toml")]);
+    let changelog_path = caller_utils_dir.join(changelog::CHANGELOG_FILE);
+    if changelog_path.exists() {
+        generated_files.push(changelog_path);
+    }
+    manifest::record(&caller_utils_dir, &generated_files)?;
+    
     Ok(())
 }
 
-// Update workspace Cargo.toml to include the caller-utils crate
-fn update_workspace_cargo_toml(base_dir: &Path) -> Result<()> {
+// Update workspace Cargo.toml to include a generated crate
+pub fn update_workspace_cargo_toml(base_dir: &Path, crate_name: &str) -> Result<()> {
     let workspace_cargo_toml = base_dir.join("Cargo.toml");
-    println!("Updating workspace Cargo.toml at {}", workspace_cargo_toml.display());
+    log!("Updating workspace Cargo.toml at {}", workspace_cargo_toml.display());
     
     if !workspace_cargo_toml.exists() {
-        println!("Workspace Cargo.toml not found at {}", workspace_cargo_toml.display());
+        log!("Workspace Cargo.toml not found at {}", workspace_cargo_toml.display());
         return Ok(());
     }
     
@@ -701,25 +4724,25 @@ fn update_workspace_cargo_toml(base_dir: &Path) -> Result<()> {
     if let Some(workspace) = parsed_toml.get_mut("workspace") {
         if let Some(members) = workspace.get_mut("members") {
             if let Some(members_array) = members.as_array_mut() {
-                // Check if caller-utils is already in the members list
+                // Check if the crate is already in the members list
                 let caller_utils_exists = members_array.iter().any(|m| {
-                    m.as_str().map_or(false, |s| s == "caller-utils")
+                    m.as_str() == Some(crate_name)
                 });
                 
                 if !caller_utils_exists {
-                    println!("Adding caller-utils to workspace members");
-                    members_array.push(Value::String("caller-utils".to_string()));
+                    log!("Adding {} to workspace members", crate_name);
+                    members_array.push(Value::String(crate_name.to_string()));
                     
                     // Write back the updated TOML
                     let updated_content = toml::to_string_pretty(&parsed_toml)
                         .with_context(|| "Failed to serialize updated workspace Cargo.toml")?;
                     
-                    fs::write(&workspace_cargo_toml, updated_content)
+                    output::write_if_changed(&workspace_cargo_toml, updated_content)
                         .with_context(|| format!("Failed to write updated workspace Cargo.toml: {}", workspace_cargo_toml.display()))?;
                     
-                    println!("Successfully updated workspace Cargo.toml");
+                    log!("Successfully updated workspace Cargo.toml");
                 } else {
-                    println!("caller-utils is already in workspace members");
+                    log!("{} is already in workspace members", crate_name);
                 }
             }
         }
@@ -728,11 +4751,13 @@ fn update_workspace_cargo_toml(base_dir: &Path) -> Result<()> {
     Ok(())
 }
 
-// Add caller-utils as a dependency to hyperware:process crates
-fn add_caller_utils_to_projects(projects: &[PathBuf]) -> Result<()> {
+// Add a generated crate as a dependency to hyperware:process crates
+fn add_caller_utils_to_projects(projects: &[PathBuf], base_dir: &Path, crate_name: &str, config: &Config) -> Result<()> {
     for project_path in projects {
         let cargo_toml_path = project_path.join("Cargo.toml");
-        println!("Adding caller-utils dependency to {}", cargo_toml_path.display());
+        
+        // Process crates may live in nested directories, so the path is computed per project
+        let dependency_path = paths::relative_path(project_path, &base_dir.join(crate_name));
         
         let content = fs::read_to_string(&cargo_toml_path)
             .with_context(|| format!("Failed to read project Cargo.toml: {}", cargo_toml_path.display()))?;
@@ -740,15 +4765,48 @@ fn add_caller_utils_to_projects(projects: &[PathBuf]) -> Result<()> {
         let mut parsed_toml: Value = content.parse()
             .with_context(|| format!("Failed to parse project Cargo.toml: {}", cargo_toml_path.display()))?;
         
-        // Add caller-utils to dependencies if not already present
+        // Libraries and UI crates among the processes can opt out, in their manifest or the config
+        let package = parsed_toml.get("package");
+        let package_name = package.and_then(|p| p.get("name")).and_then(Value::as_str).unwrap_or_default();
+        let marked = package
+            .and_then(|p| p.get("metadata"))
+            .and_then(|metadata| metadata.get("hyper-bindgen"))
+            .and_then(|metadata| metadata.get("skip"))
+            .and_then(Value::as_bool)
+            == Some(true);
+        if marked || config.skip_projects.iter().any(|name| name == package_name) {
+            let reason = if marked {
+                "package.metadata.hyper-bindgen.skip is set".to_string()
+            } else {
+                format!("it is listed in skip-projects in {}", CONFIG_FILE)
+            };
+            log!("Not adding {} dependency to {}: {}", crate_name, cargo_toml_path.display(), reason);
+            continue;
+        }
+        log!("Adding {} dependency to {}", crate_name, cargo_toml_path.display());
+        
+        // Add the crate to dependencies if not already present, fixing the path of an existing entry
         if let Some(dependencies) = parsed_toml.get_mut("dependencies") {
             if let Some(deps_table) = dependencies.as_table_mut() {
-                if !deps_table.contains_key("caller-utils") {
+                let existing_path = deps_table
+                    .get(crate_name)
+                    .and_then(|dep| dep.get("path"))
+                    .and_then(|path| path.as_str());
+                let updated = match existing_path {
+                    _ if !deps_table.contains_key(crate_name) => true,
+                    Some(path) if path != dependency_path => {
+                        log!("Fixing {} dependency path {} -> {}", crate_name, path, dependency_path);
+                        true
+                    }
+                    _ => false,
+                };
+                
+                if updated {
                     deps_table.insert(
-                        "caller-utils".to_string(),
+                        crate_name.to_string(),
                         Value::Table({
                             let mut t = toml::map::Map::new();
-                            t.insert("path".to_string(), Value::String("../caller-utils".to_string()));
+                            t.insert("path".to_string(), Value::String(dependency_path));
                             t
                         })
                     );
@@ -757,12 +4815,12 @@ fn add_caller_utils_to_projects(projects: &[PathBuf]) -> Result<()> {
                     let updated_content = toml::to_string_pretty(&parsed_toml)
                         .with_context(|| format!("Failed to serialize updated project Cargo.toml: {}", cargo_toml_path.display()))?;
                     
-                    fs::write(&cargo_toml_path, updated_content)
+                    output::write_if_changed(&cargo_toml_path, updated_content)
                         .with_context(|| format!("Failed to write updated project Cargo.toml: {}", cargo_toml_path.display()))?;
                     
-                    println!("Successfully added caller-utils dependency");
+                    log!("Successfully added {} dependency", crate_name);
                 } else {
-                    println!("caller-utils dependency already exists");
+                    log!("{} dependency already exists", crate_name);
                 }
             }
         }
@@ -771,16 +4829,235 @@ fn add_caller_utils_to_projects(projects: &[PathBuf]) -> Result<()> {
     Ok(())
 }
 
-// Create caller-utils crate and integrate with the workspace
-pub fn create_caller_utils(base_dir: &Path, api_dir: &Path, projects: &[PathBuf]) -> Result<()> {
-    // Step 1: Create the caller-utils crate
-    create_caller_utils_crate(api_dir, base_dir)?;
-    
-    // Step 2: Update workspace Cargo.toml
-    update_workspace_cargo_toml(base_dir)?;
-    
-    // Step 3: Add caller-utils dependency to each hyperware:process project
-    add_caller_utils_to_projects(projects)?;
+// Create the generated crates and integrate them with the workspace
+pub fn create_caller_utils(base_dir: &Path, api_dir: &Path, projects: &[PathBuf], config: &Config) -> Result<()> {
+    for (crate_name, profile) in config.profiles()? {
+        // Step 1: Create the crate
+        create_caller_utils_crate(api_dir, base_dir, config, &crate_name, &profile)?;
+        
+        // Step 2: Update workspace Cargo.toml
+        update_workspace_cargo_toml(base_dir, &crate_name)?;
+        
+        // Step 3: Add the crate as a dependency to each hyperware:process project
+        if profile.add_to_projects {
+            add_caller_utils_to_projects(projects, base_dir, &crate_name, config)?;
+        }
+        
+        // Step 4: Make sure a crate meant for a registry actually packages
+        if config.publish.enabled {
+            verify_package(&base_dir.join(&crate_name))?;
+        }
+    }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Interfaces parsed from WIT written to a temporary file
+    fn parse_wit(name: &str, wit: &str) -> Vec<ParsedInterface> {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("hyper-bindgen-{}-{}.wit", name, std::process::id()));
+        fs::write(&path, wit).unwrap();
+        let interfaces = parse_wit_file(&path, &dir).unwrap();
+        fs::remove_file(&path).unwrap();
+        interfaces
+    }
+
+    // Signatures parsed from a WIT file written with the given line ending and prefix, described
+    // as `function (kind) field: type, ...`
+    fn parsed_signatures(name: &str, prefix: &str, line_ending: &str) -> Vec<String> {
+        let wit = "interface chat {\n    use standard.{address};\n\n    record send-message-signature-remote {\n        \
+                   target: address,\n        message: string,\n        returning: bool\n    }\n\n    \
+                   record ping-signature-local {\n        target: address,\n        returning: string\n    }\n}\n";
+        parse_wit(name, &format!("{}{}", prefix, wit.replace('\n', line_ending)))
+            .iter()
+            .flat_map(|interface| &interface.signatures)
+            .map(|signature| {
+                let fields: Vec<String> = signature
+                    .fields
+                    .iter()
+                    .map(|field| format!("{}: {}", field.name, field.wit_type))
+                    .collect();
+                format!("{} ({}) {} at line {}", signature.function_name, signature.attr_type, fields.join(", "), signature.source.line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crlf_and_bom_signatures_match_lf() {
+        let lf = parsed_signatures("signatures-lf", "", "\n");
+        assert_eq!(
+            lf,
+            [
+                "send-message (remote) target: address, message: string, returning: bool at line 4",
+                "ping (local) target: address, returning: string at line 10",
+            ]
+        );
+        assert_eq!(parsed_signatures("signatures-crlf", "", "\r\n"), lf);
+        assert_eq!(parsed_signatures("signatures-bom-crlf", "\u{feff}", "\r\n"), lf);
+    }
+
+    // The `let request = ...;` line of each stub generated for the interface
+    fn request_lines(interface: &ParsedInterface, envelope: Envelope) -> Vec<String> {
+        let types = ExampleTypes {
+            records: BTreeMap::new(),
+            enum_cases: BTreeMap::new(),
+        };
+        interface
+            .signatures
+            .iter()
+            .map(|signature| {
+                let stub = generate_async_function(signature, &interface.name, envelope, StubStyle::AsyncFn, &types, false, &[], &BTreeMap::new());
+                stub.lines()
+                    .find_map(|line| line.trim().strip_prefix("let request = "))
+                    .unwrap()
+                    .trim_end_matches(';')
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn request_bodies_match_serde_tuple_variants() {
+        let interfaces = parse_wit(
+            "request-bodies",
+            "interface calls {
+    use standard.{address};
+
+    record none-signature-remote {
+        target: address,
+        returning: bool
+    }
+
+    record one-signature-remote {
+        target: address,
+        a: string,
+        returning: bool
+    }
+
+    record two-signature-remote {
+        target: address,
+        a: string,
+        b: u32,
+        returning: bool
+    }
+
+    record three-signature-remote {
+        target: address,
+        a: string,
+        b: u32,
+        c: bool,
+        returning: bool
+    }
+}
+",
+        );
+        assert_eq!(
+            request_lines(&interfaces[0], Envelope::Plain),
+            [
+                r#"json!({"None" : {}})"#,
+                r#"json!({"One": (a,)})"#,
+                r#"json!({"Two": (a, b)})"#,
+                r#"json!({"Three": (a, b, c)})"#,
+            ]
+        );
+        assert_eq!(
+            request_lines(&interfaces[0], Envelope::Namespaced)[2],
+            r#"json!({"calls": {"Two": (a, b)}})"#
+        );
+
+        // The golden expressions evaluate to serde's encoding of the request enum the process
+        // decodes, with the single parameter in a one-element tuple
+        #[derive(Serialize)]
+        enum Request {
+            None {},
+            One((String,)),
+            Two(String, u32),
+            Three(String, u32, bool),
+        }
+        let (a, b, c) = ("text".to_string(), 7u32, true);
+        let encoded = |request: Request| serde_json::to_value(request).unwrap();
+        assert_eq!(serde_json::json!({"None" : {}}), encoded(Request::None {}));
+        assert_eq!(serde_json::json!({"One": (a.clone(),)}), encoded(Request::One((a.clone(),))));
+        assert_eq!(serde_json::json!({"Two": (a.clone(), b)}), encoded(Request::Two(a.clone(), b)));
+        assert_eq!(serde_json::json!({"Three": (a.clone(), b, c)}), encoded(Request::Three(a.clone(), b, c)));
+        assert_eq!(serde_json::json!({"One": (a.clone(),)}).to_string(), r#"{"One":["text"]}"#);
+        assert_eq!(serde_json::json!({"Three": (a, b, c)}).to_string(), r#"{"Three":["text",7,true]}"#);
+    }
+
+    #[test]
+    fn future_stub_styles_return_send_futures() {
+        let interfaces = parse_wit(
+            "send-futures",
+            "interface calls {
+    use standard.{address};
+
+    /// @serialize-calls
+    record ping-signature-remote {
+        target: address,
+        returning: bool
+    }
+}
+",
+        );
+        let types = ExampleTypes {
+            records: BTreeMap::new(),
+            enum_cases: BTreeMap::new(),
+        };
+        let stub = |style| {
+            generate_async_function(&interfaces[0].signatures[0], "calls", Envelope::Plain, style, &types, true, &[], &BTreeMap::new())
+        };
+        let impl_future = stub(StubStyle::ImplFuture);
+        assert!(impl_future.contains(
+            "pub fn ping_remote_rpc(target: &Address) -> impl std::future::Future<Output = SendResult<bool>> + Send + '_ {"
+        ));
+        assert!(impl_future.contains("pub fn ping_remote_rpc_self() -> impl std::future::Future<Output = SendResult<bool>> + Send {"));
+        // The lock of serialized calls is shared through an `Arc`, keeping the future `Send`
+        assert!(impl_future.contains("std::sync::Arc<serial::CallLock>"));
+        assert!(stub(StubStyle::Boxed).contains("pub fn ping_remote_rpc(target: &Address) -> StubFuture<'_, bool> {"));
+    }
+
+    #[test]
+    fn paginated_helpers_for_generic_item_types() {
+        let interfaces = parse_wit(
+            "paginated",
+            "interface pages {
+    use standard.{address};
+
+    record list-scores-signature-remote {
+        target: address,
+        cursor: option<string>,
+        limit: u32,
+        returning: tuple<list<tuple<string, u32>>, option<string>>
+    }
+
+    record list-names-signature-remote {
+        target: address,
+        cursor: option<u64>,
+        limit: u32,
+        returning: tuple<list<string>, option<u64>>
+    }
+
+    record list-other-signature-remote {
+        target: address,
+        cursor: option<u64>,
+        limit: u32,
+        returning: tuple<list<string>, option<string>>
+    }
+}
+",
+        );
+        let item_types: Vec<Option<String>> = interfaces[0]
+            .signatures
+            .iter()
+            .map(|signature| paginated_item_type(signature, &BTreeMap::new()))
+            .collect();
+        assert_eq!(
+            item_types,
+            [Some("(String, u32)".to_string()), Some("String".to_string()), None]
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Case conversions between Rust and WIT names.
+//!
+//! These are the rules hyper-bindgen uses for every name it writes, exposed so that other tools
+//! derive the same WIT identifiers, Rust identifiers and JSON keys:
+//!
+//! - WIT names are kebab-case, and a WIT word can't start with a digit, so a word made of
+//!   digits is joined to the one before it: `sha_256_hash` and `Sha256Hash` both become
+//!   `sha256-hash`.
+//! - snake_case replaces the hyphens of a kebab-case name: `sha256-hash` -> `sha256_hash`.
+//! - PascalCase upper-cases the first character of each word and keeps digits as they are:
+//!   `sha256-hash` -> `Sha256Hash`. This is the JSON key the stubs send, and the request variant
+//!   process_macros derives from the Rust method name (`sha_256_hash` -> `Sha256Hash`).
+//! - The `%` WIT uses to escape keywords is not part of the name.
+
+// Join words that start with a digit to the previous word. Empty words, from leading, trailing or
+// doubled underscores, are dropped: WIT has no empty words.
+fn join_digit_words(words: &[&str]) -> String {
+    let mut result = String::new();
+    for word in words.iter().filter(|word| !word.is_empty()) {
+        if !result.is_empty() && !word.starts_with(|c: char| c.is_ascii_digit()) {
+            result.push('-');
+        }
+        result.push_str(word);
+    }
+    result
+}
+
+/// Convert a snake_case or PascalCase/camelCase Rust name to a kebab-case WIT name
+pub fn to_kebab_case(s: &str) -> String {
+    // First, handle the case where the input has underscores
+    if s.contains('_') {
+        return join_digit_words(&s.split('_').collect::<Vec<_>>());
+    }
+
+    let mut result = String::with_capacity(s.len() + 5); // Extra capacity for hyphens
+    let chars: Vec<char> = s.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            // Add hyphen if:
+            // 1. Not the first character
+            // 2. Previous character is lowercase
+            // 3. Or next character is lowercase (to handle acronyms like HTML)
+            if i > 0
+                && (chars[i - 1].is_lowercase()
+                    || (i < chars.len() - 1 && chars[i + 1].is_lowercase()))
+            {
+                result.push('-');
+            }
+            result.push(c.to_lowercase().next().unwrap());
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// Convert a kebab-case WIT name to snake_case
+pub fn to_snake_case(s: &str) -> String {
+    s.trim_start_matches('%').replace('-', "_")
+}
+
+/// Convert a kebab-case WIT name to PascalCase
+pub fn to_pascal_case(s: &str) -> String {
+    let mut result = String::new();
+    for part in s.trim_start_matches('%').split('-') {
+        let mut chars = part.chars();
+        if let Some(first_char) = chars.next() {
+            result.extend(first_char.to_uppercase());
+            result.extend(chars);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digit_words_join_the_previous_word() {
+        assert_eq!(to_kebab_case("sha_256_hash"), "sha256-hash");
+        assert_eq!(to_kebab_case("Sha256Hash"), "sha256-hash");
+        assert_eq!(to_kebab_case("sha256_hash"), "sha256-hash");
+        assert_eq!(to_kebab_case("version_2"), "version2");
+        assert_eq!(to_kebab_case("utf8"), "utf8");
+        assert_eq!(to_snake_case("sha256-hash"), "sha256_hash");
+        assert_eq!(to_pascal_case("sha256-hash"), "Sha256Hash");
+        // The variant process_macros derives from `sha_256_hash` matches either spelling
+        assert_eq!(to_pascal_case("sha-256-hash"), "Sha256Hash");
+        assert_eq!(to_pascal_case(&to_kebab_case("sha_256_hash")), "Sha256Hash");
+    }
+
+    #[test]
+    fn acronyms_are_one_word() {
+        assert_eq!(to_kebab_case("HTMLParser"), "html-parser");
+        assert_eq!(to_kebab_case("getHTTPResponse"), "get-http-response");
+        assert_eq!(to_kebab_case("parseHTML"), "parse-html");
+        assert_eq!(to_kebab_case("ID"), "id");
+        assert_eq!(to_kebab_case("UserId"), "user-id");
+        assert_eq!(to_pascal_case("html-parser"), "HtmlParser");
+    }
+
+    #[test]
+    fn underscores_separate_words() {
+        assert_eq!(to_kebab_case("send_message"), "send-message");
+        assert_eq!(to_kebab_case("get_user_by_id"), "get-user-by-id");
+        assert_eq!(to_kebab_case("_private"), "private");
+        assert_eq!(to_kebab_case("type_"), "type");
+        assert_eq!(to_kebab_case("send__message"), "send-message");
+        assert_eq!(to_snake_case("send-message"), "send_message");
+        assert_eq!(to_pascal_case("send-message"), "SendMessage");
+    }
+
+    #[test]
+    fn escaped_keywords_drop_the_percent() {
+        assert_eq!(to_snake_case("%type"), "type");
+        assert_eq!(to_pascal_case("%type"), "Type");
+        assert_eq!(to_pascal_case("%record-id"), "RecordId");
+    }
+}
@@ -0,0 +1,359 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::migration::GENERATOR_VERSION;
+use crate::output;
+use crate::text;
+use crate::wit_source;
+
+pub const CHANGELOG_FILE: &str = "CHANGELOG.generated.md";
+
+const CHANGELOG_HEADER: &str = "# Generated changelog\n\n\
+Changes to the stubs and types of this crate between hyper-bindgen runs. \
+This file is maintained by hyper-bindgen.\n";
+
+// Public stubs and WIT types of a generated crate, keyed by `module::function` and `interface.type`
+pub struct Surface {
+    functions: BTreeMap<String, String>,
+    types: BTreeMap<String, String>,
+}
+
+impl Surface {
+    // Read the surface from a generated lib.rs and the WIT files its bindings are built from
+    pub fn read(lib_rs: &str, wit_dir: &Path) -> Surface {
+        Surface {
+            functions: stub_signatures(lib_rs),
+            types: wit_types(wit_dir),
+        }
+    }
+}
+
+// Signatures of the stubs in a generated lib.rs, without the commented-out HTTP stubs. Stubs and
+// the helpers calling them follow a `// source:` comment, and are keyed by the module they are
+// defined in, whose `pub mod` line is indented one level less. Signatures rustfmt wrapped over
+// several lines are joined back into the single line hyper-bindgen writes.
+fn stub_signatures(lib_rs: &str) -> BTreeMap<String, String> {
+    let mut functions = BTreeMap::new();
+    // Name of the last module opened at each indentation
+    let mut modules = BTreeMap::<usize, &str>::new();
+    let mut after_source = false;
+    // Stub whose signature continues on the next lines
+    let mut wrapped: Option<(String, String)> = None;
+    for line in lib_rs.lines() {
+        if let Some((key, mut signature)) = wrapped.take() {
+            if signature.ends_with(',') {
+                signature.push(' ');
+            }
+            signature.push_str(line.trim());
+            if line.ends_with('{') || line.ends_with(';') {
+                let signature = signature.trim_end_matches('{').trim().replace(", )", ")");
+                functions.insert(key, signature);
+            } else {
+                wrapped = Some((key, signature));
+            }
+            continue;
+        }
+        let signature = line.trim();
+        let indent = line.len() - line.trim_start().len();
+        if let Some(name) = signature.strip_prefix("pub mod ") {
+            modules.insert(indent, name.trim_end_matches('{').trim());
+        }
+        if signature.starts_with("// source:") {
+            after_source = true;
+            continue;
+        }
+        // Doc comments and attributes sit between the comment and the function
+        if signature.starts_with("///") || signature.starts_with("#[") {
+            continue;
+        }
+        let is_stub = std::mem::take(&mut after_source);
+        let Some(rest) = signature
+            .strip_prefix("pub async fn ")
+            .or_else(|| signature.strip_prefix("pub fn "))
+        else {
+            continue;
+        };
+        let name = rest.split('(').next().unwrap_or_default();
+        let module = indent.checked_sub(4).and_then(|outer| modules.get(&outer)).copied().unwrap_or_default();
+        if is_stub && !(signature.ends_with('{') || signature.ends_with(';')) {
+            wrapped = Some((format!("{}::{}", module, name), signature.to_string()));
+        } else if is_stub {
+            functions.insert(
+                format!("{}::{}", module, name),
+                signature.trim_end_matches('{').trim().to_string(),
+            );
+        }
+    }
+    functions
+}
+
+// Type definitions of the interfaces in a WIT directory, with their bodies for comparison
+fn wit_types(wit_dir: &Path) -> BTreeMap<String, String> {
+    const KINDS: [&str; 5] = ["record ", "variant ", "enum ", "flags ", "type "];
+
+    let mut types = BTreeMap::new();
+    for entry in WalkDir::new(wit_dir)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "wit")) {
+            continue;
+        }
+        let Ok(content) = text::read_normalized(path) else {
+            continue;
+        };
+
+        let mut interface = "";
+        let mut current: Option<(String, String)> = None;
+        for line in content.lines() {
+            // Comments don't change a type
+            let trimmed = wit_source::strip_comment(line);
+            if trimmed.is_empty() {
+                continue;
+            }
+            if let Some(name) = trimmed.strip_prefix("interface ") {
+                interface = wit_source::unescape(name.trim_end_matches('{').trim());
+                continue;
+            }
+            if let Some((name, body)) = current.as_mut() {
+                // Compared without whitespace and trailing commas, which formatting changes
+                body.extend(trimmed.split_whitespace());
+                if trimmed.starts_with('}') && body.ends_with(",}") {
+                    body.remove(body.len() - 2);
+                }
+                if trimmed.starts_with('}') {
+                    types.insert(name.clone(), std::mem::take(body));
+                    current = None;
+                }
+                continue;
+            }
+            let Some(kind) = KINDS.iter().find(|kind| trimmed.starts_with(*kind)) else {
+                continue;
+            };
+            let name = wit_source::unescape(trimmed[kind.len()..].split([' ', '{', '=']).next().unwrap_or_default());
+            // Signature records describe functions, which are compared through the stubs
+            if name.contains("-signature-") {
+                current = Some((String::new(), String::new()));
+                continue;
+            }
+            let key = format!("{}.{}", interface, name);
+            if trimmed.ends_with('{') {
+                current = Some((key, trimmed.to_string()));
+            } else {
+                types.insert(key, trimmed.to_string());
+            }
+        }
+    }
+    // Drop the placeholder collected for signature records
+    types.remove("");
+    types
+}
+
+// Names added, removed and changed between two maps
+fn diff(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> [Vec<String>; 3] {
+    let added = after.keys().filter(|k| !before.contains_key(*k)).cloned().collect();
+    let removed = before.keys().filter(|k| !after.contains_key(*k)).cloned().collect();
+    let changed = after
+        .iter()
+        .filter(|(k, v)| before.get(*k).is_some_and(|old| old != *v))
+        .map(|(k, _)| k.clone())
+        .collect();
+    [added, removed, changed]
+}
+
+// Today's date as YYYY-MM-DD (UTC)
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or_default() as i64;
+
+    // Civil-from-days conversion for the proleptic Gregorian calendar
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// Render a changelog entry, or None when the surface didn't change
+fn render_entry(before: &Surface, after: &Surface) -> Option<String> {
+    let [added_functions, removed_functions, changed_functions] = diff(&before.functions, &after.functions);
+    let [added_types, removed_types, changed_types] = diff(&before.types, &after.types);
+
+    let sections = [
+        ("Added functions", added_functions),
+        ("Removed functions", removed_functions),
+        ("Changed functions", changed_functions),
+        ("Added types", added_types),
+        ("Removed types", removed_types),
+        ("Changed types", changed_types),
+    ];
+    if sections.iter().all(|(_, names)| names.is_empty()) {
+        return None;
+    }
+
+    // Removing or changing anything breaks callers; additions only need a minor version bump
+    let breaking = sections[1..3].iter().chain(&sections[4..]).any(|(_, names)| !names.is_empty());
+    let mut entry = format!(
+        "\n## {} (hyper-bindgen {}, {})\n",
+        today(),
+        GENERATOR_VERSION,
+        if breaking { "breaking" } else { "additive" }
+    );
+    for (title, names) in &sections {
+        if names.is_empty() {
+            continue;
+        }
+        entry.push_str(&format!("\n### {}\n\n", title));
+        for name in names {
+            match after.functions.get(name) {
+                Some(signature) => entry.push_str(&format!("- `{}`: `{}`\n", name, signature)),
+                None => entry.push_str(&format!("- `{}`\n", name)),
+            }
+        }
+    }
+    Some(entry)
+}
+
+// Append an entry describing the surface changes to the crate's generated changelog
+pub fn record(crate_dir: &Path, before: &Surface, after: &Surface) -> Result<()> {
+    let Some(entry) = render_entry(before, after) else {
+        log!("No changes to the generated surface");
+        return Ok(());
+    };
+
+    let path = crate_dir.join(CHANGELOG_FILE);
+    let mut content = match text::read_normalized(&path) {
+        Ok(content) => content,
+        Err(_) => CHANGELOG_HEADER.to_string(),
+    };
+    content.push_str(&entry);
+    output::write_if_changed(&path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    log!("Recorded surface changes in {}", path.display());
+    Ok(())
+}
+
+// A stub added, removed or changed by a run, for the `--summary-md` table
+struct StubChange {
+    // `crate_ident::module::function`
+    name: String,
+    before: Option<String>,
+    after: Option<String>,
+}
+
+// Stub changes and the counts of added, removed and changed types of the crates generated so far
+#[derive(Default)]
+struct Summary {
+    stubs: Vec<StubChange>,
+    types: [usize; 3],
+}
+
+static SUMMARY: Mutex<Option<Summary>> = Mutex::new(None);
+
+// File the Markdown summary of the run is written to, set with `--summary-md`
+static SUMMARY_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// Write a Markdown summary of the stub changes of the run to `path` when it ends
+pub fn write_summary_to(path: &Path) {
+    *SUMMARY_PATH.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.to_path_buf());
+}
+
+// Add the changes of a generated crate to the summary; on its first run everything is new
+pub fn summarize(crate_name: &str, before: Option<&Surface>, after: &Surface) {
+    let empty = Surface {
+        functions: BTreeMap::new(),
+        types: BTreeMap::new(),
+    };
+    let before = before.unwrap_or(&empty);
+    let [added, removed, changed] = diff(&before.functions, &after.functions);
+    let [added_types, removed_types, changed_types] = diff(&before.types, &after.types);
+
+    let mut summary = SUMMARY.lock().unwrap_or_else(|e| e.into_inner());
+    let summary = summary.get_or_insert_with(Summary::default);
+    for name in added.iter().chain(&removed).chain(&changed) {
+        summary.stubs.push(StubChange {
+            name: format!("{}::{}", crate_name.replace('-', "_"), name),
+            before: before.functions.get(name).cloned(),
+            after: after.functions.get(name).cloned(),
+        });
+    }
+    for (count, names) in summary.types.iter_mut().zip([added_types, removed_types, changed_types]) {
+        *count += names.len();
+    }
+}
+
+// Markdown table cell holding a signature as code
+fn code_cell(signature: &str) -> String {
+    format!("`{}`", signature.replace('|', "\\|"))
+}
+
+// Render the summary as a Markdown comment for a pull request
+fn render_summary(summary: &Summary) -> String {
+    let removed = summary.stubs.iter().filter(|change| change.after.is_none()).count();
+    let changed = summary.stubs.iter().filter(|change| change.before.is_some() && change.after.is_some()).count();
+    let added = summary.stubs.len() - removed - changed;
+    let breaking = removed + changed + summary.types[1] + summary.types[2] > 0;
+
+    let mut out = String::from("### Generated API changes\n\n");
+    if summary.stubs.is_empty() && summary.types.iter().all(|count| *count == 0) {
+        out.push_str("No changes to the generated stubs or types.\n");
+        return out;
+    }
+    out.push_str(&format!(
+        "**{}**: {} stubs added, {} removed, {} changed; {} types added, {} removed, {} changed.\n",
+        if breaking { "Breaking" } else { "Additive" },
+        added,
+        removed,
+        changed,
+        summary.types[0],
+        summary.types[1],
+        summary.types[2]
+    ));
+    if summary.stubs.is_empty() {
+        return out;
+    }
+    out.push_str("\n| Change | Stub | Signature |\n| --- | --- | --- |\n");
+    let mut stubs: Vec<&StubChange> = summary.stubs.iter().collect();
+    stubs.sort_by_key(|change| (change.after.is_some(), change.before.is_none(), change.name.as_str()));
+    for change in stubs {
+        let (kind, signature) = match (&change.before, &change.after) {
+            (Some(before), Some(after)) => ("changed", format!("{}<br>→ {}", code_cell(before), code_cell(after))),
+            (Some(before), None) => ("removed", code_cell(before)),
+            (None, Some(after)) => ("added", code_cell(after)),
+            (None, None) => continue,
+        };
+        out.push_str(&format!("| {} | `{}` | {} |\n", kind, change.name, signature));
+    }
+    out
+}
+
+// Forget the changes collected since the last summary, e.g. those of a verification run
+pub fn discard_summary() {
+    SUMMARY.lock().unwrap_or_else(|e| e.into_inner()).take();
+}
+
+// Write the summary of the run if `--summary-md` asked for it, and start a new one
+pub fn finish_summary() -> Result<()> {
+    let summary = SUMMARY.lock().unwrap_or_else(|e| e.into_inner()).take().unwrap_or_default();
+    let Some(path) = SUMMARY_PATH.lock().unwrap_or_else(|e| e.into_inner()).clone() else {
+        return Ok(());
+    };
+    output::write_if_changed(&path, render_summary(&summary))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    log!("Wrote the summary of the API changes to {}", path.display());
+    Ok(())
+}
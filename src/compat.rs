@@ -0,0 +1,132 @@
+use semver::{Version, VersionReq};
+use std::fs;
+use std::path::Path;
+use toml::Value;
+
+use crate::config::Config;
+use crate::migration::{self, GENERATOR_VERSION};
+
+// Versions of the runtime crates the generated code is written against.
+// The generated stubs call `hyperware_app_common::send` and return `SendResult`,
+// so any change to that API needs a new row here.
+struct CompatEntry {
+    generator: &'static str,
+    // (crate name, supported version range)
+    runtime: &'static [(&'static str, &'static str)],
+}
+
+const COMPATIBILITY: &[CompatEntry] = &[CompatEntry {
+    generator: "~0.1",
+    runtime: &[
+        ("hyperprocess_macro", ">=0.1.0, <0.2.0"),
+        ("hyperware_app_common", ">=0.1.0, <0.2.0"),
+        ("hyperware_process_lib", ">=1.0.4, <2.0.0"),
+    ],
+}];
+
+// Find the compatibility row for the running generator
+fn current_entry() -> Option<&'static CompatEntry> {
+    let generator = Version::parse(GENERATOR_VERSION).ok()?;
+    COMPATIBILITY.iter().find(|entry| {
+        VersionReq::parse(entry.generator).is_ok_and(|req| req.matches(&generator))
+    })
+}
+
+// Collect every locked version of a package from the workspace Cargo.lock
+fn locked_versions(lock: &Value, package: &str) -> Vec<Version> {
+    lock.get("package")
+        .and_then(|p| p.as_array())
+        .map(|packages| {
+            packages
+                .iter()
+                .filter(|p| p.get("name").and_then(|n| n.as_str()) == Some(package))
+                .filter_map(|p| p.get("version").and_then(|v| v.as_str()))
+                .filter_map(|v| Version::parse(v).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Check a single crate against the supported range, returning a warning if it doesn't match
+fn check_crate(lock: &Value, package: &str, supported: &str) -> Vec<String> {
+    let Ok(req) = VersionReq::parse(supported) else {
+        return vec![format!("Invalid compatibility range for {}: {}", package, supported)];
+    };
+
+    let mut warnings = Vec::new();
+    for version in locked_versions(lock, package) {
+        if req.matches(&version) {
+            log!("  {} {} is supported ({})", package, version, supported);
+            continue;
+        }
+
+        // A runtime newer than anything we know about usually means hyper-bindgen itself is outdated
+        let newer = req.comparators.iter().all(|c| {
+            version >= Version::new(c.major, c.minor.unwrap_or(0), c.patch.unwrap_or(0))
+        });
+        if newer {
+            warnings.push(format!(
+                "{} {} is newer than this hyper-bindgen ({}) supports ({}); consider updating hyper-bindgen",
+                package, version, GENERATOR_VERSION, supported
+            ));
+        } else {
+            warnings.push(format!(
+                "{} {} is older than this hyper-bindgen ({}) expects ({}); generated stubs may not compile",
+                package, version, GENERATOR_VERSION, supported
+            ));
+        }
+    }
+    warnings
+}
+
+// Check that an existing generated crate wasn't produced by a newer hyper-bindgen than this one
+fn check_generator_freshness(caller_utils_dir: &Path, crate_name: &str) -> Option<String> {
+    let content = fs::read_to_string(caller_utils_dir.join("src").join("lib.rs")).ok()?;
+    let header = migration::read_header(&content)?;
+    let previous = Version::parse(&header.generator_version).ok()?;
+    let current = Version::parse(GENERATOR_VERSION).ok()?;
+
+    if previous > current {
+        Some(format!(
+            "{} was last generated by hyper-bindgen {}, but this binary is {}; please update hyper-bindgen",
+            crate_name, previous, current
+        ))
+    } else {
+        None
+    }
+}
+
+// Compare the workspace's runtime crate versions with the generator's compatibility table.
+// Only warns; a mismatch is not fatal because the lockfile may be stale.
+pub fn check_workspace(base_dir: &Path, config: &Config) -> Vec<String> {
+    log!("Checking runtime crate compatibility for hyper-bindgen {}", GENERATOR_VERSION);
+    let mut warnings = Vec::new();
+
+    for (crate_name, _) in config.profiles().unwrap_or_default() {
+        if let Some(warning) = check_generator_freshness(&base_dir.join(&crate_name), &crate_name) {
+            warnings.push(warning);
+        }
+    }
+
+    let Some(entry) = current_entry() else {
+        warnings.push(format!(
+            "hyper-bindgen {} has no entry in its compatibility table",
+            GENERATOR_VERSION
+        ));
+        return warnings;
+    };
+
+    let lock_path = base_dir.join("Cargo.lock");
+    let lock = match fs::read_to_string(&lock_path).map(|content| content.parse::<Value>()) {
+        Ok(Ok(lock)) => lock,
+        _ => {
+            log!("  No readable Cargo.lock at {}, skipping version checks", lock_path.display());
+            return warnings;
+        }
+    };
+
+    for (package, supported) in entry.runtime {
+        warnings.extend(check_crate(&lock, package, supported));
+    }
+    warnings
+}
@@ -0,0 +1,274 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Index;
+use std::path::Path;
+use walkdir::WalkDir;
+use wit_parser::decoding::{decode, DecodedWasm};
+use wit_parser::{Handle, Interface, InterfaceId, Resolve, Type, TypeDef, TypeDefKind, TypeId, TypeOwner, WorldItem};
+
+use crate::output;
+use crate::report::{self, SkipReason};
+use crate::text;
+use crate::wit_source;
+
+// Render a WIT type reference as it would be written in source, looking up type definitions in
+// the types of a `Resolve` or of an unresolved package
+pub fn render_type(types: &impl Index<TypeId, Output = TypeDef>, ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".to_string(),
+        Type::U8 => "u8".to_string(),
+        Type::U16 => "u16".to_string(),
+        Type::U32 => "u32".to_string(),
+        Type::U64 => "u64".to_string(),
+        Type::S8 => "s8".to_string(),
+        Type::S16 => "s16".to_string(),
+        Type::S32 => "s32".to_string(),
+        Type::S64 => "s64".to_string(),
+        Type::F32 => "f32".to_string(),
+        Type::F64 => "f64".to_string(),
+        Type::Char => "char".to_string(),
+        Type::String => "string".to_string(),
+        Type::ErrorContext => "error-context".to_string(),
+        Type::Id(id) => {
+            let def = &types[*id];
+            // Escaped, so that a type named `result` isn't read back as the built-in one
+            if let Some(name) = &def.name {
+                return wit_source::escape(name);
+            }
+            render_definition(types, &def.kind)
+        }
+    }
+}
+
+// Render a single interface in the layout hyper-bindgen writes to the api directory
+fn render_interface(resolve: &Resolve, id: InterfaceId, interface: &Interface) -> Result<String> {
+    let name = interface.name.as_deref().context("Cannot render an anonymous interface")?;
+    let mut uses: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut definitions = Vec::new();
+
+    for (type_name, type_id) in &interface.types {
+        let def = &resolve.types[*type_id];
+
+        // Types pulled in from another interface with `use`
+        if let TypeDefKind::Type(Type::Id(source_id)) = def.kind {
+            let source = &resolve.types[source_id];
+            if let TypeOwner::Interface(owner) = source.owner {
+                if owner != id {
+                    if let Some(owner_name) = &resolve.interfaces[owner].name {
+                        uses.entry(wit_source::escape(owner_name)).or_default().push(wit_source::escape(type_name));
+                        continue;
+                    }
+                }
+            }
+        }
+
+        let type_name = wit_source::escape(type_name);
+        let definition = match &def.kind {
+            TypeDefKind::Record(record) => {
+                let fields: Vec<String> = record
+                    .fields
+                    .iter()
+                    .map(|f| format!("        {}: {}", wit_source::escape(&f.name), render_type(&resolve.types, &f.ty)))
+                    .collect();
+                format!("    record {} {{\n{}\n    }}", type_name, fields.join(",\n"))
+            }
+            TypeDefKind::Variant(variant) => {
+                let cases: Vec<String> = variant
+                    .cases
+                    .iter()
+                    .map(|c| match &c.ty {
+                        Some(ty) => format!("        {}({})", wit_source::escape(&c.name), render_type(&resolve.types, ty)),
+                        None => format!("        {}", wit_source::escape(&c.name)),
+                    })
+                    .collect();
+                format!("    variant {} {{\n{}\n    }}", type_name, cases.join(",\n"))
+            }
+            TypeDefKind::Enum(enum_) => {
+                let cases: Vec<String> = enum_
+                    .cases
+                    .iter()
+                    .map(|c| format!("        {}", wit_source::escape(&c.name)))
+                    .collect();
+                format!("    enum {} {{\n{}\n    }}", type_name, cases.join(",\n"))
+            }
+            TypeDefKind::Flags(flags) => {
+                let names: Vec<String> = flags
+                    .flags
+                    .iter()
+                    .map(|f| format!("        {}", wit_source::escape(&f.name)))
+                    .collect();
+                format!("    flags {} {{\n{}\n    }}", type_name, names.join(",\n"))
+            }
+            TypeDefKind::Resource => format!("    resource {};", type_name),
+            TypeDefKind::Type(_)
+            | TypeDefKind::List(_)
+            | TypeDefKind::Option(_)
+            | TypeDefKind::Tuple(_)
+            | TypeDefKind::Result(_)
+            | TypeDefKind::Stream(_)
+            | TypeDefKind::Future(_) => {
+                format!("    type {} = {};", type_name, render_definition(&resolve.types, &def.kind))
+            }
+            other => {
+                report::skip(
+                    SkipReason::UnsupportedType,
+                    format!("type {}.{}", name, type_name),
+                    format!("{} types can't be rendered", other.as_str()),
+                );
+                continue;
+            }
+        };
+        definitions.push(definition);
+    }
+
+    let mut content = format!("interface {} {{\n", wit_source::escape(name));
+    for (owner, types) in &uses {
+        content.push_str(&format!("    use {}.{{{}}};\n", owner, types.join(", ")));
+    }
+    if !uses.is_empty() {
+        content.push('\n');
+    }
+    content.push_str(&definitions.join("\n\n"));
+    content.push_str("\n}\n");
+    Ok(content)
+}
+
+// Render an anonymous type definition, such as the right-hand side of a `type x = ...;` alias
+pub fn render_definition(types: &impl Index<TypeId, Output = TypeDef>, kind: &TypeDefKind) -> String {
+    match kind {
+        TypeDefKind::Type(inner) => render_type(types, inner),
+        TypeDefKind::List(inner) => format!("list<{}>", render_type(types, inner)),
+        TypeDefKind::Option(inner) => format!("option<{}>", render_type(types, inner)),
+        TypeDefKind::Tuple(tuple) => {
+            let types: Vec<String> = tuple.types.iter().map(|t| render_type(types, t)).collect();
+            format!("tuple<{}>", types.join(", "))
+        }
+        TypeDefKind::Result(result) => match (&result.ok, &result.err) {
+            (None, None) => "result".to_string(),
+            (Some(ok), None) => format!("result<{}>", render_type(types, ok)),
+            (None, Some(err)) => format!("result<_, {}>", render_type(types, err)),
+            (Some(ok), Some(err)) => format!(
+                "result<{}, {}>",
+                render_type(types, ok),
+                render_type(types, err)
+            ),
+        },
+        TypeDefKind::Handle(Handle::Own(resource)) => {
+            format!("own<{}>", wit_source::escape(types[*resource].name.as_deref().unwrap_or_default()))
+        }
+        TypeDefKind::Handle(Handle::Borrow(resource)) => {
+            format!("borrow<{}>", wit_source::escape(types[*resource].name.as_deref().unwrap_or_default()))
+        }
+        TypeDefKind::Stream(Some(inner)) => format!("stream<{}>", render_type(types, inner)),
+        TypeDefKind::Future(Some(inner)) => format!("future<{}>", render_type(types, inner)),
+        other => other.as_str().to_string(),
+    }
+}
+
+// Add `import` statements for the given interfaces to every world in the api directory,
+// creating a world named after the component if there is none yet
+pub fn add_world_imports(api_dir: &Path, world_name: &str, interfaces: &[String]) -> Result<()> {
+    let mut updated_world = false;
+
+    for entry in WalkDir::new(api_dir)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "wit")) {
+            continue;
+        }
+        let Ok(content) = text::read_normalized(path) else {
+            continue;
+        };
+        if !wit_source::declares_world(&content) {
+            continue;
+        }
+
+        let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+        for interface in interfaces {
+            let import = format!("import {};", interface);
+            if lines.iter().any(|line| line.trim() == import) {
+                continue;
+            }
+            // Insert after the world header so includes stay last
+            if let Some(pos) = lines.iter().position(|line| wit_source::world_header(line).is_some()) {
+                lines.insert(pos + 1, format!("    {}", import));
+            }
+        }
+        output::write_if_changed(path, lines.join("\n") + "\n")?;
+        log!("Added component imports to world file {}", path.display());
+        updated_world = true;
+    }
+
+    if !updated_world {
+        let imports: Vec<String> = interfaces
+            .iter()
+            .map(|interface| format!("    import {};", interface))
+            .collect();
+        let world_file = api_dir.join(format!("{}.wit", world_name));
+        let content = format!(
+            "world {} {{\n{}\n    include process-v1;\n}}\n",
+            world_name,
+            imports.join("\n")
+        );
+        output::write_if_changed(&world_file, content)?;
+        log!("Created world file {}", world_file.display());
+    }
+
+    Ok(())
+}
+
+// Extract the exported interfaces of a compiled component into the api directory.
+// Returns the names of the interfaces written.
+pub fn extract_wit(wasm_path: &Path, api_dir: &Path) -> Result<Vec<String>> {
+    log!("Decoding component {}", wasm_path.display());
+    let bytes = fs::read(wasm_path)
+        .with_context(|| format!("Failed to read {}", wasm_path.display()))?;
+
+    let (resolve, world_id) = match decode(&bytes)
+        .with_context(|| format!("Failed to decode {} as a component", wasm_path.display()))?
+    {
+        DecodedWasm::Component(resolve, world_id) => (resolve, world_id),
+        DecodedWasm::WitPackage(..) => bail!(
+            "{} is an encoded WIT package, not a component",
+            wasm_path.display()
+        ),
+    };
+
+    let world = &resolve.worlds[world_id];
+    log!("Component world: {}", world.name);
+    fs::create_dir_all(api_dir)?;
+
+    let mut written = Vec::new();
+    for item in world.exports.values() {
+        let WorldItem::Interface { id, .. } = item else {
+            report::skip(SkipReason::NotAnInterface, "exported function", "only interfaces get stubs");
+            continue;
+        };
+        let interface = &resolve.interfaces[*id];
+        let Some(name) = &interface.name else {
+            report::skip(SkipReason::NotAnInterface, "anonymous exported interface", "only named interfaces get stubs");
+            continue;
+        };
+
+        let content = render_interface(&resolve, *id, interface)?;
+        let interface_file = api_dir.join(format!("{}.wit", name));
+        if interface_file.exists() {
+            log!("  Overwriting existing {}", interface_file.display());
+        }
+        output::write_if_changed(&interface_file, content)?;
+        log!("  Wrote exported interface {} to {}", name, interface_file.display());
+        written.push(name.clone());
+    }
+
+    if written.is_empty() {
+        bail!("{} exports no interfaces to generate stubs for", wasm_path.display());
+    }
+
+    add_world_imports(api_dir, &world.name, &written)?;
+    Ok(written)
+}
@@ -0,0 +1,390 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Optional configuration file read from the workspace root
+pub const CONFIG_FILE: &str = "hyper-bindgen.toml";
+
+// How request bodies are wrapped on the wire
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Envelope {
+    // `{"SendMessage": ...}`
+    #[default]
+    Plain,
+    // `{"chat": {"SendMessage": ...}}`, disambiguating functions with the same name across interfaces
+    Namespaced,
+    // `{"v": 1, "trace": "...", "body": {"SendMessage": ...}}`, carrying a version and the trace
+    // id of the call so it can be followed across processes
+    Traced,
+}
+
+// Shape of the generated stub functions
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StubStyle {
+    // `pub async fn foo(..) -> SendResult<T>`
+    #[default]
+    AsyncFn,
+    // `pub fn foo(..) -> impl Future<Output = SendResult<T>> + Send + '_`
+    ImplFuture,
+    // `pub fn foo(..) -> StubFuture<'_, T>`, a pinned boxed `Send` future that can be stored by name
+    Boxed,
+    // `#[maybe_async] pub async fn foo(..) -> SendResult<T>`, a blocking `fn` when the consuming
+    // crate enables the generated crate's `sync` feature
+    MaybeAsync,
+}
+
+// How the stub functions are named: whether the kind and `_rpc` suffix is always added, only
+// where a function has stubs of several kinds, or never
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StubNames {
+    // `send_message_remote_rpc`
+    #[default]
+    Full,
+    // `send_message`, or `send_message_remote` and `send_message_local` for a function with stubs
+    // of several kinds
+    Short,
+    // `send_message`; a function with stubs of several kinds is an error
+    Bare,
+}
+
+// How settled an interface is, shown in its module docs and the crate README
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stability {
+    Stable,
+    Beta,
+    // Only for the process crates listed in `allowed-importers`, and left out of the crate prelude
+    Internal,
+}
+
+impl std::fmt::Display for Stability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Stability::Stable => "stable",
+            Stability::Beta => "beta",
+            Stability::Internal => "internal",
+        })
+    }
+}
+
+// Settings that apply to a single world
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WorldConfig {
+    pub envelope: Option<Envelope>,
+}
+
+// Settings that apply to a single interface
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct InterfaceConfig {
+    // Seconds its stubs wait for a response, overriding `@timeout` and the global default
+    pub timeout_secs: Option<u64>,
+    // Generate camelCase copies of its records, overriding the global setting
+    pub camel_case_types: Option<bool>,
+    // Generate `_self` stubs calling its functions on the current process, overriding the global setting
+    pub self_stubs: Option<bool>,
+    // Name of its module in the generated crate instead of the snake_case interface name
+    pub alias: Option<String>,
+    pub stability: Option<Stability>,
+    // Team or person responsible for the interface
+    pub owner: Option<String>,
+    // Package names of the process crates allowed to use an internal interface
+    pub allowed_importers: Vec<String>,
+}
+
+// Extra code generated for a WIT record
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TypeConfig {
+    // Traits derived in addition to the ones every generated record has, e.g. `Hash`
+    pub derives: Vec<String>,
+    // Attributes written above the definition, e.g. `#[serde(deny_unknown_fields)]`
+    pub attrs: Vec<String>,
+}
+
+// Rust type of a WIT type name outside the WIT spec, e.g. a 128-bit integer a protocol declares
+// as an alias in its WIT files
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TypeExtension {
+    // Rust type the stubs use for the WIT type, e.g. `u128` or `my_types::Amount`
+    pub rust: String,
+    // Expression for a default value of the Rust type, `<rust>::default()` when unset
+    pub default: Option<String>,
+    // Crates the Rust type comes from, added to the generated crates with stubs, keyed by crate name
+    // with their version requirement
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+}
+
+// Seconds stubs wait for a response when neither the config nor the WIT set a timeout
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+// Name of the generated crate when no profiles are configured
+pub const DEFAULT_CRATE: &str = "caller-utils";
+
+// A crate generated from the api directory, named after its profile
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProfileConfig {
+    // Only the WIT types, without stubs or the runtime dependencies they need
+    pub types_only: bool,
+    // Add the crate as a dependency of every process crate
+    pub add_to_projects: bool,
+    // Worlds whose interfaces the crate is generated for, overriding `generate-worlds`
+    pub worlds: Vec<String>,
+}
+
+impl Default for ProfileConfig {
+    fn default() -> Self {
+        Self {
+            types_only: false,
+            add_to_projects: true,
+            worlds: Vec::new(),
+        }
+    }
+}
+
+// Shell commands run around generation, from the workspace root
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HooksConfig {
+    // Before the api directory is generated and parsed, e.g. to fetch WIT dependencies
+    pub pre_generate: Vec<String>,
+    // After every crate is written, e.g. to run `cargo fmt` on the generated code
+    pub post_generate: Vec<String>,
+}
+
+// Rules `hyper-bindgen govern` checks a change of the api directory against
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GovernanceConfig {
+    // Interfaces that may not change at all, apart from comments and formatting
+    pub frozen: Vec<String>,
+    // People or teams who must all approve a change of an interface, keyed by interface name
+    pub reviewers: BTreeMap<String, Vec<String>>,
+    pub naming: NamingRules,
+}
+
+// Rules for the names of the types and functions a change adds
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NamingRules {
+    // Longest allowed kebab-case name
+    pub max_length: Option<usize>,
+    // Words no name may contain, e.g. `data` or `tmp`
+    pub forbidden_words: Vec<String>,
+    // Words a function name must start with, e.g. `get`, `list` or `send`, when not empty
+    pub function_prefixes: Vec<String>,
+}
+
+// Package metadata for a caller-utils crate meant to be pushed to a registry
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PublishConfig {
+    // Generate a publishable crate; also enabled with `--publish`
+    pub enabled: bool,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub repository: Option<String>,
+    // Registry version of hyperware_app_common to use instead of the git dependency,
+    // which registries reject
+    pub app_common_version: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    // Default envelope for every world
+    pub envelope: Envelope,
+    // Whether stubs are async functions or plain functions returning a future
+    pub stub_style: StubStyle,
+    // Whether stub names carry the kind of the function and an `_rpc` suffix
+    pub stub_names: StubNames,
+    // Path of the module the interface modules are nested in, e.g. `api` or `v0::api`
+    pub module_path: Option<String>,
+    // Directory the WIT files are copied to (as `<target-dir>/wit`) instead of caller-utils/wit,
+    // relative to the workspace root
+    pub target_dir: Option<PathBuf>,
+    // Functions that get no stub, as `function` or `interface.function` (kebab-case WIT names)
+    pub no_stub: Vec<String>,
+    // Generate an in-memory `send` for tests, used under cfg(test) or the `fake-send` feature
+    pub fake_send: bool,
+    // Use the fake transport in every native build as well, so the crate builds for the wasm32
+    // component with the real `send` and for host-side tests with the fake one
+    pub dual_target: bool,
+    // Embed the WIT files in a `wit_sources` module for runtime introspection
+    pub wit_sources: bool,
+    // Generate a `<crate>-cli` binary that invokes any stub from the command line
+    pub cli: bool,
+    // Generate a `scope` module whose `ScopedClient` tracks, cancels and awaits the calls made
+    // through it
+    pub scoped_client: bool,
+    // Exclude the generated crates from coverage reports
+    pub coverage_off: bool,
+    // Generate every crate without stubs, as with `--types-only`
+    pub types_only: bool,
+    // Fail when the api directory defines types no signature uses, as with `--strict`
+    pub strict: bool,
+    // Fail when a signature or type refers to a type no interface defines, as with
+    // `--strict-types`
+    pub strict_types: bool,
+    // Name of the crate generated when no profiles are configured, `caller-utils` by default
+    pub crate_name: Option<String>,
+    // Crates to generate, keyed by crate name; a single full crate named by `crate-name` when empty
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    // Package names of process crates that don't get the generated crates as a dependency, like
+    // `[package.metadata.hyper-bindgen] skip = true` in their Cargo.toml
+    pub skip_projects: Vec<String>,
+    // Per-world overrides, keyed by world name
+    pub worlds: BTreeMap<String, WorldConfig>,
+    // Worlds the crates are generated for, as with `--world`; the one world of the api directory
+    // (or its `types-` counterpart) when empty
+    pub generate_worlds: Vec<String>,
+    // Seconds stubs wait for a response, unless an interface sets its own
+    pub timeout_secs: Option<u64>,
+    // Generate a `camel` module with camelCase copies of each interface's records
+    pub camel_case_types: bool,
+    // Generate `_self` variants of the stubs that call the current process
+    pub self_stubs: bool,
+    // Per-interface overrides, keyed by kebab-case interface name
+    pub interfaces: BTreeMap<String, InterfaceConfig>,
+    // Extra derives and attributes of WIT records, keyed by `record` or `interface.record`
+    pub types: BTreeMap<String, TypeConfig>,
+    // Rust types of WIT type names outside the WIT spec, keyed by WIT name, in addition to the
+    // built-in `u128` and `s128`
+    pub type_extensions: BTreeMap<String, TypeExtension>,
+    // Commands run before and after generation
+    pub hooks: HooksConfig,
+    // Settings for publishing the generated crate
+    pub publish: PublishConfig,
+    // Rules for changes of the api directory, checked by `hyper-bindgen govern`
+    pub governance: GovernanceConfig,
+}
+
+impl Config {
+    // Load hyper-bindgen.toml from the workspace root, falling back to defaults if it doesn't exist
+    pub fn load(base_dir: &Path) -> Result<Self> {
+        let path = base_dir.join(CONFIG_FILE);
+        if !path.exists() {
+            log!("No {} found, using default configuration", CONFIG_FILE);
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        log!("Loaded configuration from {}", path.display());
+        Ok(config)
+    }
+
+    // Settings for a world, also matching the process world when given its `types-` counterpart
+    pub fn world(&self, world: &str) -> Option<&WorldConfig> {
+        self.worlds
+            .get(world)
+            .or_else(|| self.worlds.get(world.strip_prefix("types-")?))
+    }
+
+    // The crates to generate, as (crate name, profile)
+    pub fn profiles(&self) -> Result<Vec<(String, ProfileConfig)>> {
+        let valid = |name: &str| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        if self.profiles.is_empty() {
+            let name = self.crate_name.as_deref().unwrap_or(DEFAULT_CRATE);
+            if !valid(name) {
+                bail!("Invalid crate-name `{}` in {}", name, CONFIG_FILE);
+            }
+            let profile = ProfileConfig {
+                types_only: self.types_only,
+                worlds: self.generate_worlds.clone(),
+                ..ProfileConfig::default()
+            };
+            return Ok(vec![(name.to_string(), profile)]);
+        }
+        if self.crate_name.is_some() {
+            bail!("{} sets both crate-name and [profiles]; profiles are named after their crates", CONFIG_FILE);
+        }
+        for name in self.profiles.keys() {
+            if !valid(name) {
+                bail!("Invalid profile name `{}` in {}: it is used as the crate name", name, CONFIG_FILE);
+            }
+        }
+        Ok(self
+            .profiles
+            .iter()
+            .map(|(name, profile)| {
+                let mut profile = profile.clone();
+                profile.types_only |= self.types_only;
+                if profile.worlds.is_empty() {
+                    profile.worlds = self.generate_worlds.clone();
+                }
+                (name.clone(), profile)
+            })
+            .collect())
+    }
+
+    // Whether the deny-list excludes a function from the generated stubs
+    pub fn is_no_stub(&self, interface: &str, function: &str) -> bool {
+        self.no_stub.iter().any(|entry| match entry.split_once('.') {
+            Some((iface, func)) => iface == interface && func == function,
+            None => entry == function,
+        })
+    }
+
+    // Timeout of an interface's stubs and where it was set: the interface's config, its
+    // `@timeout` annotation (passed in), the global config or the built-in default
+    pub fn timeout_for(&self, interface: &str, annotation: Option<u64>) -> (u64, &'static str) {
+        if let Some(secs) = self.interfaces.get(interface).and_then(|i| i.timeout_secs) {
+            return (secs, "interfaces.timeout-secs in hyper-bindgen.toml");
+        }
+        if let Some(secs) = annotation {
+            return (secs, "the @timeout annotation of the interface");
+        }
+        match self.timeout_secs {
+            Some(secs) => (secs, "timeout-secs in hyper-bindgen.toml"),
+            None => (DEFAULT_TIMEOUT_SECS, "the hyper-bindgen default"),
+        }
+    }
+
+    // Whether an interface's module gets camelCase copies of its records
+    pub fn camel_case_types_for(&self, interface: &str) -> bool {
+        self.interfaces
+            .get(interface)
+            .and_then(|i| i.camel_case_types)
+            .unwrap_or(self.camel_case_types)
+    }
+
+    // Whether the generated crate has the in-memory transport for tests
+    pub fn fake_transport(&self) -> bool {
+        self.fake_send || self.dual_target
+    }
+
+    // Stability declared for an interface
+    pub fn stability_for(&self, interface: &str) -> Option<Stability> {
+        self.interfaces.get(interface).and_then(|i| i.stability)
+    }
+
+    // Whether an interface's stubs get `_self` variants
+    pub fn self_stubs_for(&self, interface: &str) -> bool {
+        self.interfaces
+            .get(interface)
+            .and_then(|i| i.self_stubs)
+            .unwrap_or(self.self_stubs)
+    }
+
+    // Envelope used for requests to interfaces of the given world
+    pub fn envelope_for(&self, world: &str) -> Envelope {
+        self.world(world)
+            .and_then(|world| world.envelope)
+            .unwrap_or(self.envelope)
+    }
+}
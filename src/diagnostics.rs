@@ -0,0 +1,224 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::caller_utils_generator;
+use crate::migration;
+use crate::text;
+use crate::wit_source;
+
+const SOURCE_KEY: &str = "// source:";
+
+// A compiler error, with the WIT construct it was generated from when it points into generated code
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub rendered: String,
+    pub location: Option<String>,
+    pub origin: Option<String>,
+}
+
+// Name of the parameter a column of a generated function signature points into
+fn parameter_at(line: &str, column: usize) -> Option<&str> {
+    let offset = column.checked_sub(1)?;
+    let open = line.find('(')?;
+    if offset <= open {
+        return None;
+    }
+
+    // Split the parameter list on top-level commas, skipping those inside generics and tuples
+    let mut depth = 0;
+    let mut start = open + 1;
+    for (i, c) in line[open + 1..].char_indices() {
+        let i = open + 1 + i;
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' if line[..i].ends_with('-') => {}
+            '>' | ']' => depth -= 1,
+            ')' if depth > 0 => depth -= 1,
+            ',' | ')' if depth == 0 => {
+                if offset < i {
+                    return line[start..i].split(':').next().map(str::trim);
+                }
+                if c == ')' {
+                    return None;
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Name and location of the field a parameter was generated from, in the WIT signature record
+// starting at `record` (given as `<file>:<line>`)
+fn wit_field(base_dir: &Path, record: &str, parameter: &str) -> Option<(String, String)> {
+    let (file, line) = record.rsplit_once(':')?;
+    let line: usize = line.parse().ok()?;
+    let content = text::read_normalized(&base_dir.join(file)).ok()?;
+
+    for (index, candidate) in content.lines().enumerate().skip(line) {
+        let code = wit_source::strip_comment(candidate);
+        if code.starts_with('}') {
+            break;
+        }
+        // Lines continuing a field split over several lines hold its type only
+        let Some((name, _)) = code.split_once(':') else {
+            continue;
+        };
+        let name = name.trim();
+        if caller_utils_generator::to_rust_identifier(name) == parameter {
+            return Some((name.to_string(), format!("{}:{}", file, index + 1)));
+        }
+    }
+    None
+}
+
+// Find what a line of a generated file was generated from: the `// source:` comment of the
+// enclosing stub (down to the field for errors in a parameter), or the WIT files themselves
+// for the wit_bindgen::generate! block
+fn origin_of_line(base_dir: &Path, content: &str, line: usize, column: usize) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let index = line.checked_sub(1)?;
+    if index >= lines.len() {
+        return None;
+    }
+
+    // Least indentation of the lines closing an item or opening a module above the line: a stub
+    // whose comment is indented as much or more ended before the line
+    let mut boundary = usize::MAX;
+    for candidate in lines[..=index].iter().rev() {
+        let trimmed = candidate.trim();
+        let indent = candidate.len() - candidate.trim_start().len();
+        if let Some(source) = trimmed.strip_prefix(SOURCE_KEY) {
+            if indent >= boundary {
+                return None;
+            }
+            let record = source.trim();
+            let signature = lines[index].trim_start();
+            if signature.starts_with("pub async fn ") || signature.starts_with("pub fn ") {
+                if let Some(parameter) = parameter_at(lines[index], column) {
+                    if let Some((field, location)) = wit_field(base_dir, record, parameter) {
+                        return Some(format!(
+                            "WIT field `{}` at {} (signature record at {})",
+                            field, location, record
+                        ));
+                    }
+                }
+            }
+            return Some(format!("WIT signature record at {}", record));
+        }
+        if trimmed.starts_with("wit_bindgen::generate!") {
+            return (boundary == usize::MAX).then(|| "the WIT files loaded by wit_bindgen::generate!".to_string());
+        }
+        if trimmed == "}" || trimmed.starts_with("pub mod ") {
+            boundary = boundary.min(indent);
+        }
+    }
+    None
+}
+
+// Map a primary span to the WIT construct that produced it, if it lies in a hyper-bindgen generated file
+fn origin_of_span(base_dir: &Path, file_name: &str, line: usize, column: usize) -> Option<String> {
+    let content = fs::read_to_string(base_dir.join(file_name)).ok()?;
+    migration::read_header(&content)?;
+    origin_of_line(base_dir, &content, line, column)
+}
+
+// Collect the errors from a stream of JSON messages, as printed by `cargo --message-format=json`
+// or `rustc --error-format=json`
+pub fn parse_errors(base_dir: &Path, json_lines: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for line in json_lines.lines() {
+        let Ok(value) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        // cargo wraps each rustc diagnostic in a compiler-message
+        let message = match value.get("reason").and_then(Value::as_str) {
+            Some("compiler-message") => &value["message"],
+            Some(_) => continue,
+            None => &value,
+        };
+        if message.get("level").and_then(Value::as_str) != Some("error") {
+            continue;
+        }
+
+        let rendered = message
+            .get("rendered")
+            .and_then(Value::as_str)
+            .or_else(|| message.get("message").and_then(Value::as_str))
+            .unwrap_or_default()
+            .trim_end()
+            .to_string();
+        let primary = message
+            .get("spans")
+            .and_then(Value::as_array)
+            .and_then(|spans| spans.iter().find(|span| span["is_primary"].as_bool() == Some(true)));
+        let (location, origin) = match primary {
+            Some(span) => {
+                let file_name = span["file_name"].as_str().unwrap_or_default();
+                let line = span["line_start"].as_u64().unwrap_or_default() as usize;
+                let column = span["column_start"].as_u64().unwrap_or_default() as usize;
+                (
+                    Some(format!("{}:{}", file_name, line)),
+                    origin_of_span(base_dir, file_name, line, column),
+                )
+            }
+            None => (None, None),
+        };
+
+        diagnostics.push(Diagnostic {
+            rendered,
+            location,
+            origin,
+        });
+    }
+    diagnostics
+}
+
+// Print compiler errors with the WIT constructs they were generated from
+pub fn log_errors(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        log!("{}", diagnostic.rendered);
+        if let (Some(location), Some(origin)) = (&diagnostic.location, &diagnostic.origin) {
+            log!("  = {} was generated from {}", location, origin);
+        }
+        log!("");
+    }
+}
+
+// Run `cargo check` on the given packages and fail with the compiler errors if they don't build
+pub fn verify_build(base_dir: &Path, packages: &[String]) -> Result<()> {
+    log!("\n=== Verifying the generated code builds ===");
+    let mut command = Command::new("cargo");
+    command.args(["check", "--message-format=json"]).current_dir(base_dir);
+    for package in packages {
+        command.args(["-p", package]);
+    }
+    log!("Running cargo check for {}", packages.join(", "));
+
+    let output = command.output().with_context(|| "Failed to run `cargo check`")?;
+    if output.status.success() {
+        log!("Generated code builds");
+        return Ok(());
+    }
+
+    let diagnostics = parse_errors(base_dir, &String::from_utf8_lossy(&output.stdout));
+    if diagnostics.is_empty() {
+        // cargo failed before compiling anything, e.g. while resolving dependencies
+        bail!(
+            "`cargo check` failed:\n{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    log_errors(&diagnostics);
+
+    let generated = diagnostics.iter().filter(|d| d.origin.is_some()).count();
+    bail!(
+        "`cargo check` failed with {} errors ({} in generated code)",
+        diagnostics.len(),
+        generated
+    );
+}
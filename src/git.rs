@@ -0,0 +1,121 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+use crate::text;
+
+// Paths hyper-bindgen keeps its own state in; changes there are never "unrelated"
+const IGNORED_PREFIXES: [&str; 1] = [".hyper-bindgen/"];
+
+// Maximum number of dirty paths listed in the warning
+const MAX_LISTED: usize = 10;
+
+// Uncommitted changes in the git repository containing base_dir.
+// Returns None when base_dir isn't inside a git repository or git isn't available.
+fn uncommitted_changes(base_dir: &Path) -> Option<Vec<String>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(base_dir)
+        .args(["status", "--porcelain", "--untracked-files=normal"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    // Porcelain paths are relative to the repository root, which base_dir may be below
+    let prefix = workspace_prefix(base_dir)?;
+    let changes = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.len() > 3)
+        .filter(|line| {
+            let path = line[3..].strip_prefix(prefix.as_str()).unwrap_or(&line[3..]);
+            !IGNORED_PREFIXES.iter().any(|ignored| path.starts_with(ignored))
+        })
+        .map(|line| line.to_string())
+        .collect();
+    Some(changes)
+}
+
+// Path of base_dir relative to the root of its git repository, with a trailing slash, or empty at
+// the root
+fn workspace_prefix(base_dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(base_dir)
+        .args(["rev-parse", "--show-prefix"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim_end_matches('\n').to_string())
+}
+
+// Warn when the working tree has uncommitted changes that generated output would mix with,
+// or fail if a clean tree is required
+pub fn check_clean(base_dir: &Path, require_clean: bool) -> Result<()> {
+    let Some(changes) = uncommitted_changes(base_dir) else {
+        log!("Not a git repository (or git unavailable), skipping working tree check");
+        return Ok(());
+    };
+
+    if changes.is_empty() {
+        log!("Working tree is clean");
+        return Ok(());
+    }
+
+    let mut listing: Vec<String> = changes
+        .iter()
+        .take(MAX_LISTED)
+        .map(|change| format!("  {}", change))
+        .collect();
+    if changes.len() > MAX_LISTED {
+        listing.push(format!("  ... and {} more", changes.len() - MAX_LISTED));
+    }
+
+    if require_clean {
+        bail!(
+            "Working tree has {} uncommitted changes and --require-clean was given:\n{}\n\
+             Commit or stash them before regenerating.",
+            changes.len(),
+            listing.join("\n")
+        );
+    }
+
+    log!(
+        "Warning: working tree has {} uncommitted changes; generated output will be mixed with them:\n{}",
+        changes.len(),
+        listing.join("\n")
+    );
+    Ok(())
+}
+
+// Run git in base_dir and return its standard output, failing with its error output
+fn run(base_dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(base_dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Files directly in `dir` (relative to base_dir) as committed at `reference`, as (path, content)
+// sorted by path. A directory that didn't exist at the reference has no files.
+pub fn files_at(base_dir: &Path, reference: &str, dir: &str) -> Result<Vec<(String, String)>> {
+    run(base_dir, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", reference)])
+        .with_context(|| format!("`{}` is not a commit of the git repository at {}", reference, base_dir.display()))?;
+    let listing = run(base_dir, &["ls-tree", "--name-only", reference, &format!("{}/", dir)])?;
+    let mut files = Vec::new();
+    for path in listing.lines() {
+        let content = run(base_dir, &["show", &format!("{}:./{}", reference, path)])?;
+        files.push((path.to_string(), text::normalize(&content)));
+    }
+    Ok(files)
+}
@@ -0,0 +1,245 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::config::{GovernanceConfig, CONFIG_FILE};
+use crate::git;
+use crate::paths;
+use crate::text;
+use crate::wit_source;
+
+// How an interface differs from the base of the change
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Change {
+    Added,
+    Changed,
+    Removed,
+}
+
+impl Change {
+    fn as_str(self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Changed => "changed",
+            Change::Removed => "removed",
+        }
+    }
+}
+
+// A rule the change breaks
+#[derive(Serialize)]
+pub struct Violation {
+    // `frozen`, `reviewers` or `naming`
+    pub rule: &'static str,
+    pub interface: String,
+    pub message: String,
+}
+
+// The interfaces a change of the api directory touches and the rules it breaks
+#[derive(Serialize)]
+pub struct Evaluation {
+    pub base: String,
+    pub passed: bool,
+    pub interfaces: BTreeMap<String, Change>,
+    pub violations: Vec<Violation>,
+}
+
+impl Evaluation {
+    // One line per changed interface and broken rule, then the verdict
+    pub fn render(&self) -> String {
+        let mut out = format!("Interfaces changed since {}:\n", self.base);
+        if self.interfaces.is_empty() {
+            out.push_str("  none\n");
+        }
+        for (name, change) in &self.interfaces {
+            out.push_str(&format!("  {} ({})\n", name, change.as_str()));
+        }
+        for violation in &self.violations {
+            out.push_str(&format!("FAIL [{}] {}: {}\n", violation.rule, violation.interface, violation.message));
+        }
+        if self.passed {
+            out.push_str("PASS\n");
+        }
+        out
+    }
+}
+
+// Interfaces of a set of WIT files, keyed by name, with their bodies as compared for changes:
+// without whitespace, trailing commas and comments other than `/// @` annotations, so that a
+// field split over several lines or with a comment after it compares equal
+fn interface_bodies<'a>(files: impl Iterator<Item = &'a str>) -> BTreeMap<String, String> {
+    let mut bodies = BTreeMap::new();
+    for content in files {
+        let mut current: Option<(String, String)> = None;
+        let mut depth = 0usize;
+        for line in content.lines() {
+            let line = line.trim();
+            let line = if line.starts_with("/// @") { line } else { wit_source::strip_comment(line) };
+            if line.is_empty() {
+                continue;
+            }
+            if current.is_none() {
+                let Some(name) = line.strip_prefix("interface ") else {
+                    continue;
+                };
+                let name = name.trim_end_matches('{').trim().trim_start_matches('%').to_string();
+                current = Some((name, String::new()));
+                depth = 0;
+            }
+            let Some((name, body)) = current.as_mut() else {
+                continue;
+            };
+            body.extend(line.split_whitespace());
+            depth += line.matches('{').count();
+            depth = depth.saturating_sub(line.matches('}').count());
+            if depth == 0 && line.contains('}') {
+                bodies.insert(std::mem::take(name), body.replace(",}", "}"));
+                current = None;
+            }
+        }
+    }
+    bodies
+}
+
+// Names of the types and functions an interface defines, signature records counting as their
+// function, keyed by interface name
+fn interface_names<'a>(files: impl Iterator<Item = (&'a str, &'a str)>) -> BTreeMap<String, (BTreeSet<String>, BTreeSet<String>)> {
+    let mut names = BTreeMap::<String, (BTreeSet<String>, BTreeSet<String>)>::new();
+    for (path, content) in files {
+        // Files that don't parse are reported by generation
+        let Ok(interfaces) = wit_source::parse(path, content) else {
+            continue;
+        };
+        for interface in interfaces {
+            let (types, functions) = names.entry(interface.name).or_default();
+            for definition in interface.definitions {
+                match definition.name.split_once("-signature-") {
+                    Some((function, _)) => functions.insert(function.to_string()),
+                    None => types.insert(definition.name),
+                };
+            }
+            functions.extend(interface.functions.into_iter().map(|function| function.name));
+        }
+    }
+    names
+}
+
+// Why a name the change adds breaks the naming rules, if it does
+fn naming_violation(rules: &GovernanceConfig, name: &str, is_function: bool) -> Option<String> {
+    let naming = &rules.naming;
+    let kind = if is_function { "function" } else { "type" };
+    if let Some(max_length) = naming.max_length.filter(|max_length| name.len() > *max_length) {
+        return Some(format!("{} `{}` is longer than {} characters", kind, name, max_length));
+    }
+    if let Some(word) = name.split('-').find(|word| naming.forbidden_words.iter().any(|forbidden| forbidden == word)) {
+        return Some(format!("{} `{}` contains the forbidden word `{}`", kind, name, word));
+    }
+    let first = name.split('-').next().unwrap_or_default();
+    if is_function && !naming.function_prefixes.is_empty() && !naming.function_prefixes.iter().any(|prefix| prefix == first) {
+        return Some(format!(
+            "function `{}` doesn't start with one of {}",
+            name,
+            naming.function_prefixes.join(", ")
+        ));
+    }
+    None
+}
+
+// WIT files of the api directory on disk, as (path from base_dir, content)
+fn current_files(base_dir: &Path, api_dir: &Path) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(api_dir)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "wit")) {
+            continue;
+        }
+        let content = text::read_normalized(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        files.push((paths::to_slash(path.strip_prefix(base_dir).unwrap_or(path)), content));
+    }
+    Ok(files)
+}
+
+// Evaluate the WIT files of the api directory against the governance rules of hyper-bindgen.toml,
+// as a change from the api directory committed at `base`. `approved_by` are the reviewers who
+// approved the change.
+pub fn evaluate(base_dir: &Path, rules: &GovernanceConfig, base: &str, approved_by: &[String]) -> Result<Evaluation> {
+    let before_files: Vec<(String, String)> = git::files_at(base_dir, base, "api")?
+        .into_iter()
+        .filter(|(path, _)| path.ends_with(".wit"))
+        .collect();
+    let after_files = current_files(base_dir, &base_dir.join("api"))?;
+
+    let before = interface_bodies(before_files.iter().map(|(_, content)| content.as_str()));
+    let after = interface_bodies(after_files.iter().map(|(_, content)| content.as_str()));
+    let mut interfaces = BTreeMap::new();
+    for (name, body) in &after {
+        match before.get(name) {
+            None => interfaces.insert(name.clone(), Change::Added),
+            Some(old) if old != body => interfaces.insert(name.clone(), Change::Changed),
+            Some(_) => None,
+        };
+    }
+    for name in before.keys().filter(|name| !after.contains_key(*name)) {
+        interfaces.insert(name.clone(), Change::Removed);
+    }
+
+    let mut violations = Vec::new();
+    for name in interfaces.keys().filter(|name| rules.frozen.contains(name)) {
+        violations.push(Violation {
+            rule: "frozen",
+            interface: name.clone(),
+            message: format!("the interface is frozen in {} and may not change", CONFIG_FILE),
+        });
+    }
+
+    let approved: BTreeSet<&str> = approved_by.iter().map(|reviewer| reviewer.trim_start_matches('@')).collect();
+    for (name, reviewers) in rules.reviewers.iter().filter(|(name, _)| interfaces.contains_key(*name)) {
+        let missing: Vec<&str> = reviewers
+            .iter()
+            .map(String::as_str)
+            .filter(|reviewer| !approved.contains(reviewer.trim_start_matches('@')))
+            .collect();
+        if !missing.is_empty() {
+            violations.push(Violation {
+                rule: "reviewers",
+                interface: name.clone(),
+                message: format!("needs the approval of {}", missing.join(", ")),
+            });
+        }
+    }
+
+    // Names that were already there when the rules were introduced are left alone
+    let before_names = interface_names(before_files.iter().map(|(path, content)| (path.as_str(), content.as_str())));
+    let after_names = interface_names(after_files.iter().map(|(path, content)| (path.as_str(), content.as_str())));
+    for (interface, (types, functions)) in &after_names {
+        let (old_types, old_functions) = before_names.get(interface).cloned().unwrap_or_default();
+        let added = types
+            .difference(&old_types)
+            .map(|name| (name, false))
+            .chain(functions.difference(&old_functions).map(|name| (name, true)));
+        for (name, is_function) in added {
+            if let Some(message) = naming_violation(rules, name, is_function) {
+                violations.push(Violation {
+                    rule: "naming",
+                    interface: interface.clone(),
+                    message,
+                });
+            }
+        }
+    }
+
+    Ok(Evaluation {
+        base: base.to_string(),
+        passed: violations.is_empty(),
+        interfaces,
+        violations,
+    })
+}
@@ -0,0 +1,69 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+// A hook that ran during generation, kept for the run summary
+pub struct HookRun {
+    pub stage: &'static str,
+    pub command: String,
+    pub output: String,
+}
+
+// Build the platform shell invocation for a hook command
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    }
+}
+
+// Run the hooks registered for a stage from the workspace root, in order.
+// A failing hook stops the run; the output of every hook is returned for the report.
+pub fn run(base_dir: &Path, api_dir: &Path, stage: &'static str, commands: &[String]) -> Result<Vec<HookRun>> {
+    let mut runs = Vec::new();
+    for command in commands {
+        log!("Running {} hook: {}", stage, command);
+        let output = shell(command)
+            .current_dir(base_dir)
+            .env("HYPER_BINDGEN_STAGE", stage)
+            .env("HYPER_BINDGEN_API_DIR", api_dir)
+            .output()
+            .with_context(|| format!("Failed to start {} hook `{}`", stage, command))?;
+
+        let mut captured = String::from_utf8_lossy(&output.stdout).into_owned();
+        captured.push_str(&String::from_utf8_lossy(&output.stderr));
+        let captured = captured.trim().to_string();
+
+        if !output.status.success() {
+            bail!(
+                "{} hook `{}` failed ({}):\n{}",
+                stage,
+                command,
+                output.status,
+                captured
+            );
+        }
+
+        runs.push(HookRun {
+            stage,
+            command: command.clone(),
+            output: captured,
+        });
+    }
+    Ok(runs)
+}
+
+// Print the hooks that ran, with their output, as part of a run summary
+pub fn log_runs(runs: &[HookRun]) {
+    for run in runs {
+        log!("- Ran {} hook `{}`", run.stage, run.command);
+        for line in run.output.lines() {
+            log!("    {}", line);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::wit_format;
+use crate::wit_generator::to_wit_identifier;
+
+// A WIT type inferred from one or more JSON values
+#[derive(Clone, PartialEq, Debug)]
+enum Inferred {
+    // Only `null` or empty arrays were seen, so nothing is known about the type
+    Unknown,
+    Bool,
+    U64,
+    S64,
+    F64,
+    String,
+    List(Box<Inferred>),
+    Option(Box<Inferred>),
+    // Fields with their original JSON keys, in the order they were first seen
+    Record(Vec<(String, Inferred)>),
+}
+
+// The type of a JSON value, or None if it has a list whose items can't share a type
+fn infer(value: &Value) -> Option<Inferred> {
+    Some(match value {
+        Value::Null => Inferred::Option(Box::new(Inferred::Unknown)),
+        Value::Bool(_) => Inferred::Bool,
+        Value::Number(number) if number.is_u64() => Inferred::U64,
+        Value::Number(number) if number.is_i64() => Inferred::S64,
+        Value::Number(_) => Inferred::F64,
+        Value::String(_) => Inferred::String,
+        Value::Array(items) => {
+            let mut item = Inferred::Unknown;
+            for value in items {
+                item = merge(&item, &infer(value)?)?;
+            }
+            Inferred::List(Box::new(item))
+        }
+        Value::Object(fields) => Inferred::Record(
+            fields
+                .iter()
+                .map(|(key, value)| Some((key.clone(), infer(value)?)))
+                .collect::<Option<_>>()?,
+        ),
+    })
+}
+
+// The type describing values of both types, or None when they can't be reconciled
+fn merge(a: &Inferred, b: &Inferred) -> Option<Inferred> {
+    use Inferred::*;
+    Some(match (a, b) {
+        (Unknown, other) | (other, Unknown) => other.clone(),
+        (Option(a), Option(b)) => Option(Box::new(merge(a, b)?)),
+        (Option(a), other) | (other, Option(a)) => Option(Box::new(merge(a, other)?)),
+        (U64, S64) | (S64, U64) => S64,
+        (U64 | S64, F64) | (F64, U64 | S64) => F64,
+        (List(a), List(b)) => List(Box::new(merge(a, b)?)),
+        (Record(a), Record(b)) => {
+            // Fields missing from one of the records are optional
+            let mut fields = Vec::new();
+            for (key, a_type) in a {
+                match b.iter().find(|(other, _)| other == key) {
+                    Some((_, b_type)) => fields.push((key.clone(), merge(a_type, b_type)?)),
+                    None => fields.push((key.clone(), optional(a_type))),
+                }
+            }
+            for (key, b_type) in b {
+                if !a.iter().any(|(other, _)| other == key) {
+                    fields.push((key.clone(), optional(b_type)));
+                }
+            }
+            Record(fields)
+        }
+        (a, b) if a == b => a.clone(),
+        _ => return None,
+    })
+}
+
+fn optional(inferred: &Inferred) -> Inferred {
+    match inferred {
+        Inferred::Option(_) => inferred.clone(),
+        other => Inferred::Option(Box::new(other.clone())),
+    }
+}
+
+// Record definitions collected while naming the inferred types
+struct Records {
+    // (name, definition body) of each record, in the order they were named
+    definitions: Vec<(String, String)>,
+    warnings: Vec<String>,
+}
+
+impl Records {
+    // WIT type syntax of an inferred type, defining the records it needs under `name`
+    fn wit_type(&mut self, name: &str, inferred: &Inferred) -> String {
+        match inferred {
+            Inferred::Unknown => {
+                self.warnings.push(format!("`{}` was only ever null or empty, typed as string", name));
+                "string".to_string()
+            }
+            Inferred::Bool => "bool".to_string(),
+            Inferred::U64 => "u64".to_string(),
+            Inferred::S64 => "s64".to_string(),
+            Inferred::F64 => "f64".to_string(),
+            Inferred::String => "string".to_string(),
+            Inferred::List(item) => format!("list<{}>", self.wit_type(&format!("{}-item", name), item)),
+            Inferred::Option(inner) => format!("option<{}>", self.wit_type(name, inner)),
+            Inferred::Record(fields) => self.record(name, fields),
+        }
+    }
+
+    // Define a record for the fields, reusing an identical definition and otherwise numbering
+    // the name when it is taken
+    fn record(&mut self, name: &str, fields: &[(String, Inferred)]) -> String {
+        let mut body = String::new();
+        for (key, field_type) in fields {
+            let field_name = to_wit_identifier(key);
+            let wit_type = self.wit_type(&field_name.replace('%', ""), field_type);
+            if field_name.trim_start_matches('%').replace('-', "_") != key.as_str() {
+                body.push_str(&format!("    // JSON key `{}`\n", key));
+            }
+            body.push_str(&format!("    {}: {},\n", field_name, wit_type));
+        }
+
+        let base = to_wit_identifier(name);
+        let mut record_name = base.clone();
+        for suffix in 2.. {
+            match self.definitions.iter().find(|(existing, _)| *existing == record_name) {
+                Some((_, existing_body)) if *existing_body == body => return record_name,
+                Some(_) => record_name = format!("{}{}", base, suffix),
+                None => break,
+            }
+        }
+        self.definitions.push((record_name.clone(), body));
+        record_name
+    }
+}
+
+// Infer the WIT records and the signature record of `function` from example JSON payloads.
+// Each request sample is an object of the function's named arguments; response samples are
+// the values it returns.
+pub fn from_json(function: &str, kind: &str, requests: &[PathBuf], responses: &[PathBuf]) -> Result<String> {
+    let read = |path: &PathBuf| -> Result<Value> {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("{} is not valid JSON", path.display()))
+    };
+    let merge_samples = |paths: &[PathBuf]| -> Result<Inferred> {
+        let mut merged = Inferred::Unknown;
+        for path in paths {
+            let Some(sample) = infer(&read(path)?) else {
+                bail!("{} has a list whose items don't share a type, which WIT can't express", path.display());
+            };
+            merged = match merge(&merged, &sample) {
+                Some(merged) => merged,
+                None => bail!(
+                    "{} doesn't have the shape of the samples before it; infer from samples of one payload",
+                    path.display()
+                ),
+            };
+        }
+        Ok(merged)
+    };
+
+    let function = to_wit_identifier(function).trim_start_matches('%').to_string();
+    let Inferred::Record(arguments) = merge_samples(requests)? else {
+        bail!("Request samples must be JSON objects with the arguments of {} as keys", function);
+    };
+
+    let mut records = Records {
+        definitions: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let mut signature = String::new();
+    signature.push_str(if kind == "http" { "    target: string,\n" } else { "    target: address,\n" });
+    for (key, argument_type) in &arguments {
+        let name = to_wit_identifier(key);
+        let wit_type = records.wit_type(name.trim_start_matches('%'), argument_type);
+        signature.push_str(&format!("    {}: {},\n", name, wit_type));
+    }
+    let returning = if responses.is_empty() {
+        "unit".to_string()
+    } else {
+        let returning = merge_samples(responses)?;
+        records.wit_type(&format!("{}-response", function), &returning)
+    };
+    signature.push_str(&format!("    returning: {},\n", returning));
+
+    let mut wit = String::new();
+    for warning in &records.warnings {
+        wit.push_str(&format!("// TODO: {}\n", warning));
+    }
+    wit.push_str("use standard.{address};\n\n");
+    for (name, body) in &records.definitions {
+        wit.push_str(&format!("record {} {{\n{}}}\n\n", name, body));
+    }
+    wit.push_str(&format!("// Function signature for: {} ({})\n", function, kind));
+    wit.push_str(&format!("record {}-signature-{} {{\n{}}}\n", function, kind, signature));
+    Ok(wit_format::format(&wit))
+}
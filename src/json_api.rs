@@ -0,0 +1,190 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::text;
+use crate::wit_source;
+
+// Suffix of the files in the api directory describing an interface in JSON instead of WIT
+pub const SUFFIX: &str = ".api.json";
+
+// An interface of a peer that doesn't publish WIT, described in `api/<name>.api.json` with WIT
+// names and type syntax
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Description {
+    // Name of the interface, the file name without `.api.json` by default
+    interface: Option<String>,
+    #[serde(default)]
+    annotations: Vec<String>,
+    #[serde(default)]
+    types: Vec<TypeDescription>,
+    functions: Vec<FunctionDescription>,
+}
+
+#[derive(Deserialize)]
+pub struct TypeDescription {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: TypeKind,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TypeKind {
+    Record(Vec<Member>),
+    Variant(Vec<Member>),
+    Enum(Vec<String>),
+    // WIT type syntax of a `type` alias
+    Alias(String),
+}
+
+// A record field, or a variant case with an optional payload
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Member {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub wit_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FunctionDescription {
+    name: String,
+    // `remote`, `local` or `http`
+    kind: String,
+    #[serde(default)]
+    params: Vec<Member>,
+    // WIT type of the response, `unit` when left out
+    returns: Option<String>,
+    // Annotations as they'd follow the `@` of a WIT doc comment, e.g. `timeout 10`
+    #[serde(default)]
+    annotations: Vec<String>,
+}
+
+// A function of a JSON-described interface as the fields of a signature record
+pub struct Function {
+    pub name: String,
+    pub kind: String,
+    // `(name, WIT type)` fields, from `target` to `returning`
+    pub fields: Vec<(String, String)>,
+    pub annotations: Vec<String>,
+    // 1-based line and column of the function's name in the file
+    pub line: usize,
+    pub column: usize,
+}
+
+pub struct Interface {
+    pub name: String,
+    pub annotations: Vec<String>,
+    pub types: Vec<TypeDescription>,
+    pub functions: Vec<Function>,
+}
+
+// Whether a file of the api directory is a JSON API description
+pub fn is_description(path: &Path) -> bool {
+    path.is_file() && path.file_name().is_some_and(|name| name.to_string_lossy().ends_with(SUFFIX))
+}
+
+// JSON API descriptions in the api directory, sorted by file name
+pub fn find_descriptions(api_dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(api_dir)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| is_description(path))
+        .collect()
+}
+
+// Whether a name is a WIT identifier: lowercase words of letters and digits joined by hyphens
+fn is_wit_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .split('-')
+            .all(|word| word.starts_with(|c: char| c.is_ascii_lowercase()) && word.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()))
+}
+
+// Read a JSON API description into the signature records of its functions
+pub fn parse(path: &Path) -> Result<Interface> {
+    let content = text::read_normalized(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let description: Description =
+        serde_json::from_str(&content).with_context(|| format!("{} is not a valid JSON API description", path.display()))?;
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = description
+        .interface
+        .unwrap_or_else(|| file_name.trim_end_matches(SUFFIX).to_string());
+    let members = description.types.iter().flat_map(|definition| match &definition.kind {
+        TypeKind::Record(members) | TypeKind::Variant(members) => members.iter().map(|member| member.name.as_str()).collect(),
+        TypeKind::Enum(cases) => cases.iter().map(String::as_str).collect::<Vec<_>>(),
+        TypeKind::Alias(_) => Vec::new(),
+    });
+    let names = std::iter::once(name.as_str())
+        .chain(description.types.iter().map(|definition| definition.name.as_str()))
+        .chain(members)
+        .chain(description.functions.iter().flat_map(|function| {
+            std::iter::once(function.name.as_str()).chain(function.params.iter().map(|param| param.name.as_str()))
+        }));
+    for name in names {
+        if !is_wit_name(name) {
+            bail!("{}: `{}` is not a kebab-case WIT name", path.display(), name);
+        }
+    }
+    for definition in &description.types {
+        if let TypeKind::Record(fields) = &definition.kind {
+            if let Some(field) = fields.iter().find(|field| field.wit_type.is_none()) {
+                bail!("{}: field {} of {} has no type", path.display(), field.name, definition.name);
+            }
+        }
+    }
+
+    // Functions are found by their quoted name after the `functions` key
+    let functions_at = content.find("\"functions\"").unwrap_or(0);
+    let mut functions = Vec::new();
+    for function in description.functions {
+        let target = match function.kind.as_str() {
+            "remote" | "local" => "address",
+            "http" => "string",
+            other => bail!(
+                "{}: function {} has kind `{}`, expected `remote`, `local` or `http`",
+                path.display(),
+                function.name,
+                other
+            ),
+        };
+        let mut fields = vec![("target".to_string(), target.to_string())];
+        for param in function.params {
+            let Some(wit_type) = param.wit_type else {
+                bail!("{}: parameter {} of {} has no type", path.display(), param.name, function.name);
+            };
+            fields.push((param.name, wit_type));
+        }
+        fields.push(("returning".to_string(), function.returns.unwrap_or_else(|| "unit".to_string())));
+
+        let quoted = format!("\"{}\"", function.name);
+        let (line, column) = content[functions_at..].find(&quoted).map_or((1, 1), |at| {
+            let at = functions_at + at;
+            let line_start = content[..at].rfind('\n').map_or(0, |newline| newline + 1);
+            (content[..at].matches('\n').count() + 1, wit_source::column(&content[line_start..], at - line_start))
+        });
+        functions.push(Function {
+            name: function.name,
+            kind: function.kind,
+            fields,
+            annotations: function.annotations,
+            line,
+            column,
+        });
+    }
+
+    Ok(Interface {
+        name,
+        annotations: description.annotations,
+        types: description.types,
+        functions,
+    })
+}
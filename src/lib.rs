@@ -0,0 +1,3 @@
+//! Library interface of hyper-bindgen, for tools that need to agree with the names it generates.
+
+pub mod case;
@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::paths;
+use crate::text;
+use crate::wit_source::{strip_comment, type_names, unescape};
+
+// Keywords that define a named type in a WIT interface
+const TYPE_KINDS: [&str; 6] = ["record ", "variant ", "enum ", "flags ", "type ", "resource "];
+
+// A type defined in an interface, with the names its definition refers to
+struct TypeDefinition {
+    location: String,
+    references: Vec<String>,
+}
+
+// The part of a definition line naming other types: the type of a record field, the payload of
+// a variant case or the right-hand side of a type alias
+fn referenced_types(line: &str) -> Option<&str> {
+    if let Some((_, payload)) = line.split_once('(') {
+        return Some(payload);
+    }
+    line.split_once(':').or_else(|| line.split_once('=')).map(|(_, rest)| rest)
+}
+
+// Find the types defined in the api directory that no signature record or function reaches, directly or
+// through other types, as `<file>:<line>: <interface>.<type>`
+pub fn unused_types(api_dir: &Path, base_dir: &Path) -> Vec<String> {
+    // Types keyed by `interface.type`, and the roots every signature record refers to
+    let mut types = BTreeMap::<String, TypeDefinition>::new();
+    let mut roots = Vec::new();
+    // Types brought into an interface with `use other.{name}`, keyed by `interface.name`
+    let mut imports = BTreeMap::<String, String>::new();
+
+    for entry in WalkDir::new(api_dir)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "wit")) {
+            continue;
+        }
+        let Ok(content) = text::read_normalized(path) else {
+            continue;
+        };
+        let file = paths::to_slash(path.strip_prefix(base_dir).unwrap_or(path));
+
+        let mut interface = String::new();
+        // The definition whose body is being read, and whether it is a signature record
+        let mut current: Option<(String, bool)> = None;
+        // Code of the member being read, which may continue on the following lines until its comma
+        let mut member = String::new();
+        // Whether the line continues a `func` declaration spanning several lines
+        let mut in_function = false;
+        for (index, line) in content.lines().enumerate() {
+            let line = strip_comment(line);
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("interface ") {
+                interface = unescape(name.trim_end_matches('{').trim()).to_string();
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix("use ") {
+                // `use other.{a, b as c};`
+                if let Some((other, names)) = rest.split_once(".{") {
+                    for name in names.trim_end_matches(['}', ';']).split(',') {
+                        let name = name.trim();
+                        let (original, local) = name.split_once(" as ").unwrap_or((name, name));
+                        imports.insert(
+                            format!("{}.{}", interface, unescape(local.trim())),
+                            format!("{}.{}", unescape(other.trim()), unescape(original.trim())),
+                        );
+                    }
+                }
+                continue;
+            }
+
+            if let Some((key, is_signature)) = &current {
+                let closed = line.starts_with('}');
+                if !closed {
+                    member.push(' ');
+                    member.push_str(line);
+                    if !member.ends_with(',') {
+                        continue;
+                    }
+                }
+                let referenced: Vec<String> = referenced_types(&std::mem::take(&mut member))
+                    .map(type_names)
+                    .unwrap_or_default();
+                if *is_signature {
+                    roots.extend(referenced.into_iter().map(|name| format!("{}.{}", interface, name)));
+                } else if let Some(definition) = types.get_mut(key) {
+                    definition.references.extend(referenced);
+                }
+                if closed {
+                    current = None;
+                }
+                continue;
+            }
+            // `func` declarations refer to types like signature records do
+            if in_function || line.contains(": func") || line.contains(": async func") {
+                let rest = if in_function { line } else { line.split_once(':').map_or(line, |(_, rest)| rest) };
+                roots.extend(type_names(rest).into_iter().map(|name| format!("{}.{}", interface, name)));
+                in_function = !line.ends_with(';');
+                continue;
+            }
+
+            let Some(kind) = TYPE_KINDS.iter().find(|kind| line.starts_with(*kind)) else {
+                continue;
+            };
+            let rest = &line[kind.len()..];
+            let name = rest
+                .split(|c: char| c == '{' || c == '=' || c.is_whitespace())
+                .next()
+                .unwrap_or_default()
+                .trim_start_matches('%');
+            let key = format!("{}.{}", interface, name);
+            if name.contains("-signature-") {
+                if line.ends_with('{') {
+                    current = Some((key, true));
+                }
+                continue;
+            }
+            types.insert(
+                key.clone(),
+                TypeDefinition {
+                    location: format!("{}:{}", file, index + 1),
+                    references: referenced_types(rest)
+                        .filter(|_| *kind == "type ")
+                        .map(type_names)
+                        .unwrap_or_default(),
+                },
+            );
+            if line.ends_with('{') {
+                current = Some((key, false));
+            }
+        }
+    }
+
+    // Walk from the signature records through the definitions they refer to
+    let mut reached = BTreeSet::new();
+    let mut pending = roots;
+    while let Some(key) = pending.pop() {
+        let key = imports.get(&key).cloned().unwrap_or(key);
+        if !reached.insert(key.clone()) {
+            continue;
+        }
+        if let Some(definition) = types.get(&key) {
+            let interface = key.split('.').next().unwrap_or_default();
+            pending.extend(definition.references.iter().map(|name| format!("{}.{}", interface, name)));
+        }
+    }
+
+    types
+        .iter()
+        .filter(|(key, _)| !reached.contains(*key))
+        .map(|(key, definition)| format!("{}: {}", definition.location, key))
+        .collect()
+}
+
+// Whether `text` starts with a use of `module`, e.g. `chat::send` or `chat,` but not `chatty`
+fn names_module(text: &str, module: &str) -> bool {
+    text.strip_prefix(module)
+        .is_some_and(|rest| !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+// Find the uses of internal interface modules (paths from the crate root) of the generated crates
+// in a process crate's sources, as `<file>:<line>: <crate>::<module>`
+pub fn internal_imports(project: &Path, base_dir: &Path, crate_idents: &[String], modules: &[String]) -> Vec<String> {
+    let mut found = Vec::new();
+    for entry in WalkDir::new(project.join("src"))
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "rs")) {
+            continue;
+        }
+        let Ok(content) = text::read_normalized(path) else {
+            continue;
+        };
+        let file = paths::to_slash(path.strip_prefix(base_dir).unwrap_or(path));
+        for (index, line) in content.lines().enumerate() {
+            for crate_ident in crate_idents {
+                let prefix = format!("{}::", crate_ident);
+                for (at, _) in line.match_indices(&prefix) {
+                    let rest = &line[at + prefix.len()..];
+                    // `crate::{a, b::c}` names several paths at once
+                    let items: Vec<&str> = match rest.strip_prefix('{') {
+                        Some(group) => group.split('}').next().unwrap_or_default().split(',').map(str::trim).collect(),
+                        None => vec![rest],
+                    };
+                    for module in modules {
+                        if items.iter().any(|item| names_module(item, module)) {
+                            found.push(format!("{}:{}: {}::{}", file, index + 1, crate_ident, module));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    found
+}
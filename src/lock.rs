@@ -0,0 +1,174 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::config::Config;
+use crate::json_api;
+use crate::migration::GENERATOR_VERSION;
+use crate::output;
+use crate::paths;
+use crate::text;
+
+// Inputs of the last generation, written to the workspace root next to hyper-bindgen.toml
+pub const LOCK_FILE: &str = "hyper-bindgen.lock";
+
+// Bumped when the layout of the lockfile changes
+const LOCK_VERSION: u32 = 1;
+
+const LOCK_HEADER: &str = "# Inputs of the last hyper-bindgen generation.\n\
+# This file is maintained by hyper-bindgen; commit it with the generated code.\n\n";
+
+// The generator version, configuration and WIT files a generation run used
+#[derive(Serialize, Deserialize)]
+pub struct Lock {
+    version: u32,
+    generator: String,
+    // hyper-bindgen.toml as loaded, before command line overrides
+    config: toml::Value,
+    // SHA-256 of each WIT file in the api directory, keyed by its path from the workspace root
+    wit: BTreeMap<String, String>,
+}
+
+impl Lock {
+    // Describe the inputs currently on disk
+    pub fn current(base_dir: &Path, api_dir: &Path, config: &Config) -> Result<Lock> {
+        Ok(Lock {
+            version: LOCK_VERSION,
+            generator: GENERATOR_VERSION.to_string(),
+            config: toml::Value::try_from(config).context("Failed to serialize the configuration")?,
+            wit: wit_hashes(base_dir, api_dir)?,
+        })
+    }
+
+    // Read the lockfile of a workspace, if it has one
+    pub fn read(base_dir: &Path) -> Result<Option<Lock>> {
+        let path = base_dir.join(LOCK_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = text::read_normalized(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let lock: Lock = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if lock.version != LOCK_VERSION {
+            bail!(
+                "{} has version {}, but this hyper-bindgen reads version {}",
+                path.display(),
+                lock.version,
+                LOCK_VERSION
+            );
+        }
+        Ok(Some(lock))
+    }
+
+    // Write the lockfile to the workspace root
+    pub fn write(&self, base_dir: &Path) -> Result<()> {
+        let path = base_dir.join(LOCK_FILE);
+        let content = format!("{}{}", LOCK_HEADER, toml::to_string(self)?);
+        if output::write_if_changed(&path, content)? {
+            log!("Updated {}", path.display());
+        }
+        Ok(())
+    }
+
+    // Differences in the generator version and configuration from a previous lock
+    pub fn config_drift(&self, locked: &Lock) -> Vec<String> {
+        let mut drift = Vec::new();
+        if self.generator != locked.generator {
+            drift.push(format!(
+                "generator version {} (locked {})",
+                self.generator, locked.generator
+            ));
+        }
+        let (current, previous) = (flatten(&self.config), flatten(&locked.config));
+        for (key, value) in &current {
+            match previous.get(key) {
+                None => drift.push(format!("config `{}` = {} (not set when locked)", key, value)),
+                Some(old) if old != value => {
+                    drift.push(format!("config `{}` = {} (locked {})", key, value, old))
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, old) in &previous {
+            if !current.contains_key(key) {
+                drift.push(format!("config `{}` removed (locked {})", key, old));
+            }
+        }
+        drift
+    }
+
+    // Differences in the WIT files from a previous lock
+    pub fn wit_drift(&self, locked: &Lock) -> Vec<String> {
+        let mut drift = Vec::new();
+        for (file, hash) in &self.wit {
+            match locked.wit.get(file) {
+                None => drift.push(format!("{} added", file)),
+                Some(old) if old != hash => drift.push(format!("{} changed", file)),
+                Some(_) => {}
+            }
+        }
+        for file in locked.wit.keys() {
+            if !self.wit.contains_key(file) {
+                drift.push(format!("{} removed", file));
+            }
+        }
+        drift
+    }
+}
+
+// Hash every WIT file and JSON API description in the api directory, ignoring line ending
+// differences
+fn wit_hashes(base_dir: &Path, api_dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut hashes = BTreeMap::new();
+    if !api_dir.exists() {
+        return Ok(hashes);
+    }
+    for entry in WalkDir::new(api_dir)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "wit") || json_api::is_description(path)) {
+            continue;
+        }
+        let content = text::read_normalized(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let name = path.strip_prefix(base_dir).unwrap_or(path);
+        hashes.insert(
+            paths::to_slash(name),
+            format!("{:x}", Sha256::digest(content.as_bytes())),
+        );
+    }
+    Ok(hashes)
+}
+
+// Flatten a configuration table to dotted keys and their rendered values
+fn flatten(value: &toml::Value) -> BTreeMap<String, String> {
+    fn visit(prefix: &str, value: &toml::Value, flat: &mut BTreeMap<String, String>) {
+        match value {
+            toml::Value::Table(table) => {
+                for (key, value) in table {
+                    let key = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    visit(&key, value, flat);
+                }
+            }
+            other => {
+                flat.insert(prefix.to_string(), other.to_string());
+            }
+        }
+    }
+
+    let mut flat = BTreeMap::new();
+    visit("", value, &mut flat);
+    flat
+}
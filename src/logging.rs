@@ -0,0 +1,12 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Progress output is suppressed when a command writes machine-readable data to stdout
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
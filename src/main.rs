@@ -1,47 +1,932 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+// Print progress output unless a command asked for quiet stdout
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if !$crate::logging::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
 
 mod wit_generator;
 mod caller_utils_generator;
+mod backup;
+mod changelog;
+mod compat;
+mod component;
+mod config;
+mod diagnostics;
+mod git;
+mod govern;
+mod hooks;
+mod infer;
+mod json_api;
+mod lint;
+mod lock;
+mod logging;
+mod manifest;
+mod migrate;
+mod migration;
+mod node;
+mod output;
+mod paths;
+mod prompt;
+mod report;
+mod run_lock;
+mod scaffold;
+mod text;
+mod wit_format;
+mod wit_source;
+
+#[derive(Parser)]
+#[command(
+    name = "hyper-bindgen",
+    version,
+    about = "Generate WIT files and caller-utils RPC stubs for Hyperware processes",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    generate: GenerateArgs,
+
+    /// Answer yes to every confirmation instead of asking
+    #[arg(long, global = true, conflicts_with = "non_interactive")]
+    yes: bool,
+
+    /// Never ask for confirmation, taking the default answer of each question (also what happens
+    /// when standard input isn't a terminal)
+    #[arg(long, global = true)]
+    non_interactive: bool,
+}
+
+#[derive(Args, Clone, Default)]
+struct GenerateArgs {
+    /// Run generation twice and fail if the second run changes anything
+    #[arg(long)]
+    verify_idempotent: bool,
+
+    /// Fail instead of warning when the git working tree has uncommitted changes
+    #[arg(long)]
+    require_clean: bool,
+
+    /// Generate a caller-utils crate that can be published to a registry,
+    /// using the [publish] metadata from hyper-bindgen.toml
+    #[arg(long)]
+    publish: bool,
+
+    /// Copy the WIT files to <DIR>/wit instead of caller-utils/wit, e.g. a shared cargo
+    /// target directory (relative paths are resolved from the workspace root)
+    #[arg(long, value_name = "DIR")]
+    target_dir: Option<PathBuf>,
+
+    /// Generate crates with only the wit_bindgen::generate! block and re-exported types,
+    /// without RPC stubs or the runtime dependencies they need
+    #[arg(long)]
+    types_only: bool,
+
+    /// Run `cargo check` on the generated crates (or also on the process crates with
+    /// `--verify-build=all`) and fail with the errors mapped back to their WIT sources
+    #[arg(long, value_name = "SCOPE", num_args = 0..=1, default_missing_value = "generated")]
+    verify_build: Option<VerifyBuild>,
+
+    /// Fail if hyper-bindgen.toml or the generator version differ from hyper-bindgen.lock,
+    /// or if generation changes the WIT files it records, instead of updating the lockfile
+    #[arg(long)]
+    locked: bool,
+
+    /// Fail instead of warning when the api directory defines types no signature uses
+    #[arg(long)]
+    strict: bool,
+
+    /// Fail when a signature or type refers to a custom type no interface defines, instead of
+    /// generating a reference to it
+    #[arg(long)]
+    strict_types: bool,
+
+    /// Generate the crates for the interfaces of this world; repeat it to combine several worlds
+    /// in one crate (defaults to the only world in the api directory)
+    #[arg(long = "world", value_name = "WORLD")]
+    worlds: Vec<String>,
+
+    /// Also write the warnings and errors found in the api directory to this file, as a JSON
+    /// array of `{severity, file, line, column, message}` objects
+    #[arg(long, value_name = "PATH")]
+    diagnostics_json: Option<PathBuf>,
+
+    /// Write a Markdown table of the stubs the run added, removed or changed to this file, to be
+    /// posted as a pull request comment
+    #[arg(long, value_name = "PATH")]
+    summary_md: Option<PathBuf>,
+
+    /// Wait for another hyper-bindgen run in the same workspace to finish instead of failing
+    #[arg(long)]
+    wait: bool,
+}
+
+// Crates checked by --verify-build
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+enum VerifyBuild {
+    /// Only the crates hyper-bindgen generated
+    Generated,
+    /// The generated crates and the process crates depending on them
+    All,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate WIT files and the caller-utils crate (the default when no command is given)
+    Generate(GenerateArgs),
+    /// Bring the whole workspace in line: format the WIT files, generate them from the process
+    /// crates and the caller-utils crates from them, format the Rust code and check that it builds
+    Sync {
+        #[command(flatten)]
+        generate: GenerateArgs,
+        /// Don't run `cargo check` at the end
+        #[arg(long, conflicts_with = "verify_build")]
+        no_verify: bool,
+    },
+    /// Parse the api directory and print the interfaces, signatures and types it describes
+    Parse {
+        /// Print the parsed model as a JSON document
+        #[arg(long)]
+        json: bool,
+    },
+    /// Compare the api directory, configuration and generator version with hyper-bindgen.lock,
+    /// and the generated crates with their manifests, and fail if any of them drifted since the
+    /// last generation
+    Check,
+    /// Delete every file hyper-bindgen generated in the caller-utils crates, as listed in their
+    /// .hyper-bindgen-manifest.json
+    Clean,
+    /// Format the WIT files of the api directory: indentation, blank lines, trailing commas,
+    /// aligned record fields and sorted type definitions
+    Fmt {
+        /// Only list the files that aren't formatted, failing if there are any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print WIT records and a signature record inferred from example JSON payloads of a
+    /// function, as a starting point for typing an existing JSON protocol
+    Infer {
+        /// Example requests, each an object with the function's arguments as keys
+        #[arg(long = "from-json", required = true, num_args = 1..)]
+        from_json: Vec<PathBuf>,
+        /// Name of the function, e.g. `send-message`
+        #[arg(long)]
+        function: String,
+        /// Kind of the signature record
+        #[arg(long, default_value = "remote", value_parser = ["remote", "local", "http"])]
+        kind: String,
+        /// Example responses, to infer the return type (`unit` when omitted)
+        #[arg(long, num_args = 1..)]
+        response: Vec<PathBuf>,
+    },
+    /// Scan a process crate for requests built with `json!` and matched by name, and print a
+    /// WIT interface with a signature record for each of them
+    Migrate {
+        /// Path to the process crate
+        project: PathBuf,
+        /// Also rewrite the call sites sending the requests to use the generated stubs
+        #[arg(long)]
+        rewrite: bool,
+    },
+    /// Restore the files modified by the last generation run from its backup
+    Undo,
+    /// Extract the exported interfaces of a compiled WASM component into the api directory
+    /// and generate caller stubs for them
+    FromComponent {
+        /// Path to the component (.wasm)
+        wasm: PathBuf,
+    },
+    /// Query a process on a running node for the WIT description of its API
+    /// and generate caller stubs for it
+    FromNode {
+        /// Process to query, as `[node@]process:package:publisher`
+        address: String,
+        /// HTTP URL of the node serving the process
+        #[arg(long, default_value = "http://localhost:8080")]
+        url: String,
+    },
+    /// Create a hyperware:process crate with an example endpoint, add it to the workspace
+    /// and generate its WIT interface and caller-utils stubs
+    NewProcess {
+        /// Name of the crate, e.g. `chat`
+        name: String,
+        /// World the process joins (defaults to the only world in the api directory)
+        #[arg(long)]
+        world: Option<String>,
+    },
+    /// Check the changes of the api directory since a git commit against the [governance] rules of
+    /// hyper-bindgen.toml (frozen interfaces, required reviewers and naming), failing if any is broken
+    Govern {
+        /// Commit the change is compared to, e.g. the target branch of a pull request
+        #[arg(long, default_value = "HEAD")]
+        base: String,
+        /// Reviewers who approved the change, comma-separated or repeated
+        #[arg(long, value_delimiter = ',')]
+        approved_by: Vec<String>,
+        /// Print the evaluation as a JSON document
+        #[arg(long)]
+        json: bool,
+    },
+    /// Point compiler errors in generated code at the WIT records and fields they were generated from
+    ExplainError {
+        /// File with the JSON diagnostics of `cargo check --message-format=json` or
+        /// `rustc --error-format=json` (standard input when omitted or "-")
+        diagnostics: Option<PathBuf>,
+    },
+}
 
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+    prompt::set_answers(if cli.yes {
+        prompt::Answers::Yes
+    } else if cli.non_interactive {
+        prompt::Answers::Defaults
+    } else {
+        prompt::Answers::Interactive
+    });
+
     // Get the current working directory
     let cwd = std::env::current_dir()?;
-    println!("Current working directory: {}", cwd.display());
-    
+
+    match cli.command {
+        None => generate(&cwd, &cli.generate),
+        Some(Command::Generate(args)) => generate(&cwd, &args),
+        Some(Command::Sync { generate, no_verify }) => sync(&cwd, generate, no_verify),
+        Some(Command::Parse { json }) => parse(&cwd, json),
+        Some(Command::Check) => check(&cwd),
+        Some(Command::Clean) => clean(&cwd),
+        Some(Command::Fmt { check }) => fmt(&cwd, check),
+        Some(Command::Infer { from_json, function, kind, response }) => {
+            print!("{}", infer::from_json(&function, &kind, &from_json, &response)?);
+            Ok(())
+        }
+        Some(Command::Migrate { project, rewrite }) => migrate(&cwd, &project, rewrite),
+        Some(Command::Undo) => {
+            let _lock = run_lock::acquire(&cwd, false)?;
+            backup::undo(&cwd)
+        }
+        Some(Command::FromComponent { wasm }) => from_component(&cwd, &wasm),
+        Some(Command::FromNode { address, url }) => from_node(&cwd, &address, &url),
+        Some(Command::NewProcess { name, world }) => new_process(&cwd, &name, world.as_deref()),
+        Some(Command::Govern { base, approved_by, json }) => govern(&cwd, &base, &approved_by, json),
+        Some(Command::ExplainError { diagnostics }) => explain_error(&cwd, diagnostics.as_deref()),
+    }
+}
+
+// Propose WIT for the hand-rolled requests of a process crate, and optionally point its call
+// sites at the stubs generated from it
+fn migrate(cwd: &Path, project: &Path, rewrite: bool) -> Result<()> {
+    let project = cwd.join(project);
+    let scan = migrate::scan(&project)?;
+    print!("{}", migrate::propose(&project, &scan)?);
+    if !rewrite {
+        return Ok(());
+    }
+
+    let _lock = run_lock::acquire(cwd, false)?;
+    let config = Config::load(cwd)?;
+    // Call sites use the stubs of the first crate generated
+    let Some((crate_name, _)) = config.profiles()?.into_iter().next() else {
+        bail!("No caller-utils crate is configured in hyper-bindgen.toml");
+    };
+    let rewritten = migrate::rewrite(&project, &scan, &config, &crate_name)?;
+    let files: Vec<PathBuf> = rewritten.iter().map(|(path, _)| path.clone()).collect();
+    prepare_outputs(cwd, &files)?;
+    for (path, content) in rewritten {
+        output::write_if_changed(&path, content)?;
+    }
+    Ok(())
+}
+
+// Print the parsed model of the api directory
+fn parse(cwd: &Path, json: bool) -> Result<()> {
+    logging::set_quiet(json);
+
+    let api_dir = cwd.join("api");
+    let api = caller_utils_generator::parse_api(&api_dir, cwd);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&api)?);
+    } else {
+        print!("{}", api);
+    }
+
+    Ok(())
+}
+
+// Gate a change of the api directory on the governance rules, e.g. in the CI of a pull request
+fn govern(cwd: &Path, base: &str, approved_by: &[String], json: bool) -> Result<()> {
+    logging::set_quiet(json);
+
+    let config = Config::load(cwd)?;
+    let evaluation = govern::evaluate(cwd, &config.governance, base, approved_by)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&evaluation)?);
+    } else {
+        print!("{}", evaluation.render());
+    }
+    if !evaluation.passed {
+        bail!("The api changes break {} governance rules", evaluation.violations.len());
+    }
+    Ok(())
+}
+
+// Compare the inputs on disk with the lockfile of the last generation
+fn check(cwd: &Path) -> Result<()> {
+    let Some(locked) = lock::Lock::read(cwd)? else {
+        bail!("No {} found, run hyper-bindgen to create it", lock::LOCK_FILE);
+    };
+    let config = Config::load(cwd)?;
+    let current = lock::Lock::current(cwd, &cwd.join("api"), &config)?;
+
+    let mut drift = current.config_drift(&locked);
+    drift.extend(current.wit_drift(&locked));
+    // The generated crates must still be exactly what the last run wrote
+    for (crate_name, _) in config.profiles()? {
+        drift.extend(manifest::drift(&cwd.join(crate_name))?);
+    }
+    if !drift.is_empty() {
+        bail!(
+            "The workspace differs from the last generation recorded in {} and the crate manifests:\n  {}",
+            lock::LOCK_FILE,
+            drift.join("\n  ")
+        );
+    }
+    log!("Generation inputs match {} and the generated crates match their manifests", lock::LOCK_FILE);
+    Ok(())
+}
+
+// Delete the generated crates' files, keeping anything added by hand
+fn clean(cwd: &Path) -> Result<()> {
+    let _lock = run_lock::acquire(cwd, false)?;
+    let config = Config::load(cwd)?;
+    let crate_names: Vec<String> = config.profiles()?.into_iter().map(|(crate_name, _)| crate_name).collect();
+    // Without a terminal, e.g. in CI, the files are deleted as before confirmations existed
+    if !prompt::confirm(&format!("Delete the generated files of {}?", crate_names.join(", ")), true)? {
+        log!("Nothing was deleted");
+        return Ok(());
+    }
+    prepare_outputs(cwd, &generated_paths(cwd, &[], &config)?)?;
+
+    for crate_name in crate_names {
+        log!("Cleaning {}", crate_name);
+        manifest::clean(cwd, &cwd.join(crate_name))?;
+    }
+    Ok(())
+}
+
+// Format the WIT files of the api directory, or with `check` fail if any of them isn't formatted
+fn fmt(cwd: &Path, check: bool) -> Result<()> {
+    let unformatted = unformatted_wit(cwd)?;
+    if check {
+        for (path, _) in &unformatted {
+            log!("{} is not formatted", paths::relative_path(cwd, path));
+        }
+        if !unformatted.is_empty() {
+            bail!("{} WIT files are not formatted, run `hyper-bindgen fmt`", unformatted.len());
+        }
+        return Ok(());
+    }
+
+    if unformatted.is_empty() {
+        log!("All WIT files are formatted");
+        return Ok(());
+    }
+    let _lock = run_lock::acquire(cwd, false)?;
+    prepare_outputs(cwd, &[cwd.join("api")])?;
+    write_formatted_wit(cwd, unformatted)
+}
+
+// The WIT files in the api directory that formatting would change, with their formatted content
+fn unformatted_wit(cwd: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut unformatted = Vec::new();
+    for entry in walkdir::WalkDir::new(cwd.join("api"))
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "wit")) {
+            continue;
+        }
+        // Line endings and byte order marks are normalized too
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let formatted = wit_format::format(&text::read_normalized(path)?);
+        if formatted != content {
+            unformatted.push((path.to_path_buf(), formatted));
+        }
+    }
+    Ok(unformatted)
+}
+
+fn write_formatted_wit(cwd: &Path, unformatted: Vec<(PathBuf, String)>) -> Result<()> {
+    for (path, formatted) in unformatted {
+        output::write_if_changed(&path, formatted)?;
+        log!("Formatted {}", paths::relative_path(cwd, &path));
+    }
+    Ok(())
+}
+
+// Map the errors in a set of compiler diagnostics back to the WIT they were generated from
+fn explain_error(cwd: &Path, diagnostics: Option<&Path>) -> Result<()> {
+    let input = match diagnostics {
+        Some(path) if path != Path::new("-") => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?,
+        _ => std::io::read_to_string(std::io::stdin()).context("Failed to read standard input")?,
+    };
+
+    let errors = diagnostics::parse_errors(cwd, &input);
+    if errors.is_empty() {
+        log!("No errors found in the diagnostics");
+        return Ok(());
+    }
+    diagnostics::log_errors(&errors);
+
+    let generated = errors.iter().filter(|e| e.origin.is_some()).count();
+    log!("{} errors, {} in code generated by hyper-bindgen", errors.len(), generated);
+    Ok(())
+}
+
+// Generate caller stubs for a third-party process we only have the built component of
+fn from_component(cwd: &Path, wasm: &Path) -> Result<()> {
+    let _lock = run_lock::acquire(cwd, false)?;
+    let config = Config::load(cwd)?;
+    let api_dir = cwd.join("api");
+    prepare_outputs(cwd, &generated_paths(cwd, &[], &config)?)?;
+
+    log!("\n=== STEP 1: Extracting WIT from component ===");
+    let interfaces = component::extract_wit(wasm, &api_dir)?;
+
+    log!("\n=== STEP 2: Generating Caller Utils Crate ===");
+    caller_utils_generator::create_caller_utils(cwd, &api_dir, &[], &config)?;
+
+    log!("\n=== Summary ===");
+    log!("- Extracted {} interfaces: {}", interfaces.len(), interfaces.join(", "));
+    log!("- Created caller-utils crate with stub implementations");
+    report::log_skipped(&report::take_skipped());
+    report::finish_diagnostics()?;
+    changelog::finish_summary()?;
+    Ok(())
+}
+
+// Generate caller stubs from the API a process on a running node publishes
+fn from_node(cwd: &Path, address: &str, url: &str) -> Result<()> {
+    let address = node::ProcessAddress::parse(address)?;
+    let _lock = run_lock::acquire(cwd, false)?;
+    let config = Config::load(cwd)?;
+    let api_dir = cwd.join("api");
+    prepare_outputs(cwd, &generated_paths(cwd, &[], &config)?)?;
+
+    log!("\n=== STEP 1: Fetching WIT from {} ===", address);
+    let interfaces = node::fetch_wit(url, &address, &api_dir)?;
+    component::add_world_imports(&api_dir, &address.world_name(), &interfaces)?;
+
+    log!("\n=== STEP 2: Generating Caller Utils Crate ===");
+    caller_utils_generator::create_caller_utils(cwd, &api_dir, &[], &config)?;
+
+    log!("\n=== Summary ===");
+    log!("- Fetched {} interfaces from {}: {}", interfaces.len(), address, interfaces.join(", "));
+    log!("- Created caller-utils crate with stub implementations");
+    report::log_skipped(&report::take_skipped());
+    report::finish_diagnostics()?;
+    changelog::finish_summary()?;
+    Ok(())
+}
+
+// Scaffold a process crate, then run generation so it is ready to call and be called
+fn new_process(cwd: &Path, name: &str, world: Option<&str>) -> Result<()> {
+    let crate_dir = {
+        let _lock = run_lock::acquire(cwd, false)?;
+        scaffold::new_process(cwd, name, world)?
+    };
+    generate(cwd, &GenerateArgs::default())?;
+    log!("\nCreated process {} in {}", name, crate_dir.display());
+    Ok(())
+}
+
+// Run every step that keeps the WIT files, the generated crates and the process crates consistent
+// with each other, checking by default that the process crates still build against the stubs
+fn sync(cwd: &Path, mut args: GenerateArgs, no_verify: bool) -> Result<()> {
+    let _lock = run_lock::acquire(cwd, args.wait)?;
+    let config = with_overrides(Config::load(cwd)?, &args);
+    let projects = wit_generator::find_rust_projects(cwd);
+    // One backup covers every step, so `hyper-bindgen undo` reverts the whole sync
+    let mut outputs = generated_paths(cwd, &projects, &config)?;
+    outputs.extend(projects.iter().map(|project| project.join("src")));
+    prepare_outputs(cwd, &outputs)?;
+
+    log!("\n=== Formatting WIT files ===");
+    let unformatted = unformatted_wit(cwd)?;
+    if unformatted.is_empty() {
+        log!("All WIT files are formatted");
+    }
+    write_formatted_wit(cwd, unformatted)?;
+
+    // Checking the build is left until the Rust code is formatted
+    let verify = args.verify_build.take().or(Some(VerifyBuild::All)).filter(|_| !no_verify);
+    generate_locked(cwd, &args, false)?;
+
+    // Only the process crates: the generated crates stay exactly as generation writes them, so
+    // `generate` and `sync` agree on their content
+    log!("\n=== Formatting Rust code ===");
+    let packages = projects
+        .iter()
+        .map(|project| wit_generator::package_name(project))
+        .collect::<Result<Vec<_>>>()?;
+    if packages.is_empty() {
+        log!("No crates to format");
+        return Ok(());
+    }
+    format_rust(cwd, &packages)?;
+
+    if let Some(scope) = verify {
+        verify_build(cwd, &projects, &config, scope)?;
+    }
+    log!("\nWorkspace is in sync");
+    Ok(())
+}
+
+// Format the Rust code of workspace packages with `cargo fmt`
+fn format_rust(cwd: &Path, packages: &[String]) -> Result<()> {
+    let mut command = std::process::Command::new("cargo");
+    command.arg("fmt").current_dir(cwd);
+    for package in packages {
+        command.args(["-p", package]);
+    }
+    log!("Running cargo fmt for {}", packages.join(", "));
+
+    let output = command.output().context("Failed to run `cargo fmt`")?;
+    if !output.status.success() {
+        bail!("`cargo fmt` failed:\n{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+// Generate WIT files from the process crates and the caller-utils crate from the WIT files
+fn generate(cwd: &Path, args: &GenerateArgs) -> Result<()> {
+    // Held until generation and verification finish, so concurrent runs can't interleave writes
+    let _lock = run_lock::acquire(cwd, args.wait)?;
+    generate_locked(cwd, args, true)
+}
+
+// The configuration with the command line overrides of a generation run applied
+fn with_overrides(mut config: Config, args: &GenerateArgs) -> Config {
+    config.publish.enabled |= args.publish;
+    config.types_only |= args.types_only;
+    config.strict |= args.strict;
+    config.strict_types |= args.strict_types;
+    if !args.worlds.is_empty() {
+        config.generate_worlds = args.worlds.clone();
+    }
+    if let Some(target_dir) = &args.target_dir {
+        config.target_dir = Some(target_dir.clone());
+    }
+    config
+}
+
+// Generate with the run lock held. `backup` is false when the caller already backed up
+// everything the run touches, as `sync` does for all of its steps.
+fn generate_locked(cwd: &Path, args: &GenerateArgs, backup: bool) -> Result<()> {
+    // The lockfile records the configuration file, not the command line overrides
+    let file_config = Config::load(cwd)?;
+    let config = with_overrides(file_config.clone(), args);
+    if let Some(path) = &args.diagnostics_json {
+        report::write_diagnostics_to(&cwd.join(path));
+    }
+    if let Some(path) = &args.summary_md {
+        changelog::write_summary_to(&cwd.join(path));
+    }
+
+    // Regenerating on top of unrelated uncommitted changes makes the result hard to review
+    git::check_clean(cwd, args.require_clean)?;
+
+    let api_dir = cwd.join("api");
+    let locked = lock::Lock::read(cwd)?;
+    if args.locked {
+        let Some(locked) = &locked else {
+            bail!("--locked needs a {}, run hyper-bindgen without it first", lock::LOCK_FILE);
+        };
+        let drift = lock::Lock::current(cwd, &api_dir, &file_config)?.config_drift(locked);
+        if !drift.is_empty() {
+            bail!(
+                "Configuration differs from {}, refusing to generate with --locked:\n  {}",
+                lock::LOCK_FILE,
+                drift.join("\n  ")
+            );
+        }
+    }
+
+    if backup {
+        let projects = wit_generator::find_rust_projects(cwd);
+        prepare_outputs(cwd, &generated_paths(cwd, &projects, &config)?)?;
+    }
+
+    // Hooks run around this generation only, not the second run of --verify-idempotent
+    std::fs::create_dir_all(&api_dir)?;
+    let mut hook_runs = hooks::run(cwd, &api_dir, "pre-generate", &config.hooks.pre_generate)?;
+    let projects = run_generation(cwd, &config)?;
+    if !projects.is_empty() {
+        hook_runs.extend(hooks::run(cwd, &api_dir, "post-generate", &config.hooks.post_generate)?);
+    }
+    hooks::log_runs(&hook_runs);
+    // Written for this run only: the run of --verify-idempotent compares against its output
+    report::finish_diagnostics()?;
+    changelog::finish_summary()?;
+
+    if !projects.is_empty() {
+        let current = lock::Lock::current(cwd, &api_dir, &file_config)?;
+        match &locked {
+            Some(locked) if args.locked => {
+                let drift = current.wit_drift(locked);
+                if !drift.is_empty() {
+                    bail!(
+                        "Generation changed the WIT files recorded in {}, which --locked forbids \
+                         (`hyper-bindgen undo` restores the previous output):\n  {}",
+                        lock::LOCK_FILE,
+                        drift.join("\n  ")
+                    );
+                }
+            }
+            _ => current.write(cwd)?,
+        }
+    }
+
+    if let Some(scope) = args.verify_build {
+        if !projects.is_empty() {
+            verify_build(cwd, &projects, &config, scope)?;
+        }
+    }
+
+    if args.verify_idempotent {
+        verify_idempotent(cwd, &projects, &config)?;
+    }
+
+    Ok(())
+}
+
+// Check that the generated crates, and optionally the process crates, compile
+fn verify_build(cwd: &Path, projects: &[PathBuf], config: &Config, scope: VerifyBuild) -> Result<()> {
+    let mut packages: Vec<String> = config.profiles()?.into_iter().map(|(name, _)| name).collect();
+    if scope == VerifyBuild::All {
+        for project in projects {
+            packages.push(wit_generator::package_name(project)?);
+        }
+    }
+    diagnostics::verify_build(cwd, &packages)
+}
+
+// Fail before touching anything if an output can't be written, then back up everything a run
+// may touch so `hyper-bindgen undo` can restore it
+fn prepare_outputs(cwd: &Path, outputs: &[PathBuf]) -> Result<()> {
+    let mut touched = outputs.to_vec();
+    touched.push(backup::next_backup_dir(cwd)?);
+    output::check_writable(&touched)?;
+    backup::create(cwd, outputs)?;
+    Ok(())
+}
+
+// Every file and directory a generation run may create or modify
+fn generated_paths(cwd: &Path, projects: &[PathBuf], config: &Config) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![cwd.join("Cargo.toml"), cwd.join("api"), cwd.join(lock::LOCK_FILE)];
+    for (crate_name, _) in config.profiles()? {
+        let crate_dir = cwd.join(crate_name);
+        let (wit_dir, _) = caller_utils_generator::wit_location(cwd, &crate_dir, config)?;
+        paths.extend([
+            crate_dir.join("Cargo.toml"),
+            crate_dir.join("src"),
+            crate_dir.join(changelog::CHANGELOG_FILE),
+            crate_dir.join("README.md"),
+            crate_dir.join(manifest::MANIFEST_FILE),
+            // Copy left by older layouts, removed on migration
+            crate_dir.join("target").join("wit"),
+            wit_dir,
+        ]);
+    }
+    paths.extend(projects.iter().map(|project| project.join("Cargo.toml")));
+    Ok(paths)
+}
+
+// Run generation a second time and fail if any output file is added, removed or rewritten
+fn verify_idempotent(cwd: &Path, projects: &[PathBuf], config: &Config) -> Result<()> {
+    log!("\n=== Verifying idempotency ===");
+    let outputs = generated_paths(cwd, projects, config)?;
+
+    let before = output::snapshot(&outputs)?;
+    run_generation(cwd, config)?;
+    let after = output::snapshot(&outputs)?;
+    // The first run already reported its diagnostics and changes, which this one repeats or
+    // finds empty
+    report::take_diagnostics();
+    changelog::discard_summary();
+
+    let changes = output::diff_snapshots(&before, &after);
+    if !changes.is_empty() {
+        bail!(
+            "Generation is not idempotent, the second run modified:\n  {}",
+            changes.join("\n  ")
+        );
+    }
+
+    log!("Second run produced no changes");
+    Ok(())
+}
+
+// Run both generation steps once, returning the processed projects
+fn run_generation(cwd: &Path, config: &Config) -> Result<Vec<PathBuf>> {
+    log!("Current working directory: {}", cwd.display());
+
     // Create the api directory if it doesn't exist
     let api_dir = cwd.join("api");
-    println!("API directory: {}", api_dir.display());
-    
+    log!("API directory: {}", api_dir.display());
+
     std::fs::create_dir_all(&api_dir)?;
-    println!("Created or verified api directory");
-    
+    log!("Created or verified api directory");
+
     // Step 1: Generate WIT files from Rust code
-    println!("\n=== STEP 1: Generating WIT Files ===");
-    let (processed_projects, interfaces) = wit_generator::generate_wit_files(&cwd, &api_dir)?;
-    
+    log!("\n=== STEP 1: Generating WIT Files ===");
+    let (processed_projects, interfaces) = wit_generator::generate_wit_files(cwd, &api_dir)?;
+
     if processed_projects.is_empty() {
-        println!("No relevant Rust projects found with hyperware:process metadata.");
-        return Ok(());
+        log!("No relevant Rust projects found with hyperware:process metadata.");
+        return Ok(processed_projects);
+    }
+
+    // Types nothing refers to accumulate in shared WIT as functions are removed
+    let unused = lint::unused_types(&api_dir, cwd);
+    for unused_type in &unused {
+        log!("Warning: {} is not used by any signature", unused_type);
+    }
+    if config.strict && !unused.is_empty() {
+        bail!("{} WIT types are not used by any signature (--strict)", unused.len());
+    }
+
+    // Internal interfaces are only for the process crates listed in their `allowed-importers`
+    let crate_idents: Vec<String> = config.profiles()?.into_iter().map(|(crate_name, _)| crate_name.replace('-', "_")).collect();
+    let mut internal_imports = Vec::new();
+    for project in &processed_projects {
+        let package = wit_generator::package_name(project)?;
+        let modules: Vec<String> = config
+            .interfaces
+            .iter()
+            .filter(|(name, settings)| {
+                config.stability_for(name) == Some(config::Stability::Internal) && !settings.allowed_importers.contains(&package)
+            })
+            .map(|(name, _)| caller_utils_generator::module_path(config, name))
+            .collect();
+        if !modules.is_empty() {
+            internal_imports.extend(lint::internal_imports(project, cwd, &crate_idents, &modules));
+        }
+    }
+    for import in &internal_imports {
+        log!("Error: {} is an internal interface this process is not allowed to import", import);
+    }
+    if !internal_imports.is_empty() {
+        bail!(
+            "{} uses of internal interfaces outside their allowed-importers in {}",
+            internal_imports.len(),
+            config::CONFIG_FILE
+        );
+    }
+
+    // Settings of an interface that was removed would otherwise silently stop applying
+    let interface_names = caller_utils_generator::interface_names(&api_dir);
+    for name in config.interfaces.keys() {
+        if !interface_names.contains(name) {
+            log!("Warning: [interfaces.{}] in {} configures an interface that no longer exists", name, config::CONFIG_FILE);
+        }
+    }
+
+    // Warn early if the workspace runtime crates don't match the generator's output conventions
+    for warning in compat::check_workspace(cwd, config) {
+        log!("Warning: {}", warning);
     }
-    
+
     // Step 2: Create caller-utils crate with stubs
-    println!("\n=== STEP 2: Generating Caller Utils Crate ===");
+    log!("\n=== STEP 2: Generating Caller Utils Crate ===");
     if !interfaces.is_empty() {
-        caller_utils_generator::create_caller_utils(&cwd, &api_dir, &processed_projects)?;
+        caller_utils_generator::create_caller_utils(cwd, &api_dir, &processed_projects, config)?;
     } else {
-        println!("No interfaces found, skipping caller-utils creation");
+        log!("No interfaces found, skipping caller-utils creation");
     }
-    
+
     // Print summary
-    println!("\n=== Summary ===");
-    println!("- Processed {} Rust projects", processed_projects.len());
-    println!("- Generated {} WIT interface files", interfaces.len());
+    log!("\n=== Summary ===");
+    log!("- Processed {} Rust projects", processed_projects.len());
+    log!("- Generated {} WIT interface files", interfaces.len());
     if !interfaces.is_empty() {
-        println!("- Created caller-utils crate with stub implementations");
-        println!("- Updated workspace Cargo.toml");
-        println!("- Added caller-utils dependency to projects");
+        if config.types_only {
+            log!("- Created types-only caller-utils crate");
+        } else {
+            log!("- Created caller-utils crate with stub implementations");
+        }
+        log!("- Updated workspace Cargo.toml");
+        log!("- Added caller-utils dependency to projects");
     }
-    println!("\nAll operations completed successfully!");
-    
-    Ok(())
-}
\ No newline at end of file
+    report::log_skipped(&report::take_skipped());
+    log!("\nAll operations completed successfully!");
+
+    Ok(processed_projects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A workspace with the api world and one process crate, in a fresh temporary directory
+    fn fixture_workspace(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hyper-bindgen-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let files = [
+            ("Cargo.toml", "[workspace]\nresolver = \"2\"\nmembers = [\"chat\"]\n"),
+            (
+                "api/chat-app-dot-os-v0.wit",
+                "world chat-app-dot-os-v0 {\n    import chat;\n    include process-v1;\n}\n",
+            ),
+            (
+                "chat/Cargo.toml",
+                "[package]\nname = \"chat\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                 [dependencies]\nserde = \"1.0\"\n\n[package.metadata.component]\npackage = \"hyperware:process\"\n",
+            ),
+            ("chat/src/lib.rs", CHAT_PROCESS),
+        ];
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    const CHAT_PROCESS: &str = r#"use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub author: String,
+    pub body: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Status {
+    Online,
+    Away(String),
+}
+
+#[derive(Default)]
+pub struct ChatState {}
+
+#[hyperprocess(
+    name = "Chat",
+    wit_world = "chat-app-dot-os-v0"
+)]
+impl ChatState {
+    #[remote]
+    #[local]
+    fn send_message(&mut self, message: ChatMessage, channel: String) -> bool {
+        true
+    }
+
+    #[http]
+    fn get_history(&self, channel: String) -> Vec<ChatMessage> {
+        vec![]
+    }
+
+    #[remote]
+    fn ping(&self) -> String {
+        "pong".into()
+    }
+
+    #[local]
+    fn set_status(&mut self, status: Status) {}
+}
+"#;
+
+    #[test]
+    fn second_generation_changes_nothing() {
+        let cwd = fixture_workspace("idempotent");
+        let config = Config::default();
+        let projects = run_generation(&cwd, &config).unwrap();
+        assert_eq!(projects.len(), 1);
+        assert!(cwd.join("caller-utils/src/lib.rs").is_file());
+
+        let outputs = generated_paths(&cwd, &projects, &config).unwrap();
+        let before = output::snapshot(&outputs).unwrap();
+        run_generation(&cwd, &config).unwrap();
+        let after = output::snapshot(&outputs).unwrap();
+        assert_eq!(output::diff_snapshots(&before, &after), Vec::<String>::new());
+
+        fs::remove_dir_all(&cwd).unwrap();
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::migration::GENERATOR_VERSION;
+use crate::output;
+use crate::paths;
+
+// Files a generated crate consists of, written to the crate directory
+pub const MANIFEST_FILE: &str = ".hyper-bindgen-manifest.json";
+
+// Every file hyper-bindgen owns in a generated crate, keyed by its path relative to the crate
+// directory, with the SHA-256 of the contents it wrote
+#[derive(Serialize, Deserialize, Default)]
+pub struct Manifest {
+    generator: String,
+    files: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    // Whether the manifest lists a file of the directory it was written to
+    pub fn lists(&self, dir: &Path, file: &Path) -> bool {
+        self.files.contains_key(&paths::relative_path(dir, file))
+    }
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    let content = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:x}", Sha256::digest(&content)))
+}
+
+// Read the manifest of a generated crate, if it has one
+pub fn read(crate_dir: &Path) -> Result<Option<Manifest>> {
+    let path = crate_dir.join(MANIFEST_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let manifest = serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(manifest))
+}
+
+// Record the files a run generated, deleting those the previous run generated and this one didn't
+pub fn record(crate_dir: &Path, files: &[PathBuf]) -> Result<()> {
+    let manifest = replace(crate_dir, files)?.0;
+
+    for file in foreign_files(crate_dir, &manifest) {
+        log!(
+            "Warning: {} is not generated by hyper-bindgen; files added to a generated crate are not maintained",
+            file.display()
+        );
+    }
+    Ok(())
+}
+
+// Write the manifest of a directory hyper-bindgen shares with hand-written files, returning the
+// files of the previous manifest that are no longer generated, after deleting them
+pub fn replace_shared(dir: &Path, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    Ok(replace(dir, files)?.1)
+}
+
+fn replace(dir: &Path, files: &[PathBuf]) -> Result<(Manifest, Vec<PathBuf>)> {
+    let previous = read(dir)?.unwrap_or_default();
+
+    let mut manifest = Manifest {
+        generator: GENERATOR_VERSION.to_string(),
+        files: BTreeMap::new(),
+    };
+    for file in files {
+        manifest.files.insert(paths::relative_path(dir, file), hash_file(file)?);
+    }
+
+    let mut removed = Vec::new();
+    for stale in previous.files.keys().filter(|file| !manifest.files.contains_key(*file)) {
+        let path = dir.join(stale);
+        if path.is_file() {
+            log!("Removing {}, which is no longer generated", path.display());
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            // Directories of generated files, like `src/bin`, go with their last file; removing a
+            // directory that still has other files fails and is ignored
+            if let Some(parent) = path.parent().filter(|parent| *parent != dir) {
+                let _ = fs::remove_dir(parent);
+            }
+            removed.push(path);
+        }
+    }
+
+    let content = serde_json::to_string_pretty(&manifest)? + "\n";
+    output::write_if_changed(&dir.join(MANIFEST_FILE), content)?;
+    Ok((manifest, removed))
+}
+
+// Files in a generated crate that its manifest doesn't list, outside the build output
+fn foreign_files(crate_dir: &Path, manifest: &Manifest) -> Vec<PathBuf> {
+    WalkDir::new(crate_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.path() != crate_dir.join("target"))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            path.file_name().is_some_and(|name| name != MANIFEST_FILE)
+                && !manifest.files.contains_key(&paths::relative_path(crate_dir, path))
+        })
+        .collect()
+}
+
+// Differences between a generated crate and its manifest: generated files that were edited or
+// deleted, and files that were added by hand
+pub fn drift(crate_dir: &Path) -> Result<Vec<String>> {
+    let Some(manifest) = read(crate_dir)? else {
+        return Ok(vec![format!("{} has no {}", crate_dir.display(), MANIFEST_FILE)]);
+    };
+
+    let mut drift = Vec::new();
+    for (file, hash) in &manifest.files {
+        let path = crate_dir.join(file);
+        if !path.exists() {
+            drift.push(format!("{} was deleted", path.display()));
+        } else if hash_file(&path)? != *hash {
+            drift.push(format!("{} was edited by hand", path.display()));
+        }
+    }
+    for file in foreign_files(crate_dir, &manifest) {
+        drift.push(format!("{} was added by hand", file.display()));
+    }
+    Ok(drift)
+}
+
+// Delete every file a generated crate's manifest lists, the manifest itself and the directories
+// left empty inside the workspace
+pub fn clean(base_dir: &Path, crate_dir: &Path) -> Result<()> {
+    let Some(manifest) = read(crate_dir)? else {
+        log!("{} has no {}, nothing to clean", crate_dir.display(), MANIFEST_FILE);
+        return Ok(());
+    };
+
+    let mut directories = Vec::new();
+    for file in manifest.files.keys().map(String::as_str).chain([MANIFEST_FILE]) {
+        let path = paths::normalize(&crate_dir.join(file));
+        if path.is_file() {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            log!("  Removed {}", path.display());
+        }
+        directories.extend(path.ancestors().skip(1).map(Path::to_path_buf));
+    }
+
+    // Deepest first, so parents are empty by the time they are reached
+    directories.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    directories.dedup();
+    for dir in directories {
+        if dir == base_dir || !dir.starts_with(base_dir) {
+            continue;
+        }
+        if fs::read_dir(&dir).is_ok_and(|mut entries| entries.next().is_none()) {
+            fs::remove_dir(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,449 @@
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use hyper_bindgen::case::{to_kebab_case, to_snake_case};
+
+use crate::caller_utils_generator::{module_path, to_rust_identifier};
+use crate::config::{Config, StubNames};
+use crate::paths;
+use crate::text;
+use crate::wit_format;
+use crate::wit_generator::{package_name, to_wit_identifier};
+
+// How a hand-rolled request passes its arguments under its `{"Name": ...}` key
+enum Payload {
+    // `(a, b)` or `[a, b]`, the positional encoding the stubs send (empty for `{}`)
+    Positional(Vec<String>),
+    // `{"a": x, "b": y}`, arguments named by the keys of an object
+    Named(Vec<(String, String)>),
+    // Any other single value
+    Single(String),
+}
+
+// A request built with `json!({"Name": ...})`
+struct CallSite {
+    file: PathBuf,
+    line: usize,
+    name: String,
+    payload: Payload,
+    // Byte range of the `json!(..)` invocation in the file
+    span: Range<usize>,
+}
+
+// The requests a process crate builds and the places it matches incoming requests by name
+pub struct Scan {
+    calls: Vec<CallSite>,
+    // Locations of each request name matched as a string, like `"SendMessage" =>`
+    handlers: BTreeMap<String, Vec<String>>,
+}
+
+// Index just past a string literal, `'"'` character literal or comment starting at `i`, or None
+// when nothing is skipped there
+fn skip(content: &str, i: usize) -> Option<usize> {
+    let rest = &content[i..];
+    if rest.starts_with("//") {
+        return Some(rest.find('\n').map_or(content.len(), |end| i + end));
+    }
+    if rest.starts_with("/*") {
+        return Some(rest.find("*/").map_or(content.len(), |end| i + end + 2));
+    }
+    if rest.starts_with("'\"'") {
+        return Some(i + 3);
+    }
+    if rest.starts_with('"') {
+        let mut escaped = false;
+        for (offset, c) in rest.char_indices().skip(1) {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => return Some(i + offset + 1),
+                _ => escaped = false,
+            }
+        }
+        return Some(content.len());
+    }
+    None
+}
+
+// Index of the bracket closing the one at `open`, passing over strings and comments
+fn closing(content: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = open;
+    while i < content.len() {
+        if let Some(next) = skip(content, i) {
+            i = next;
+            continue;
+        }
+        let c = content[i..].chars().next()?;
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    None
+}
+
+// Split a list of expressions at the commas outside brackets and strings
+fn split_top_level(list: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < list.len() {
+        if let Some(next) = skip(list, i) {
+            i = next;
+            continue;
+        }
+        let c = list[i..].chars().next().unwrap_or_default();
+        if matches!(c, '(' | '[' | '{') {
+            i = closing(list, i).map_or(list.len(), |close| close + 1);
+            continue;
+        }
+        if c == ',' {
+            items.push(list[start..i].trim().to_string());
+            start = i + 1;
+        }
+        i += c.len_utf8();
+    }
+    items.push(list[start..].trim().to_string());
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+// The key and value of a `"key": value` entry of a `json!` object
+fn split_entry(entry: &str) -> Option<(String, String)> {
+    let end = skip(entry, 0)?;
+    let key = entry.get(1..end - 1)?;
+    let value = entry[end..].trim_start().strip_prefix(':')?;
+    Some((key.to_string(), value.trim().to_string()))
+}
+
+// Request names are the PascalCase variant names serde uses as keys
+fn is_request_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+// Whether the bracket at the start of `value` closes at its end
+fn is_bracketed(value: &str, open: char) -> bool {
+    value.starts_with(open) && closing(value, 0) == Some(value.len() - 1)
+}
+
+// The request name and payload of the body of a `json!(..)` invocation
+fn parse_request(body: &str) -> Option<(String, Payload)> {
+    let object = body.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let [entry] = split_top_level(object).try_into().ok()?;
+    let (name, value) = split_entry(&entry)?;
+    if !is_request_name(&name) {
+        return None;
+    }
+
+    let payload = if matches!(value.as_str(), "{}" | "()" | "[]") {
+        Payload::Positional(Vec::new())
+    } else if is_bracketed(&value, '[') {
+        Payload::Positional(split_top_level(&value[1..value.len() - 1]))
+    } else if is_bracketed(&value, '(') {
+        let inner = &value[1..value.len() - 1];
+        let items = split_top_level(inner);
+        // `(x)` only groups an expression, `(x,)` is a tuple
+        if items.len() == 1 && !inner.trim_end().ends_with(',') {
+            Payload::Single(items[0].clone())
+        } else {
+            Payload::Positional(items)
+        }
+    } else if is_bracketed(&value, '{') {
+        let entries: Option<Vec<_>> = split_top_level(&value[1..value.len() - 1])
+            .iter()
+            .map(|entry| split_entry(entry))
+            .collect();
+        match entries {
+            Some(entries) => Payload::Named(entries),
+            None => Payload::Single(value),
+        }
+    } else {
+        Payload::Single(value)
+    };
+    Some((name, payload))
+}
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
+// Scan the sources of a process crate for `json!` requests and requests matched by name
+pub fn scan(project: &Path) -> Result<Scan> {
+    let src_dir = project.join("src");
+    if !src_dir.is_dir() {
+        bail!("{} has no src directory", project.display());
+    }
+
+    let mut calls = Vec::new();
+    let mut handlers = BTreeMap::<String, Vec<String>>::new();
+    for entry in WalkDir::new(&src_dir).sort_by_file_name().into_iter().filter_map(Result::ok) {
+        let path = entry.path();
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "rs")) {
+            continue;
+        }
+        let content = text::read_normalized(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let location = |offset| format!("{}:{}", paths::relative_path(project, path), line_of(&content, offset));
+
+        let mut i = 0;
+        while i < content.len() {
+            if let Some(next) = skip(&content, i) {
+                // String literals matched in a `match` arm or looked up in a map name a handler
+                let literal = &content[i..next];
+                if let Some(name) = literal.strip_prefix('"').and_then(|l| l.strip_suffix('"')) {
+                    let before = content[..i].trim_end();
+                    let after = content[next..].trim_start();
+                    // `"Name" =>` or `Some("Name") =>`
+                    let matched = after.trim_start_matches([')', ' ']).starts_with("=>")
+                        || before.ends_with("get(")
+                        || before.ends_with("contains_key(")
+                        || (before.ends_with('[') && after.starts_with(']'));
+                    if is_request_name(name) && matched {
+                        handlers.entry(name.to_string()).or_default().push(location(i));
+                    }
+                }
+                i = next;
+                continue;
+            }
+            if content[i..].starts_with("json!(") {
+                let open = i + "json!".len();
+                if let Some(close) = closing(&content, open) {
+                    if let Some((name, payload)) = parse_request(&content[open + 1..close]) {
+                        calls.push(CallSite {
+                            file: path.to_path_buf(),
+                            line: line_of(&content, i),
+                            name,
+                            payload,
+                            span: i..close + 1,
+                        });
+                    }
+                }
+            }
+            i += content[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    Ok(Scan { calls, handlers })
+}
+
+// WIT name of the argument an expression passes, like `message` for `&msg.message.clone()`
+fn argument_name(expression: &str) -> Option<String> {
+    let mut expression = expression.trim().trim_start_matches('&').trim_start_matches("mut ").trim_start_matches('*');
+    for suffix in [".clone()", ".to_string()", ".to_owned()", ".into()"] {
+        expression = expression.strip_suffix(suffix).unwrap_or(expression);
+    }
+    let name = expression.rsplit(['.', ':']).next()?;
+    let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    is_identifier.then(|| to_wit_identifier(name))
+}
+
+// WIT type of a literal expression
+fn literal_type(expression: &str) -> Option<&'static str> {
+    let expression = expression.trim();
+    let literal = expression
+        .strip_suffix(".to_string()")
+        .or_else(|| expression.strip_prefix("String::from(").and_then(|e| e.strip_suffix(')')))
+        .unwrap_or(expression);
+    if literal.starts_with('"') && skip(literal, 0) == Some(literal.len()) {
+        Some("string")
+    } else if literal == "true" || literal == "false" {
+        Some("bool")
+    } else if literal.parse::<u64>().is_ok() {
+        Some("u64")
+    } else if literal.parse::<i64>().is_ok() {
+        Some("s64")
+    } else if literal.parse::<f64>().is_ok() {
+        Some("f64")
+    } else {
+        None
+    }
+}
+
+// Name of the interface a process crate gets, from its package name
+fn interface_name(project: &Path) -> Result<String> {
+    Ok(to_kebab_case(&package_name(project)?))
+}
+
+// Propose an interface with a signature record for every request the crate sends or handles.
+// Argument names come from the expressions the first call site passes and their types from
+// literals; anything that can't be derived is marked with a `TODO` comment.
+pub fn propose(project: &Path, scan: &Scan) -> Result<String> {
+    let names: BTreeSet<&String> = scan.calls.iter().map(|call| &call.name).chain(scan.handlers.keys()).collect();
+    if names.is_empty() {
+        bail!(
+            "Found no requests built with json!({{\"Name\": ..}}) or matched by name in {}",
+            project.join("src").display()
+        );
+    }
+
+    let interface = interface_name(project)?;
+    let mut warnings = Vec::new();
+    let mut records = String::new();
+    for name in names {
+        let function = to_kebab_case(name);
+        let calls: Vec<&CallSite> = scan.calls.iter().filter(|call| call.name == *name).collect();
+        if let Some(handlers) = scan.handlers.get(name) {
+            records.push_str(&format!("// Handled at {}\n", handlers.join(", ")));
+        }
+        if !calls.is_empty() {
+            let sent_from: Vec<String> = calls
+                .iter()
+                .map(|call| format!("{}:{}", paths::relative_path(project, &call.file), call.line))
+                .collect();
+            records.push_str(&format!("// Sent from {}\n", sent_from.join(", ")));
+        }
+
+        // (argument name, expression passed for it) from the first call site
+        let arguments: Vec<(String, String)> = match calls.first().map(|call| &call.payload) {
+            None => {
+                warnings.push(format!("no call site shows the arguments of {}", name));
+                Vec::new()
+            }
+            Some(Payload::Positional(expressions)) => expressions
+                .iter()
+                .enumerate()
+                .map(|(index, expression)| {
+                    let name = argument_name(expression).unwrap_or_else(|| format!("arg{}", index));
+                    (name, expression.clone())
+                })
+                .collect(),
+            Some(Payload::Named(entries)) => {
+                warnings.push(format!(
+                    "{} is sent as an object; the stubs send arguments as a list in this order",
+                    name
+                ));
+                entries.iter().map(|(key, value)| (to_wit_identifier(key), value.clone())).collect()
+            }
+            Some(Payload::Single(expression)) => {
+                warnings.push(format!(
+                    "{} is sent as a single value; the stubs send it in a one-element list",
+                    name
+                ));
+                vec![(argument_name(expression).unwrap_or_else(|| "value".to_string()), expression.clone())]
+            }
+        };
+        let counts: BTreeSet<Option<usize>> = calls
+            .iter()
+            .map(|call| match &call.payload {
+                Payload::Positional(expressions) => Some(expressions.len()),
+                Payload::Named(entries) => Some(entries.len()),
+                Payload::Single(_) => None,
+            })
+            .collect();
+        if counts.len() > 1 {
+            warnings.push(format!("the call sites of {} pass different arguments", name));
+        }
+
+        records.push_str(&format!("// Function signature for: {} (remote)\n", function));
+        records.push_str(&format!("record {}-signature-remote {{\n", function));
+        records.push_str("    target: address,\n");
+        let mut used = BTreeSet::new();
+        for (index, (argument, expression)) in arguments.iter().enumerate() {
+            let argument = if used.insert(argument.clone()) { argument.clone() } else { format!("{}{}", argument, index) };
+            match literal_type(expression) {
+                Some(wit_type) => records.push_str(&format!("    {}: {},\n", argument, wit_type)),
+                None => {
+                    let expression = expression.split_whitespace().collect::<Vec<_>>().join(" ");
+                    records.push_str(&format!("    {}: string, // TODO: type of `{}`\n", argument, expression));
+                }
+            }
+        }
+        records.push_str("    returning: unit, // TODO: type of the response\n");
+        records.push_str("}\n\n");
+    }
+
+    let mut wit = String::new();
+    for warning in &warnings {
+        wit.push_str(&format!("// TODO: {}\n", warning));
+    }
+    wit.push_str(&format!(
+        "// Proposed by `hyper-bindgen migrate`: save it as api/{}.wit, import it in the world of\n\
+         // the process and use `local` signatures for requests from the same node\n",
+        interface
+    ));
+    wit.push_str(&format!("interface {} {{\nuse standard.{{address}};\n\n{}}}\n", interface, records));
+    Ok(wit_format::format(&wit))
+}
+
+// Byte range of the `Request::to(target)..send_and_await_response(..)` chain around a call site,
+// with its target expression
+fn send_chain(content: &str, call: &CallSite) -> Option<(Range<usize>, String)> {
+    let before = &content[..call.span.start];
+    let mut start = before.rfind("Request::to(")?;
+    let open = start + "Request::to".len();
+    // Include a path the type is named by, like `hyperware_process_lib::Request`
+    start -= before[..start]
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == ':')
+        .count();
+    let target_close = closing(content, open)?;
+    let target = content[open + 1..target_close].trim().to_string();
+
+    let send = call.span.end + content[call.span.end..].find(".send_and_await_response(")?;
+    // The chain must be a single expression containing the request
+    if content[start..call.span.start].contains(';') || content[call.span.end..send].contains(';') {
+        return None;
+    }
+    let end = closing(content, send + ".send_and_await_response".len())? + 1;
+    Some((start..end, target))
+}
+
+// Rewrite the call sites sending a positional request through a `Request::to(..)` chain to the
+// remote stubs of the proposed interface, returning the new content of every changed file
+pub fn rewrite(project: &Path, scan: &Scan, config: &Config, crate_name: &str) -> Result<Vec<(PathBuf, String)>> {
+    let module = format!("{}::{}", to_snake_case(crate_name), module_path(config, &interface_name(project)?));
+    let mut files = BTreeMap::<&PathBuf, Vec<&CallSite>>::new();
+    for call in &scan.calls {
+        files.entry(&call.file).or_default().push(call);
+    }
+
+    let mut rewritten = Vec::new();
+    for (file, calls) in files {
+        let mut content = text::read_normalized(file).with_context(|| format!("Failed to read {}", file.display()))?;
+        let location = |call: &CallSite| format!("{}:{}", paths::relative_path(project, file), call.line);
+        let mut replacements = Vec::new();
+        for call in calls {
+            let Payload::Positional(arguments) = &call.payload else {
+                log!("Left {} as is: the stubs send its arguments differently", location(call));
+                continue;
+            };
+            let Some((range, target)) = send_chain(&content, call) else {
+                log!("Left {} as is: it isn't sent by a Request::to(..)...send_and_await_response(..) chain", location(call));
+                continue;
+            };
+            let target = match target.strip_prefix('&') {
+                Some(_) => target,
+                None => format!("&{}", target.strip_suffix(".clone()").unwrap_or(&target)),
+            };
+            let arguments: Vec<String> = std::iter::once(target).chain(arguments.iter().cloned()).collect();
+            let function = to_kebab_case(&call.name);
+            let stub = match config.stub_names {
+                StubNames::Full => format!("{}_remote_rpc", to_snake_case(&function)),
+                StubNames::Short | StubNames::Bare => to_rust_identifier(&function),
+            };
+            log!("Rewrote {} to call {}; stubs return SendResult<T>, review how the response is handled", location(call), stub);
+            replacements.push((range, format!("{}::{}({}).await", module, stub, arguments.join(", "))));
+        }
+        if replacements.is_empty() {
+            continue;
+        }
+        // Last first, so the earlier ranges stay valid
+        replacements.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        replacements.dedup_by_key(|(range, _)| range.start);
+        for (range, replacement) in replacements {
+            content.replace_range(range, &replacement);
+        }
+        rewritten.push((file.clone(), content));
+    }
+    Ok(rewritten)
+}
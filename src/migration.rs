@@ -0,0 +1,231 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::manifest;
+
+// Version of the hyper-bindgen binary writing the generated crate
+pub const GENERATOR_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Layout version of the generated caller-utils crate.
+// Bump this whenever the module structure or preamble of the generated lib.rs changes.
+//   1: single lib.rs without a metadata header (hyper-bindgen <= 0.1.0)
+//   2: single lib.rs with a metadata header
+//   3: WIT files copied to caller-utils/wit instead of caller-utils/target/wit
+//   4: interface modules nested under their configured module-path, and per-interface and
+//      crate-level prelude modules
+pub const LAYOUT_VERSION: u32 = 4;
+
+const HEADER_MARKER: &str = "// This file is generated by hyper-bindgen. Do not edit by hand.";
+const VERSION_KEY: &str = "// hyper-bindgen-version:";
+const LAYOUT_KEY: &str = "// hyper-bindgen-layout:";
+
+// Markers that older hyper-bindgen versions left in the files they generated
+const LEGACY_MARKERS: [&str; 3] = [
+    "Generated caller utilities for RPC function stubs",
+    "Generated RPC stubs for the",
+    "Generated stub for `",
+];
+
+// Metadata read back from the header of a previously generated lib.rs
+#[derive(Debug)]
+pub struct CrateHeader {
+    pub generator_version: String,
+    pub layout: u32,
+}
+
+// Render the metadata header placed at the top of the generated lib.rs
+pub fn render_header() -> String {
+    format!(
+        "{}\n{} {}\n{} {}\n\n",
+        HEADER_MARKER, VERSION_KEY, GENERATOR_VERSION, LAYOUT_KEY, LAYOUT_VERSION
+    )
+}
+
+// Parse the metadata header from the contents of a generated lib.rs
+pub fn read_header(content: &str) -> Option<CrateHeader> {
+    let mut lines = content.lines().map(str::trim);
+    if lines.next()? != HEADER_MARKER {
+        return None;
+    }
+
+    let mut generator_version = None;
+    let mut layout = None;
+    for line in lines.take_while(|line| line.starts_with("//")) {
+        if let Some(value) = line.strip_prefix(VERSION_KEY) {
+            generator_version = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix(LAYOUT_KEY) {
+            layout = value.trim().parse().ok();
+        }
+    }
+
+    Some(CrateHeader {
+        generator_version: generator_version?,
+        layout: layout?,
+    })
+}
+
+// Check whether a file was written by a pre-header hyper-bindgen version
+fn is_legacy_generated(content: &str) -> bool {
+    LEGACY_MARKERS.iter().any(|marker| content.contains(marker))
+}
+
+// Inspect an existing caller-utils crate before regenerating it.
+//
+// Crates written with the current layout are left alone. Crates written by an older
+// hyper-bindgen are migrated by removing the generated files the new layout no longer
+// uses. Anything we cannot account for makes us refuse with instructions instead of
+// mixing old and new structures.
+pub fn prepare_existing_crate(caller_utils_dir: &Path) -> Result<()> {
+    let src_dir = caller_utils_dir.join("src");
+    let lib_rs = src_dir.join("lib.rs");
+
+    if !lib_rs.exists() {
+        log!("No existing caller-utils lib.rs found, nothing to migrate");
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&lib_rs)
+        .with_context(|| format!("Failed to read existing lib.rs: {}", lib_rs.display()))?;
+
+    let layout = match read_header(&content) {
+        Some(header) => {
+            log!(
+                "Existing caller-utils generated by hyper-bindgen {} (layout {})",
+                header.generator_version, header.layout
+            );
+            header.layout
+        }
+        None if is_legacy_generated(&content) => {
+            log!("Existing caller-utils has no metadata header, assuming layout 1");
+            1
+        }
+        None => bail!(
+            "{} exists but was not generated by hyper-bindgen.\n\
+             Refusing to overwrite it. To continue, either:\n\
+             - move or rename the existing caller-utils crate, or\n\
+             - delete {} if it is safe to regenerate",
+            caller_utils_dir.display(),
+            lib_rs.display()
+        ),
+    };
+
+    if layout > LAYOUT_VERSION {
+        bail!(
+            "{} was generated by a newer hyper-bindgen (layout {}, this binary writes layout {}).\n\
+             Upgrade hyper-bindgen with `cargo install --path <hyper-bindgen checkout>` and run it again.",
+            caller_utils_dir.display(),
+            layout,
+            LAYOUT_VERSION
+        );
+    }
+
+    if layout == LAYOUT_VERSION {
+        return Ok(());
+    }
+
+    log!(
+        "Migrating caller-utils from layout {} to layout {}",
+        layout, LAYOUT_VERSION
+    );
+
+    // Older versions emitted one file per interface module next to lib.rs.
+    // The current layout keeps every module inline, so those files must go. Files the crate's
+    // manifest lists, like the `src/bin` CLI, are regenerated in place.
+    let manifest = manifest::read(caller_utils_dir)?;
+    let mut generated_files = Vec::new();
+    let mut foreign_files = Vec::new();
+    for entry in WalkDir::new(&src_dir).sort_by_file_name() {
+        let entry = entry.with_context(|| format!("Failed to read {}", src_dir.display()))?;
+        let path = entry.into_path();
+        if path == lib_rs || !path.is_file() {
+            continue;
+        }
+        if manifest.as_ref().is_some_and(|manifest| manifest.lists(caller_utils_dir, &path)) {
+            continue;
+        }
+        if fs::read_to_string(&path).is_ok_and(|content| is_legacy_generated(&content)) {
+            generated_files.push(path);
+        } else {
+            foreign_files.push(path);
+        }
+    }
+
+    if !foreign_files.is_empty() {
+        let listing: Vec<String> = foreign_files
+            .iter()
+            .map(|path| format!("  - {}", path.display()))
+            .collect();
+        bail!(
+            "Cannot migrate {} automatically: its src directory contains files hyper-bindgen did not generate:\n{}\n\
+             Move these files out of the generated crate (it is fully owned by hyper-bindgen) and run again.",
+            caller_utils_dir.display(),
+            listing.join("\n")
+        );
+    }
+
+    remove_legacy_files(&generated_files)?;
+
+    // Layouts before 3 copied the WIT files into the crate's target directory
+    let legacy_wit_dir = caller_utils_dir.join("target").join("wit");
+    if layout < 3 && legacy_wit_dir.is_dir() {
+        log!("  Removing legacy WIT copy: {}", legacy_wit_dir.display());
+        fs::remove_dir_all(&legacy_wit_dir)
+            .with_context(|| format!("Failed to remove {}", legacy_wit_dir.display()))?;
+    }
+    log!("Migration complete, regenerating caller-utils");
+    Ok(())
+}
+
+// Delete files left behind by an older layout
+fn remove_legacy_files(files: &[PathBuf]) -> Result<()> {
+    for path in files {
+        log!("  Removing legacy generated file: {}", path.display());
+        fs::remove_file(path).with_context(|| format!("Failed to remove legacy file: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A caller-utils crate generated at `layout` with the `cli = true` binary, in a fresh
+    // temporary directory
+    fn crate_with_cli(name: &str, layout: u32) -> PathBuf {
+        let crate_dir = std::env::temp_dir().join(format!("hyper-bindgen-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&crate_dir);
+        let bin_dir = crate_dir.join("src").join("bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        let lib_rs = crate_dir.join("src").join("lib.rs");
+        let header = render_header().replace(
+            &format!("{} {}", LAYOUT_KEY, LAYOUT_VERSION),
+            &format!("{} {}", LAYOUT_KEY, layout),
+        );
+        fs::write(&lib_rs, header + "pub mod chat {}\n").unwrap();
+        let cli = bin_dir.join("caller-utils-cli.rs");
+        fs::write(&cli, "fn main() {}\n").unwrap();
+        manifest::record(&crate_dir, &[lib_rs, cli]).unwrap();
+        crate_dir
+    }
+
+    #[test]
+    fn migrates_crate_with_cli_binary() {
+        let crate_dir = crate_with_cli("migrate-cli", LAYOUT_VERSION - 1);
+        prepare_existing_crate(&crate_dir).unwrap();
+        assert!(crate_dir.join("src").join("bin").join("caller-utils-cli.rs").is_file());
+        fs::remove_dir_all(&crate_dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_hand_written_files_next_to_cli_binary() {
+        let crate_dir = crate_with_cli("migrate-foreign", LAYOUT_VERSION - 1);
+        let tool = crate_dir.join("src").join("bin").join("tool.rs");
+        fs::write(&tool, "fn main() {}\n").unwrap();
+        let error = prepare_existing_crate(&crate_dir).unwrap_err().to_string();
+        assert!(error.contains("tool.rs"), "{}", error);
+        assert!(!error.contains("caller-utils-cli.rs"), "{}", error);
+        fs::remove_dir_all(&crate_dir).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::output;
+use crate::text;
+
+// Path, relative to a process's HTTP root, where it publishes the WIT description of its API
+pub const API_PATH: &str = "api";
+
+// A process ID `process:package:publisher`, optionally prefixed with `node@`
+pub struct ProcessAddress {
+    pub node: Option<String>,
+    pub process: String,
+    pub package: String,
+    pub publisher: String,
+}
+
+impl ProcessAddress {
+    pub fn parse(address: &str) -> Result<ProcessAddress> {
+        let (node, process_id) = match address.split_once('@') {
+            Some((node, process_id)) => (Some(node.to_string()), process_id),
+            None => (None, address),
+        };
+        let parts: Vec<&str> = process_id.split(':').collect();
+        let [process, package, publisher] = parts[..] else {
+            bail!(
+                "Invalid process address `{}`, expected `[node@]process:package:publisher`",
+                address
+            );
+        };
+        Ok(ProcessAddress {
+            node,
+            process: process.to_string(),
+            package: package.to_string(),
+            publisher: publisher.to_string(),
+        })
+    }
+
+    // World name following the Hyperware convention, e.g. `chat-app-dot-os-v0`
+    pub fn world_name(&self) -> String {
+        format!("{}-{}-v0", self.package, self.publisher.replace('.', "-dot-"))
+    }
+}
+
+impl std::fmt::Display for ProcessAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(node) = &self.node {
+            write!(f, "{}@", node)?;
+        }
+        write!(f, "{}:{}:{}", self.process, self.package, self.publisher)
+    }
+}
+
+// Fetch a URL with curl, which every platform hyper-bindgen runs on ships with
+fn fetch(url: &str) -> Result<String> {
+    log!("Fetching {}", url);
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--max-time", "30", url])
+        .output()
+        .with_context(|| "Failed to run curl, which from-node needs to query the node")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to fetch {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).with_context(|| format!("{} did not return UTF-8 text", url))
+}
+
+// Split a WIT document into its top-level interfaces, keyed by interface name
+fn split_interfaces(wit: &str) -> BTreeMap<String, String> {
+    let mut interfaces = BTreeMap::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+    let mut depth = 0usize;
+    // Whether the current interface's `{` has been seen yet: it may be on the line after its name
+    let mut opened = false;
+
+    for line in wit.lines() {
+        let trimmed = line.trim();
+        if current.is_none() {
+            if let Some(rest) = trimmed.strip_prefix("interface ") {
+                let name = rest.trim_end_matches('{').trim().to_string();
+                current = Some((name, Vec::new()));
+                depth = 0;
+                opened = false;
+            } else {
+                continue;
+            }
+        }
+        let Some((name, lines)) = current.as_mut() else {
+            continue;
+        };
+        lines.push(line);
+        opened |= line.contains('{');
+        depth += line.matches('{').count();
+        depth = depth.saturating_sub(line.matches('}').count());
+        if opened && depth == 0 {
+            interfaces.insert(std::mem::take(name), lines.join("\n") + "\n");
+            current = None;
+        }
+    }
+    interfaces
+}
+
+// The file name for an interface a node published: a WIT identifier, without the `%` escaping
+// keywords. Anything else, like `../../foo`, would write outside the api directory.
+fn interface_file_name(name: &str) -> Result<String> {
+    let name = name.trim_start_matches('%');
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        bail!("Node published an interface named `{}`, which is not a WIT identifier", name);
+    }
+    Ok(name.to_string())
+}
+
+// Query a process on a running node for its API and write its interfaces to the api directory.
+// The process serves either WIT text or a JSON object mapping WIT file names to their contents.
+// Returns the names of the interfaces written.
+pub fn fetch_wit(node_url: &str, address: &ProcessAddress, api_dir: &Path) -> Result<Vec<String>> {
+    let url = format!(
+        "{}/{}:{}:{}/{}",
+        node_url.trim_end_matches('/'),
+        address.process,
+        address.package,
+        address.publisher,
+        API_PATH
+    );
+    let body = fetch(&url)?;
+
+    let documents: Vec<String> = if body.trim_start().starts_with('{') {
+        let files: BTreeMap<String, String> = serde_json::from_str(&body)
+            .with_context(|| format!("{} returned JSON that is not a map of WIT files", url))?;
+        files.into_values().collect()
+    } else {
+        vec![body]
+    };
+
+    fs::create_dir_all(api_dir)?;
+    let mut written = Vec::new();
+    for document in &documents {
+        for (name, content) in split_interfaces(&text::normalize(document)) {
+            let name = interface_file_name(&name).with_context(|| format!("Invalid WIT from {}", url))?;
+            let interface_file = api_dir.join(format!("{}.wit", name));
+            if interface_file.exists() {
+                log!("  Overwriting existing {}", interface_file.display());
+            }
+            output::write_if_changed(&interface_file, content)?;
+            log!("  Wrote interface {} to {}", name, interface_file.display());
+            written.push(name);
+        }
+    }
+
+    if written.is_empty() {
+        bail!("{} published no WIT interfaces at {}", address, url);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interfaces_split_with_brace_on_next_line() {
+        let wit = "package chat:app@0.1.0;\n\ninterface chat\n{\n    send: func();\n}\n\ninterface %flags {\n    record r {\n        a: u32\n    }\n}\n";
+        let interfaces = split_interfaces(wit);
+        assert_eq!(interfaces.keys().collect::<Vec<_>>(), ["%flags", "chat"]);
+        assert_eq!(interfaces["chat"], "interface chat\n{\n    send: func();\n}\n");
+        assert_eq!(interfaces["%flags"], "interface %flags {\n    record r {\n        a: u32\n    }\n}\n");
+    }
+
+    #[test]
+    fn interface_names_must_be_wit_identifiers() {
+        assert_eq!(interface_file_name("chat").unwrap(), "chat");
+        assert_eq!(interface_file_name("%flags").unwrap(), "flags");
+        assert_eq!(interface_file_name("sha256-hash").unwrap(), "sha256-hash");
+        for name in ["../../foo", "a/b", "Chat", "", "%", "-chat", "chat.wit", "a b"] {
+            assert!(interface_file_name(name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+// Write a file only if its contents would change, so repeated runs don't touch
+// timestamps or trigger rebuilds. Returns whether the file was written.
+pub fn write_if_changed(path: &Path, content: impl AsRef<[u8]>) -> Result<bool> {
+    let content = content.as_ref();
+    if fs::read(path).is_ok_and(|existing| existing == content) {
+        log!("Unchanged: {}", path.display());
+        return Ok(false);
+    }
+
+    write_atomic(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(true)
+}
+
+// Write a file through a temporary sibling that is fsynced and renamed over the target,
+// so a crashed or concurrent run never leaves a half-written file behind
+fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = dir.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+
+    // Persist the rename itself; directories can't be opened for syncing on every platform
+    if let Ok(dir) = fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+// Contents and modification time of every file under a set of paths
+pub type Snapshot = BTreeMap<PathBuf, (Vec<u8>, Option<SystemTime>)>;
+
+// Record the state of the given files and directories (missing paths are skipped)
+pub fn snapshot(paths: &[PathBuf]) -> Result<Snapshot> {
+    let mut snapshot = Snapshot::new();
+    for root in paths {
+        if !root.exists() {
+            continue;
+        }
+        for entry in WalkDir::new(root).sort_by_file_name() {
+            let entry = entry?;
+            let path = entry.path();
+            if path.is_file() {
+                let content = fs::read(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+                snapshot.insert(path.to_path_buf(), (content, modified));
+            }
+        }
+    }
+    Ok(snapshot)
+}
+
+// List every path that was added, removed, rewritten or touched between two snapshots
+pub fn diff_snapshots(before: &Snapshot, after: &Snapshot) -> Vec<String> {
+    let mut changes = Vec::new();
+    for (path, (content, modified)) in before {
+        match after.get(path) {
+            None => changes.push(format!("removed: {}", path.display())),
+            Some((new_content, _)) if new_content != content => {
+                changes.push(format!("changed: {}", path.display()))
+            }
+            Some((_, new_modified)) if new_modified != modified => {
+                changes.push(format!("rewritten with identical contents: {}", path.display()))
+            }
+            Some(_) => {}
+        }
+    }
+    for path in after.keys() {
+        if !before.contains_key(path) {
+            changes.push(format!("added: {}", path.display()));
+        }
+    }
+    changes
+}
+
+// Check that a directory accepts new files by creating and removing a probe file,
+// which is what atomic writes need; permission bits alone miss read-only mounts
+fn probe_directory(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".hyper-bindgen-probe.{}", std::process::id()));
+    fs::File::create(&probe)?;
+    fs::remove_file(&probe)
+}
+
+// The closest existing directory a path would be created in
+fn existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().skip(1).find(|ancestor| ancestor.is_dir())
+}
+
+// Check that every path a run may touch can be written, before anything is modified.
+// Fails listing every path that can't be written, with the reason.
+pub fn check_writable(paths: &[PathBuf]) -> Result<()> {
+    let mut directories = BTreeMap::new();
+    let mut problems = Vec::new();
+
+    for root in paths {
+        if !root.exists() {
+            if let Some(dir) = existing_ancestor(root) {
+                directories.entry(dir.to_path_buf()).or_insert_with(|| root.clone());
+            }
+            continue;
+        }
+        for entry in WalkDir::new(root).sort_by_file_name().into_iter().filter_map(Result::ok) {
+            let path = entry.path();
+            if entry.file_type().is_dir() {
+                directories.entry(path.to_path_buf()).or_insert_with(|| path.to_path_buf());
+            } else {
+                // Read-only files can't be replaced on Windows, and are a sign of a protected checkout
+                if entry.metadata().is_ok_and(|m| m.permissions().readonly()) {
+                    problems.push(format!("{}: file is read-only", path.display()));
+                }
+                if let Some(dir) = path.parent() {
+                    directories.entry(dir.to_path_buf()).or_insert_with(|| path.to_path_buf());
+                }
+            }
+        }
+    }
+
+    for (dir, needed_for) in &directories {
+        if let Err(e) = probe_directory(dir) {
+            if needed_for == dir {
+                problems.push(format!("{}: {}", dir.display(), e));
+            } else {
+                problems.push(format!("{} (needed for {}): {}", dir.display(), needed_for.display(), e));
+            }
+        }
+    }
+
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "Cannot write every path this run may modify, nothing was changed:\n  {}",
+            problems.join("\n  ")
+        );
+    }
+    Ok(())
+}
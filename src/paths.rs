@@ -0,0 +1,43 @@
+use std::path::{Component, Path, PathBuf};
+
+// Render a path with forward slashes, as Cargo.toml and wit_bindgen::generate! expect on every platform
+pub fn to_slash(path: &Path) -> String {
+    path.components()
+        .map(|c| match c {
+            Component::RootDir => String::new(),
+            other => other.as_os_str().to_string_lossy().into_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Drop `.` components and fold `..` into the preceding component without touching the filesystem.
+// Canonicalizing would resolve symlinks and, on Windows, produce `\\?\` long-path prefixes that
+// never match the paths they are compared with.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+// Path from a directory to a target, for path dependencies and generate! paths.
+// Falls back to the target itself when no relative path exists (e.g. different Windows drives).
+pub fn relative_path(from_dir: &Path, to: &Path) -> String {
+    let from_dir = normalize(from_dir);
+    let to = normalize(to);
+    match pathdiff::diff_paths(&to, &from_dir) {
+        Some(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Some(relative) => to_slash(&relative),
+        None => to.to_string_lossy().into_owned(),
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::{Context, Result};
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// How confirmations are answered: asked on the terminal, or answered without asking
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Answers {
+    // Ask when standard input is a terminal, otherwise take each question's default
+    Interactive,
+    // Take each question's default without asking (`--non-interactive`)
+    Defaults,
+    // Answer yes to every question without asking (`--yes`)
+    Yes,
+}
+
+static ANSWERS: AtomicU8 = AtomicU8::new(Answers::Interactive as u8);
+
+pub fn set_answers(answers: Answers) {
+    ANSWERS.store(answers as u8, Ordering::Relaxed);
+}
+
+fn answers() -> Answers {
+    match ANSWERS.load(Ordering::Relaxed) {
+        value if value == Answers::Yes as u8 => Answers::Yes,
+        value if value == Answers::Defaults as u8 => Answers::Defaults,
+        _ => Answers::Interactive,
+    }
+}
+
+// Ask a yes/no question, answered with `default` on an empty line. Runs without a terminal, like
+// CI, never wait for input: they take the default, as with `--non-interactive`, and log the answer.
+pub fn confirm(question: &str, default: bool) -> Result<bool> {
+    let choices = if default { "[Y/n]" } else { "[y/N]" };
+    let answer_without_asking = match answers() {
+        Answers::Yes => Some((true, "--yes")),
+        Answers::Defaults => Some((default, "--non-interactive")),
+        Answers::Interactive if !std::io::stdin().is_terminal() => Some((default, "no terminal")),
+        Answers::Interactive => None,
+    };
+    if let Some((answer, reason)) = answer_without_asking {
+        log!("{} {} {} ({})", question, choices, if answer { "yes" } else { "no" }, reason);
+        return Ok(answer);
+    }
+
+    let stdin = std::io::stdin();
+    loop {
+        // Asked on stderr, so the question shows even when stdout is machine-readable output
+        eprint!("{} {} ", question, choices);
+        std::io::stderr().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).context("Failed to read the answer")? == 0 {
+            // Input closed before an answer
+            return Ok(default);
+        }
+        match line.trim().to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => eprintln!("Please answer y or n"),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Why an item was left out of (or degraded in) the generated output
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    // A name containing "stream", which WIT identifiers can't use
+    InvalidName,
+    // A type whose name contains `__`, assumed to be macro-generated
+    InternalType,
+    // An enum variant with more than one field, which has no WIT equivalent
+    ComplexVariant,
+    // A Rust type that couldn't be converted to WIT
+    ConversionError,
+    // A WIT construct the generator doesn't handle
+    UnsupportedType,
+    // A WIT file that couldn't be read or parsed
+    UnreadableFile,
+    // An interface without any signature records
+    NoSignatures,
+    // A function excluded with `@no-stub` or the `no-stub` configuration
+    NoStub,
+    // An HTTP function, whose stub is emitted commented out
+    HttpStub,
+    // A component export that isn't a named interface
+    NotAnInterface,
+    // A `func` declaration without an annotation naming the kinds of its stubs
+    UnmarkedFunction,
+    // A signature passing a resource handle, which only means something in the owning process
+    ResourceHandle,
+    // A signature passing a stream or future, which can't be serialized into a request
+    StreamOrFuture,
+}
+
+impl SkipReason {
+    // Stable code shown in logs and reports
+    pub fn code(self) -> &'static str {
+        match self {
+            SkipReason::InvalidName => "invalid-name",
+            SkipReason::InternalType => "internal-type",
+            SkipReason::ComplexVariant => "complex-variant",
+            SkipReason::ConversionError => "conversion-error",
+            SkipReason::UnsupportedType => "unsupported-type",
+            SkipReason::UnreadableFile => "unreadable-file",
+            SkipReason::NoSignatures => "no-signatures",
+            SkipReason::NoStub => "no-stub",
+            SkipReason::HttpStub => "http-stub",
+            SkipReason::NotAnInterface => "not-an-interface",
+            SkipReason::UnmarkedFunction => "unmarked-function",
+            SkipReason::ResourceHandle => "resource-handle",
+            SkipReason::StreamOrFuture => "stream-or-future",
+        }
+    }
+}
+
+// An item the generator skipped, with the reason
+#[derive(Serialize, Debug, Clone)]
+pub struct Skipped {
+    pub reason: SkipReason,
+    pub item: String,
+    pub detail: String,
+}
+
+impl std::fmt::Display for Skipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}: {}", self.reason.code(), self.item, self.detail)
+    }
+}
+
+static SKIPPED: Mutex<Vec<Skipped>> = Mutex::new(Vec::new());
+
+// Log a skipped item and collect it for the end-of-run report
+pub fn skip(reason: SkipReason, item: impl Into<String>, detail: impl std::fmt::Display) {
+    let skipped = Skipped {
+        reason,
+        item: item.into(),
+        detail: detail.to_string(),
+    };
+    log!("  Skipped {}", skipped);
+    SKIPPED.lock().unwrap_or_else(|e| e.into_inner()).push(skipped);
+}
+
+// Take every item skipped since the last call
+pub fn take_skipped() -> Vec<Skipped> {
+    std::mem::take(&mut *SKIPPED.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+// Print the skipped items as part of a run summary
+pub fn log_skipped(skipped: &[Skipped]) {
+    if skipped.is_empty() {
+        return;
+    }
+    log!("- Skipped {} items:", skipped.len());
+    for item in skipped {
+        log!("    {}", item);
+    }
+}
+
+// How serious a problem found in the api directory is
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    // Generation goes on, possibly ignoring the construct
+    Warning,
+    // Generation stops before writing anything
+    Error,
+}
+
+// A 1-based position in a file of the api directory
+#[derive(Serialize, Debug, Clone)]
+pub struct Position {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+// A problem with a construct of the api directory, at the position of the construct
+#[derive(Serialize, Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    #[serde(flatten)]
+    pub position: Position,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}: {}", self.position, severity, self.message)
+    }
+}
+
+static DIAGNOSTICS: Mutex<Vec<Diagnostic>> = Mutex::new(Vec::new());
+
+// File the diagnostics of the run are also written to as JSON, set with `--diagnostics-json`
+static DIAGNOSTICS_JSON: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+fn diagnose(severity: Severity, position: Position, message: impl std::fmt::Display) {
+    let diagnostic = Diagnostic {
+        severity,
+        position,
+        message: message.to_string(),
+    };
+    log!("{}", diagnostic);
+    DIAGNOSTICS.lock().unwrap_or_else(|e| e.into_inner()).push(diagnostic);
+}
+
+// Log a problem generation works around and collect it for the end-of-run report
+pub fn warning(position: Position, message: impl std::fmt::Display) {
+    diagnose(Severity::Warning, position, message);
+}
+
+// Log a problem that fails the run and collect it for the end-of-run report
+pub fn error(position: Position, message: impl std::fmt::Display) {
+    diagnose(Severity::Error, position, message);
+}
+
+// Also write the diagnostics of the run to `path` as a JSON array
+pub fn write_diagnostics_to(path: &Path) {
+    *DIAGNOSTICS_JSON.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.to_path_buf());
+}
+
+// Take every diagnostic reported since the last call
+pub fn take_diagnostics() -> Vec<Diagnostic> {
+    std::mem::take(&mut *DIAGNOSTICS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+// Print the diagnostics as part of a run summary, and write them to the JSON file if one was set
+fn log_diagnostics(diagnostics: &[Diagnostic]) -> Result<()> {
+    if !diagnostics.is_empty() {
+        log!("- {} problems in the api directory:", diagnostics.len());
+        for diagnostic in diagnostics {
+            log!("    {}", diagnostic);
+        }
+    }
+    if let Some(path) = DIAGNOSTICS_JSON.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        let json = serde_json::to_string_pretty(diagnostics)?;
+        std::fs::write(path, json + "\n").with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+// Fail with every diagnostic reported so far if any of them is an error, before anything is
+// generated from the constructs they point at
+pub fn check_diagnostics() -> Result<()> {
+    let diagnostics = DIAGNOSTICS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let errors = diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error).count();
+    if errors == 0 {
+        return Ok(());
+    }
+    log_diagnostics(&diagnostics)?;
+    bail!("{} errors in the api directory", errors);
+}
+
+// Print the diagnostics of a finished run and forget them
+pub fn finish_diagnostics() -> Result<()> {
+    log_diagnostics(&take_diagnostics())
+}
@@ -0,0 +1,62 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+// Advisory lock held for the whole of a run, next to the backups under the workspace root
+const RUN_LOCK_FILE: &str = ".hyper-bindgen/run.lock";
+
+// Exclusive right to modify the workspace's generated files, released when dropped or when the
+// process exits, so a crashed run never leaves a stale lock behind
+pub struct RunLock {
+    _file: File,
+}
+
+// Take the run lock of a workspace. When another run holds it, either wait for that run to
+// finish or fail with the process ID it recorded.
+pub fn acquire(base_dir: &Path, wait: bool) -> Result<RunLock> {
+    let path = base_dir.join(RUN_LOCK_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let mut holder = String::new();
+            let _ = file.read_to_string(&mut holder);
+            let holder = match holder.trim() {
+                "" => "another process".to_string(),
+                pid => format!("process {}", pid),
+            };
+            if !wait {
+                bail!(
+                    "Another hyper-bindgen run ({}) is generating in {}; \
+                     retry when it finishes, or pass --wait to wait for it",
+                    holder,
+                    base_dir.display()
+                );
+            }
+            log!("Waiting for another hyper-bindgen run ({}) to finish...", holder);
+            file.lock()
+                .with_context(|| format!("Failed to lock {}", path.display()))?;
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+        }
+    }
+
+    // Record who holds the lock for the message other runs print
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", std::process::id())?;
+    Ok(RunLock { _file: file })
+}
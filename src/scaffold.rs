@@ -0,0 +1,135 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use hyper_bindgen::case::to_pascal_case;
+
+use crate::caller_utils_generator;
+use crate::output;
+use crate::text;
+use crate::wit_source;
+
+// Worlds defined in the api directory, without their `types-` counterparts
+fn api_worlds(api_dir: &Path) -> Vec<String> {
+    let mut worlds = Vec::new();
+    for entry in WalkDir::new(api_dir)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if !(path.is_file() && path.extension().is_some_and(|ext| ext == "wit")) {
+            continue;
+        }
+        let Ok(content) = text::read_normalized(path) else {
+            continue;
+        };
+        for name in content.lines().filter_map(wit_source::world_header) {
+            if !name.starts_with("types-") && !worlds.iter().any(|w| w == name) {
+                worlds.push(name.to_string());
+            }
+        }
+    }
+    worlds
+}
+
+fn render_cargo_toml(name: &str) -> String {
+    format!(
+        r#"[package]
+name = "{}"
+version = "0.1.0"
+edition = "2021"
+publish = false
+
+[dependencies]
+anyhow = "1.0"
+hyperprocess_macro = {{ git = "https://github.com/hyperware-ai/hyperprocess-macro" }}
+hyperware_app_common = {{ git = "https://github.com/hyperware-ai/hyperprocess-macro" }}
+hyperware_process_lib = {{ version = "1.0.4", features = ["logging"] }}
+process_macros = "0.1.0"
+serde = {{ version = "1.0", features = ["derive"] }}
+serde_json = "1.0"
+wit-bindgen = "0.41.0"
+
+[lib]
+crate-type = ["cdylib"]
+
+[package.metadata.component]
+package = "hyperware:process"
+"#,
+        name
+    )
+}
+
+fn render_lib_rs(name: &str, world: &str) -> String {
+    let pascal_name = to_pascal_case(&name.replace('_', "-"));
+    format!(
+        r#"use hyperprocess_macro::hyperprocess;
+use serde::{{Deserialize, Serialize}};
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct {pascal}State {{}}
+
+#[hyperprocess(
+    name = "{pascal}",
+    wit_world = "{world}"
+)]
+impl {pascal}State {{
+    /// Example endpoint: other processes call it through `{snake}::ping_remote_rpc` in caller-utils
+    #[remote]
+    async fn ping(&self, message: String) -> String {{
+        message
+    }}
+}}
+"#,
+        pascal = pascal_name,
+        snake = name.replace('-', "_"),
+        world = world
+    )
+}
+
+// Create a hyperware:process crate with an example endpoint and add it to the workspace.
+// The process joins `world`, or the only world of the api directory when not given.
+// Returns the directory of the new crate.
+pub fn new_process(base_dir: &Path, name: &str, world: Option<&str>) -> Result<PathBuf> {
+    let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        bail!(
+            "Invalid process name `{}`: use lowercase letters, digits, `-` and `_`, starting with a letter",
+            name
+        );
+    }
+    if !base_dir.join("Cargo.toml").exists() {
+        bail!("No workspace Cargo.toml in {}, run new-process from the workspace root", base_dir.display());
+    }
+    let crate_dir = base_dir.join(name);
+    if crate_dir.exists() {
+        bail!("{} already exists", crate_dir.display());
+    }
+
+    let world = match world {
+        Some(world) => world.to_string(),
+        None => {
+            let worlds = api_worlds(&base_dir.join("api"));
+            match &worlds[..] {
+                [world] => world.clone(),
+                [] => bail!("No world found in the api directory, pass the world to join with --world"),
+                _ => bail!(
+                    "The api directory defines several worlds ({}), pass the one to join with --world",
+                    worlds.join(", ")
+                ),
+            }
+        }
+    };
+
+    log!("Creating process crate {} in world {}", name, world);
+    fs::create_dir_all(crate_dir.join("src"))
+        .with_context(|| format!("Failed to create {}", crate_dir.display()))?;
+    output::write_if_changed(&crate_dir.join("Cargo.toml"), render_cargo_toml(name))?;
+    output::write_if_changed(&crate_dir.join("src").join("lib.rs"), render_lib_rs(name, &world))?;
+    caller_utils_generator::update_workspace_cargo_toml(base_dir, name)?;
+    Ok(crate_dir)
+}
@@ -0,0 +1,98 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const BOM: char = '\u{feff}';
+
+// Normalize text authored on other platforms before it is matched line by line:
+// strips a UTF-8 byte order mark and converts CRLF and lone CR line endings to LF
+pub fn normalize(content: &str) -> String {
+    let content = content.strip_prefix(BOM).unwrap_or(content);
+    if !content.contains('\r') {
+        return content.to_string();
+    }
+    content.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+// Read a text file (such as a WIT file) with normalized line endings and no byte order mark
+pub fn read_normalized(path: &Path) -> io::Result<String> {
+    let content = fs::read_to_string(path)?;
+    if content.starts_with(BOM) || content.contains('\r') {
+        log!("Normalizing line endings and byte order mark in {}", path.display());
+    }
+    Ok(normalize(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wit_source;
+
+    const CHAT_WIT: &str = "interface chat {
+    use standard.{address};
+
+    /// A message of a channel
+    record chat-message {
+        author: string,
+        body: string,
+    }
+
+    record send-message-signature-remote {
+        target: address,
+        message: chat-message,
+        returning: bool
+    }
+
+    ping: func(target: address) -> string;
+}
+";
+
+    // Write the WIT with the given line ending and prefix, read it back and describe what parses
+    fn parsed(name: &str, prefix: &str, line_ending: &str) -> Vec<String> {
+        let path = std::env::temp_dir().join(format!("hyper-bindgen-{}-{}.wit", name, std::process::id()));
+        fs::write(&path, format!("{}{}", prefix, CHAT_WIT.replace('\n', line_ending))).unwrap();
+        let content = read_normalized(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(content, CHAT_WIT);
+
+        let mut described = Vec::new();
+        for interface in wit_source::parse("chat.wit", &content).unwrap() {
+            for definition in &interface.definitions {
+                described.push(format!(
+                    "{}.{} {} at {}:{} docs {:?} fields {:?}",
+                    interface.name,
+                    definition.name,
+                    definition.keyword,
+                    definition.line,
+                    definition.column,
+                    definition.docs,
+                    definition.fields
+                ));
+            }
+            for function in &interface.functions {
+                described.push(format!(
+                    "{}.{} at {}:{} params {:?} -> {:?}",
+                    interface.name, function.name, function.line, function.column, function.params, function.result
+                ));
+            }
+        }
+        described
+    }
+
+    #[test]
+    fn crlf_and_bom_parse_like_lf() {
+        let lf = parsed("lf", "", "\n");
+        assert_eq!(lf.len(), 3);
+        assert!(lf[1].starts_with("chat.send-message-signature-remote record at 10:12"));
+        assert_eq!(parsed("crlf", "", "\r\n"), lf);
+        assert_eq!(parsed("bom", "\u{feff}", "\n"), lf);
+        assert_eq!(parsed("bom-crlf", "\u{feff}", "\r\n"), lf);
+        assert_eq!(parsed("cr", "", "\r"), lf);
+    }
+
+    #[test]
+    fn normalize_converts_line_endings() {
+        assert_eq!(normalize("\u{feff}a\r\nb\rc\n"), "a\nb\nc\n");
+        assert_eq!(normalize("a\nb"), "a\nb");
+    }
+}
@@ -0,0 +1,236 @@
+use crate::wit_source::strip_comment;
+
+// Kinds of type definitions whose bodies are lists of members, one per line
+const DEFINITION_KINDS: [&str; 4] = ["record ", "variant ", "enum ", "flags "];
+
+// A WIT file split into lines and the blocks they open
+enum Node {
+    Blank,
+    Line(String),
+    // A type definition: header, member lines and closing line
+    Definition(String, Vec<String>, String),
+    // Any other braced block, like an interface or world: header, body and closing line
+    Block(String, Vec<Node>, String),
+}
+
+impl Node {
+    // Key type definitions are sorted by within a run of them: their header, e.g. `record user {`.
+    // Signature records describe functions and keep their order.
+    fn sort_key(&self) -> Option<&str> {
+        match self {
+            Node::Definition(header, _, _) if !header.contains("-signature-") => Some(header),
+            Node::Line(line) if line.starts_with("type ") => Some(line),
+            _ => None,
+        }
+    }
+}
+
+// Add a line of a definition body to its members. A member whose code doesn't end with a comma
+// yet continues on the line, e.g. a field with its type on the next line; the comments of its
+// lines are joined after the code.
+fn push_member(members: &mut Vec<String>, line: &str) {
+    let open = members.last_mut().filter(|member| {
+        let code = strip_comment(member);
+        !code.is_empty() && !code.ends_with(',')
+    });
+    let Some(member) = open else {
+        members.push(line.to_string());
+        return;
+    };
+    let comments: Vec<&str> = [member.as_str(), line]
+        .into_iter()
+        .filter_map(|line| line.split_once("//"))
+        .map(|(_, comment)| comment.trim())
+        .filter(|comment| !comment.is_empty())
+        .collect();
+    let mut joined = format!("{} {}", strip_comment(member), strip_comment(line)).trim_end().to_string();
+    if !comments.is_empty() {
+        joined = format!("{} // {}", joined, comments.join(" "));
+    }
+    *member = joined;
+}
+
+// Parse lines into nodes until the line closing the enclosing block, which is returned with them
+fn parse(lines: &mut std::slice::Iter<'_, &str>) -> (Vec<Node>, Option<String>) {
+    let mut nodes = Vec::new();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        let code = strip_comment(line);
+        if line.is_empty() {
+            nodes.push(Node::Blank);
+        } else if code.starts_with('}') {
+            return (nodes, Some(line.to_string()));
+        } else if !code.ends_with('{') {
+            nodes.push(Node::Line(line.to_string()));
+        } else if DEFINITION_KINDS.iter().any(|kind| line.starts_with(kind)) {
+            let mut members = Vec::new();
+            let mut close = "}".to_string();
+            for member in lines.by_ref() {
+                let member = member.trim();
+                if strip_comment(member).starts_with('}') {
+                    close = member.to_string();
+                    break;
+                }
+                if !member.is_empty() {
+                    push_member(&mut members, member);
+                }
+            }
+            nodes.push(Node::Definition(line.to_string(), members, close));
+        } else {
+            let (body, close) = parse(lines);
+            nodes.push(Node::Block(line.to_string(), sort_definitions(body), close.unwrap_or_else(|| "}".to_string())));
+        }
+    }
+    (nodes, None)
+}
+
+// Sort each run of type definitions, i.e. definitions separated only by blank lines, by their
+// header. Comments directly above a definition move with it; a comment followed by a blank
+// line ends the run.
+fn sort_definitions(nodes: Vec<Node>) -> Vec<Node> {
+    fn flush(run: &mut Vec<Vec<Node>>, result: &mut Vec<Node>) {
+        if run.is_empty() {
+            return;
+        }
+        run.sort_by(|a, b| a.last().and_then(Node::sort_key).cmp(&b.last().and_then(Node::sort_key)));
+        for item in run.drain(..) {
+            result.extend(item);
+            result.push(Node::Blank);
+        }
+    }
+
+    let mut result = Vec::new();
+    let mut run = Vec::new();
+    let mut comments = Vec::new();
+    for node in nodes {
+        match node {
+            Node::Line(ref line) if line.starts_with("//") => comments.push(node),
+            Node::Blank => {
+                if !comments.is_empty() {
+                    flush(&mut run, &mut result);
+                    result.append(&mut comments);
+                    result.push(Node::Blank);
+                } else if run.is_empty() {
+                    result.push(Node::Blank);
+                }
+            }
+            node if node.sort_key().is_some() => {
+                let mut item = std::mem::take(&mut comments);
+                item.push(node);
+                run.push(item);
+            }
+            node => {
+                flush(&mut run, &mut result);
+                result.append(&mut comments);
+                result.push(node);
+            }
+        }
+    }
+    flush(&mut run, &mut result);
+    result.append(&mut comments);
+    result
+}
+
+// Members of a definition with a trailing comma each, and the types of record fields aligned
+fn format_members(header: &str, members: &[String]) -> Vec<String> {
+    let is_record = header.starts_with("record ");
+    // (member without its comma, trailing comment), or a comment line as the member
+    let split: Vec<(String, Option<&str>)> = members
+        .iter()
+        .map(|member| {
+            if member.starts_with("//") {
+                return (member.clone(), None);
+            }
+            let (code, comment) = match member.split_once("//") {
+                Some((code, comment)) => (code, Some(comment.trim())),
+                None => (member.as_str(), None),
+            };
+            (code.trim().trim_end_matches(',').trim_end().to_string(), comment)
+        })
+        .collect();
+
+    let field_name = |code: &str| code.split_once(':').map(|(name, _)| name.trim().len());
+    let width = if is_record {
+        split
+            .iter()
+            .filter(|(code, _)| !code.starts_with("//"))
+            .filter_map(|(code, _)| field_name(code))
+            .max()
+            .unwrap_or(0)
+    } else {
+        0
+    };
+
+    split
+        .into_iter()
+        .map(|(code, comment)| {
+            if code.starts_with("//") {
+                return code;
+            }
+            let code = match code.split_once(':') {
+                Some((name, wit_type)) if is_record => {
+                    let name = name.trim();
+                    format!("{}:{} {}", name, " ".repeat(width - name.len()), wit_type.trim())
+                }
+                _ => code,
+            };
+            match comment {
+                Some(comment) => format!("{}, // {}", code, comment),
+                None => format!("{},", code),
+            }
+        })
+        .collect()
+}
+
+fn render(nodes: &[Node], depth: usize, out: &mut Vec<String>) {
+    let indent = "    ".repeat(depth);
+    let start = out.len();
+    for node in nodes {
+        match node {
+            // No blank line at the start of a block or after another blank line
+            Node::Blank => {
+                if out.len() > start && out.last().is_some_and(|line| !line.is_empty()) {
+                    out.push(String::new());
+                }
+            }
+            Node::Line(line) => out.push(format!("{}{}", indent, line)),
+            Node::Definition(header, members, close) => {
+                out.push(format!("{}{}", indent, header));
+                for member in format_members(header, members) {
+                    out.push(format!("{}    {}", indent, member));
+                }
+                out.push(format!("{}{}", indent, close));
+            }
+            Node::Block(header, body, close) => {
+                out.push(format!("{}{}", indent, header));
+                render(body, depth + 1, out);
+                out.push(format!("{}{}", indent, close));
+            }
+        }
+    }
+    // Nor at the end of one
+    while out.len() > start && out.last().is_some_and(|line| line.is_empty()) {
+        out.pop();
+    }
+}
+
+// Format the contents of a WIT file: four-space indentation per block, at most one blank line
+// between items, a trailing comma after every member, aligned record field types and each run
+// of type definitions sorted by kind and name
+pub fn format(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut lines = lines.iter();
+    let mut nodes = Vec::new();
+    // A stray closing brace at the top level is kept as a line
+    loop {
+        let (parsed, close) = parse(&mut lines);
+        nodes.extend(parsed);
+        match close {
+            Some(close) => nodes.push(Node::Line(close)),
+            None => break,
+        }
+    }
+    let mut out = Vec::new();
+    render(&sort_definitions(nodes), 0, &mut out);
+    out.join("\n") + "\n"
+}
@@ -6,44 +6,23 @@ use syn::{self, Attribute, ImplItem, Item, Type};
 use walkdir::WalkDir;
 use toml::Value;
 
-// Helper functions for naming conventions
-fn to_kebab_case(s: &str) -> String {
-    // First, handle the case where the input has underscores
-    if s.contains('_') {
-        return s.replace('_', "-");
-    }
-    
-    let mut result = String::with_capacity(s.len() + 5); // Extra capacity for hyphens
-    let chars: Vec<char> = s.chars().collect();
-    
-    for (i, &c) in chars.iter().enumerate() {
-        if c.is_uppercase() {
-            // Add hyphen if:
-            // 1. Not the first character
-            // 2. Previous character is lowercase
-            // 3. Or next character is lowercase (to handle acronyms like HTML)
-            if i > 0 && 
-               (chars[i-1].is_lowercase() ||
-                (i < chars.len() - 1 && chars[i+1].is_lowercase()))
-            {
-                result.push('-');
-            }
-            result.push(c.to_lowercase().next().unwrap());
-        } else {
-            result.push(c);
-        }
-    }
-    
-    result
+use hyper_bindgen::case::to_kebab_case;
+
+use crate::manifest;
+use crate::output;
+use crate::report::{self, SkipReason};
+use crate::text;
+use crate::wit_format;
+use crate::wit_source;
+
+// Convert a Rust name to a WIT identifier: raw identifiers like `r#type` lose their prefix, and
+// names that are WIT keywords are escaped, e.g. `%type` for a field or `%flags` for a struct `Flags`
+pub fn to_wit_identifier(s: &str) -> String {
+    wit_source::escape(&to_kebab_case(s.trim_start_matches("r#")))
 }
 
-// Validates a name doesn't contain numbers or "stream"
+// Validates a name doesn't contain "stream"
 fn validate_name(name: &str, kind: &str) -> Result<()> {
-    // Check for numbers
-    if name.chars().any(|c| c.is_digit(10)) {
-        anyhow::bail!("Error: {} name '{}' contains numbers, which is not allowed", kind, name);
-    }
-    
     // Check for "stream"
     if name.to_lowercase().contains("stream") {
         anyhow::bail!("Error: {} name '{}' contains 'stream', which is not allowed", kind, name);
@@ -67,11 +46,11 @@ fn extract_wit_world(attrs: &[Attribute]) -> Result<String> {
         if attr.path().is_ident("hyperprocess") {
             // Convert attribute to string representation
             let attr_str = format!("{:?}", attr);
-            println!("Attribute string: {}", attr_str);
+            log!("Attribute string: {}", attr_str);
             
             // Look for wit_world in the attribute string
             if let Some(pos) = attr_str.find("wit_world") {
-                println!("Found wit_world at position {}", pos);
+                log!("Found wit_world at position {}", pos);
                 
                 // Find the literal value after wit_world by looking for lit: "value"
                 let lit_pattern = "lit: \"";
@@ -81,7 +60,7 @@ fn extract_wit_world(attrs: &[Attribute]) -> Result<String> {
                     // Find the closing quote of the literal
                     if let Some(quote_pos) = attr_str[start_pos..].find('\"') {
                         let world_name = &attr_str[start_pos..(start_pos + quote_pos)];
-                        println!("Extracted wit_world: {}", world_name);
+                        log!("Extracted wit_world: {}", world_name);
                         return Ok(world_name.to_string());
                     }
                 }
@@ -165,7 +144,7 @@ fn rust_type_to_wit(ty: &Type, used_types: &mut HashSet<String>) -> Result<Strin
                     validate_name(custom, "Type")?;
                     
                     // Convert custom type to kebab-case and add to used types
-                    let kebab_custom = to_kebab_case(custom);
+                    let kebab_custom = to_wit_identifier(custom);
                     used_types.insert(kebab_custom.clone());
                     Ok(kebab_custom)
                 }
@@ -197,31 +176,32 @@ fn find_rust_files(crate_path: &Path) -> Vec<PathBuf> {
     let mut rust_files = Vec::new();
     let src_dir = crate_path.join("src");
     
-    println!("Finding Rust files in {}", src_dir.display());
+    log!("Finding Rust files in {}", src_dir.display());
     
     if !src_dir.exists() || !src_dir.is_dir() {
-        println!("No src directory found at {}", src_dir.display());
+        log!("No src directory found at {}", src_dir.display());
         return rust_files;
     }
     
     for entry in WalkDir::new(src_dir)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(Result::ok)
     {
         let path = entry.path();
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "rs") {
-            println!("Found Rust file: {}", path.display());
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "rs") {
+            log!("Found Rust file: {}", path.display());
             rust_files.push(path.to_path_buf());
         }
     }
     
-    println!("Found {} Rust files", rust_files.len());
+    log!("Found {} Rust files", rust_files.len());
     rust_files
 }
 
-// Collect type definitions (structs and enums) from a file
+// Collect type definitions (structs, enums and type aliases) from a file
 fn collect_type_definitions_from_file(file_path: &Path) -> Result<HashMap<String, String>> {
-    println!("Collecting type definitions from file: {}", file_path.display());
+    log!("Collecting type definitions from file: {}", file_path.display());
     
     let content = fs::read_to_string(file_path)
         .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
@@ -234,20 +214,20 @@ fn collect_type_definitions_from_file(file_path: &Path) -> Result<HashMap<String
     for item in &ast.items {
         match item {
             Item::Struct(item_struct) => {
-                // Validate struct name doesn't contain numbers or "stream"
+                // Validate struct name doesn't contain "stream"
                 let orig_name = item_struct.ident.to_string();
                 
                 // Skip trying to validate if name contains "__" as these are likely internal types
                 if orig_name.contains("__") {
-                    println!("  Skipping likely internal struct: {}", orig_name);
+                    report::skip(SkipReason::InternalType, format!("struct {}", orig_name), "name contains `__`");
                     continue;
                 }
                 
                 match validate_name(&orig_name, "Struct") {
                     Ok(_) => {
                         // Use kebab-case for struct name
-                        let name = to_kebab_case(&orig_name);
-                        println!("  Found struct: {} -> {}", orig_name, name);
+                        let name = to_wit_identifier(&orig_name);
+                        log!("  Found struct: {} -> {}", orig_name, name);
                         
                         let fields: Vec<String> = match &item_struct.fields {
                             syn::Fields::Named(fields) => {
@@ -256,33 +236,33 @@ fn collect_type_definitions_from_file(file_path: &Path) -> Result<HashMap<String
                                 
                                 for f in &fields.named {
                                     if let Some(field_ident) = &f.ident {
-                                        // Validate field name doesn't contain digits
+                                        // Validate field name doesn't contain "stream"
                                         let field_orig_name = field_ident.to_string();
                                         
                                         match validate_name(&field_orig_name, "Field") {
                                             Ok(_) => {
                                                 // Convert field names to kebab-case
-                                                let field_name = to_kebab_case(&field_orig_name);
+                                                let field_name = to_wit_identifier(&field_orig_name);
                                                 
                                                 // Skip if field conversion failed
                                                 if field_name.is_empty() {
-                                                    println!("    Skipping field with empty name conversion");
+                                                    report::skip(SkipReason::InvalidName, format!("field {}.{}", orig_name, field_orig_name), "name converts to an empty WIT identifier");
                                                     continue;
                                                 }
                                                 
                                                 let field_type = match rust_type_to_wit(&f.ty, &mut used_types) {
                                                     Ok(ty) => ty,
                                                     Err(e) => {
-                                                        println!("    Error converting field type: {}", e);
+                                                        report::skip(SkipReason::ConversionError, format!("field {}.{}", orig_name, field_orig_name), format!("{}; typed as `unknown`", e));
                                                         "unknown".to_string()
                                                     }
                                                 };
                                                 
-                                                println!("    Field: {} -> {}", field_name, field_type);
+                                                log!("    Field: {} -> {}", field_name, field_type);
                                                 field_strings.push(format!("        {}: {}", field_name, field_type));
                                             },
                                             Err(e) => {
-                                                println!("    Skipping field with invalid name: {}", e);
+                                                report::skip(SkipReason::InvalidName, format!("field {}.{}", orig_name, field_orig_name), e);
                                                 continue;
                                             }
                                         }
@@ -302,26 +282,26 @@ fn collect_type_definitions_from_file(file_path: &Path) -> Result<HashMap<String
                         }
                     },
                     Err(e) => {
-                        println!("  Skipping struct with invalid name: {}", e);
+                        report::skip(SkipReason::InvalidName, format!("struct {}", orig_name), e);
                         continue;
                     }
                 }
             }
             Item::Enum(item_enum) => {
-                // Validate enum name doesn't contain numbers or "stream"
+                // Validate enum name doesn't contain "stream"
                 let orig_name = item_enum.ident.to_string();
                 
                 // Skip trying to validate if name contains "__" as these are likely internal types
                 if orig_name.contains("__") {
-                    println!("  Skipping likely internal enum: {}", orig_name);
+                    report::skip(SkipReason::InternalType, format!("enum {}", orig_name), "name contains `__`");
                     continue;
                 }
                 
                 match validate_name(&orig_name, "Enum") {
                     Ok(_) => {
                         // Use kebab-case for enum name
-                        let name = to_kebab_case(&orig_name);
-                        println!("  Found enum: {} -> {}", orig_name, name);
+                        let name = to_wit_identifier(&orig_name);
+                        log!("  Found enum: {} -> {}", orig_name, name);
                         
                         let mut variants = Vec::new();
                         let mut skip_enum = false;
@@ -342,12 +322,12 @@ fn collect_type_definitions_from_file(file_path: &Path) -> Result<HashMap<String
                                             ) {
                                                 Ok(ty) => {
                                                     // Use kebab-case for variant names and use parentheses for type
-                                                    let variant_name = to_kebab_case(&variant_orig_name);
-                                                    println!("    Variant: {} -> {}({})", variant_orig_name, variant_name, ty);
+                                                    let variant_name = to_wit_identifier(&variant_orig_name);
+                                                    log!("    Variant: {} -> {}({})", variant_orig_name, variant_name, ty);
                                                     variants.push(format!("        {}({})", variant_name, ty));
                                                 },
                                                 Err(e) => {
-                                                    println!("    Error converting variant type: {}", e);
+                                                    report::skip(SkipReason::ConversionError, format!("enum {}", orig_name), format!("variant {}: {}", variant_orig_name, e));
                                                     skip_enum = true;
                                                     break;
                                                 }
@@ -355,12 +335,12 @@ fn collect_type_definitions_from_file(file_path: &Path) -> Result<HashMap<String
                                         }
                                         syn::Fields::Unit => {
                                             // Use kebab-case for variant names
-                                            let variant_name = to_kebab_case(&variant_orig_name);
-                                            println!("    Variant: {} -> {}", variant_orig_name, variant_name);
+                                            let variant_name = to_wit_identifier(&variant_orig_name);
+                                            log!("    Variant: {} -> {}", variant_orig_name, variant_name);
                                             variants.push(format!("        {}", variant_name));
                                         },
                                         _ => {
-                                            println!("    Skipping complex variant: {}", variant_orig_name);
+                                            report::skip(SkipReason::ComplexVariant, format!("enum {}", orig_name), format!("variant {} has more than one field", variant_orig_name));
                                             // Complex variants with multiple fields aren't directly supported in WIT
                                             // For simplicity, we'll skip enums with complex variants
                                             skip_enum = true;
@@ -369,7 +349,7 @@ fn collect_type_definitions_from_file(file_path: &Path) -> Result<HashMap<String
                                     }
                                 },
                                 Err(e) => {
-                                    println!("    Skipping variant with invalid name: {}", e);
+                                    report::skip(SkipReason::InvalidName, format!("enum {}", orig_name), format!("variant {}", e));
                                     skip_enum = true;
                                     break;
                                 }
@@ -384,67 +364,166 @@ fn collect_type_definitions_from_file(file_path: &Path) -> Result<HashMap<String
                         }
                     },
                     Err(e) => {
-                        println!("  Skipping enum with invalid name: {}", e);
+                        report::skip(SkipReason::InvalidName, format!("enum {}", orig_name), e);
                         continue;
                     }
                 }
             }
+            Item::Type(item_type) => {
+                let orig_name = item_type.ident.to_string();
+
+                if orig_name.contains("__") {
+                    report::skip(SkipReason::InternalType, format!("type {}", orig_name), "name contains `__`");
+                    continue;
+                }
+                // WIT aliases can't take type parameters
+                if !item_type.generics.params.is_empty() {
+                    report::skip(SkipReason::UnsupportedType, format!("type {}", orig_name), "generic type aliases have no WIT equivalent");
+                    continue;
+                }
+                if let Err(e) = validate_name(&orig_name, "Type") {
+                    report::skip(SkipReason::InvalidName, format!("type {}", orig_name), e);
+                    continue;
+                }
+
+                let name = to_wit_identifier(&orig_name);
+                match rust_type_to_wit(&item_type.ty, &mut HashSet::new()) {
+                    Ok(ty) => {
+                        log!("  Found type alias: {} -> {} = {}", orig_name, name, ty);
+                        type_defs.insert(name.clone(), format!("    type {} = {};", name, ty));
+                    }
+                    Err(e) => report::skip(SkipReason::ConversionError, format!("type {}", orig_name), e),
+                }
+            }
             _ => {}
         }
     }
     
-    println!("Collected {} type definitions from file", type_defs.len());
+    log!("Collected {} type definitions from file", type_defs.len());
     Ok(type_defs)
 }
 
+// Check whether a directory holds a crate with hyperware:process component metadata
+fn is_process_crate(path: &Path) -> bool {
+    let cargo_toml = path.join("Cargo.toml");
+    log!("Checking {}", cargo_toml.display());
+    
+    if !cargo_toml.exists() {
+        return false;
+    }
+    // Try to read and parse Cargo.toml
+    let Ok(content) = fs::read_to_string(&cargo_toml) else {
+        return false;
+    };
+    let Ok(cargo_data) = content.parse::<Value>() else {
+        return false;
+    };
+    
+    // Check for the specific metadata
+    let Some(metadata) = cargo_data
+        .get("package")
+        .and_then(|p| p.get("metadata"))
+        .and_then(|m| m.get("component"))
+    else {
+        log!("  No package.metadata.component metadata found");
+        return false;
+    };
+    
+    let Some(package_str) = metadata.get("package").and_then(|p| p.as_str()) else {
+        return false;
+    };
+    log!("  Found package.metadata.component.package = {:?}", package_str);
+    if package_str != "hyperware:process" {
+        return false;
+    }
+    log!("  Adding project: {}", path.display());
+    true
+}
+
+// Read the package name from a crate's Cargo.toml
+pub fn package_name(project: &Path) -> Result<String> {
+    let cargo_toml = project.join("Cargo.toml");
+    let content = fs::read_to_string(&cargo_toml)
+        .with_context(|| format!("Failed to read {}", cargo_toml.display()))?;
+    let cargo_data: Value = content.parse()
+        .with_context(|| format!("Failed to parse {}", cargo_toml.display()))?;
+    cargo_data
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .map(str::to_string)
+        .with_context(|| format!("No package name in {}", cargo_toml.display()))
+}
+
+// Literal (non-glob) workspace members of the workspace Cargo.toml
+fn workspace_members(base_dir: &Path) -> Vec<String> {
+    let Ok(content) = fs::read_to_string(base_dir.join("Cargo.toml")) else {
+        return Vec::new();
+    };
+    let Ok(cargo_data) = content.parse::<Value>() else {
+        return Vec::new();
+    };
+    cargo_data
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+        .map(|members| {
+            members
+                .iter()
+                .filter_map(|m| m.as_str())
+                .filter(|m| !m.contains('*'))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 // Find all relevant Rust projects
-fn find_rust_projects(base_dir: &Path) -> Vec<PathBuf> {
+pub fn find_rust_projects(base_dir: &Path) -> Vec<PathBuf> {
     let mut projects = Vec::new();
-    println!("Scanning for Rust projects in {}", base_dir.display());
+    log!("Scanning for Rust projects in {}", base_dir.display());
     
     for entry in WalkDir::new(base_dir)
         .max_depth(1)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(Result::ok)
     {
         let path = entry.path();
         
-        if path.is_dir() && path != base_dir {
-            let cargo_toml = path.join("Cargo.toml");
-            println!("Checking {}", cargo_toml.display());
-            
-            if cargo_toml.exists() {
-                // Try to read and parse Cargo.toml
-                if let Ok(content) = fs::read_to_string(&cargo_toml) {
-                    if let Ok(cargo_data) = content.parse::<Value>() {
-                        // Check for the specific metadata
-                        if let Some(metadata) = cargo_data
-                            .get("package")
-                            .and_then(|p| p.get("metadata"))
-                            .and_then(|m| m.get("component"))
-                        {
-                            if let Some(package) = metadata.get("package") {
-                                if let Some(package_str) = package.as_str() {
-                                    println!("  Found package.metadata.component.package = {:?}", package_str);
-                                    if package_str == "hyperware:process" {
-                                        println!("  Adding project: {}", path.display());
-                                        projects.push(path.to_path_buf());
-                                    }
-                                }
-                            }
-                        } else {
-                            println!("  No package.metadata.component metadata found");
-                        }
-                    }
-                }
-            }
+        if path.is_dir() && path != base_dir && is_process_crate(path) {
+            projects.push(path.to_path_buf());
+        }
+    }
+    
+    // Process crates in nested directories are only found through the workspace members
+    for member in workspace_members(base_dir) {
+        let path = base_dir.join(&member);
+        if path.parent() != Some(base_dir) && path.is_dir() && is_process_crate(&path) {
+            projects.push(path);
         }
     }
     
-    println!("Found {} relevant Rust projects", projects.len());
+    log!("Found {} relevant Rust projects", projects.len());
     projects
 }
 
+// Collect `@name value` annotation lines from a method's doc comments, without the `@`
+fn doc_annotations(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(doc), .. }),
+                ..
+            }) => Some(doc.value()),
+            _ => None,
+        })
+        .filter_map(|doc| doc.trim().strip_prefix('@').map(|annotation| annotation.trim().to_string()))
+        .collect()
+}
+
 // Helper function to generate signature struct for specific attribute type
 fn generate_signature_struct(
     kebab_name: &str,
@@ -455,8 +534,11 @@ fn generate_signature_struct(
     // Create signature struct name with attribute type
     let signature_struct_name = format!("{}-signature-{}", kebab_name, attr_type);
     
-    // Generate comment for this specific function
-    let comment = format!("    // Function signature for: {} ({})", kebab_name, attr_type);
+    // Generate comment for this specific function, followed by any `@` annotations from its docs
+    let mut comment = format!("    // Function signature for: {} ({})", kebab_name, attr_type);
+    for annotation in doc_annotations(&method.attrs) {
+        comment.push_str(&format!("\n    /// @{}", annotation));
+    }
     
     // Create struct fields that directly represent function parameters
     let mut struct_fields = Vec::new();
@@ -483,7 +565,7 @@ fn generate_signature_struct(
                 // Validate parameter name
                 match validate_name(&param_orig_name, "Parameter") {
                     Ok(_) => {
-                        let param_name = to_kebab_case(&param_orig_name);
+                        let param_name = to_wit_identifier(&param_orig_name);
                         
                         // Rust type to WIT type
                         match rust_type_to_wit(&pat_type.ty, used_types) {
@@ -492,14 +574,14 @@ fn generate_signature_struct(
                                 struct_fields.push(format!("        {}: {}", param_name, param_type));
                             },
                             Err(e) => {
-                                println!("    Error converting parameter type: {}", e);
+                                report::skip(SkipReason::ConversionError, format!("parameter {}.{}", kebab_name, param_orig_name), format!("{}; typed as `unknown`", e));
                                 // Use a placeholder type for this parameter
                                 struct_fields.push(format!("        {}: unknown", param_name));
                             }
                         }
                     },
                     Err(e) => {
-                        println!("    Skipping parameter with invalid name: {}", e);
+                        report::skip(SkipReason::InvalidName, format!("parameter {}.{}", kebab_name, param_orig_name), format!("{}; replaced with `invalid-param`", e));
                         // Use a placeholder for invalid parameter names
                         struct_fields.push("        invalid-param: unknown".to_string());
                     }
@@ -511,12 +593,12 @@ fn generate_signature_struct(
     // Add return type field
     match &method.sig.output {
         syn::ReturnType::Type(_, ty) => {
-            match rust_type_to_wit(&*ty, used_types) {
+            match rust_type_to_wit(ty, used_types) {
                 Ok(return_type) => {
                     struct_fields.push(format!("        returning: {}", return_type));
                 },
                 Err(e) => {
-                    println!("    Error converting return type: {}", e);
+                    report::skip(SkipReason::ConversionError, format!("return type of {}", kebab_name), format!("{}; typed as `unknown`", e));
                     struct_fields.push("        returning: unknown".to_string());
                 }
             }
@@ -554,13 +636,13 @@ impl AsTypePath for syn::Type {
 
 // Process a single Rust project and generate WIT files
 fn process_rust_project(project_path: &Path, api_dir: &Path) -> Result<Option<String>> {
-    println!("\nProcessing project: {}", project_path.display());
+    log!("\nProcessing project: {}", project_path.display());
     
     // Find lib.rs for this project
     let lib_rs = project_path.join("src").join("lib.rs");
     
     if !lib_rs.exists() {
-        println!("No lib.rs found for project: {}", project_path.display());
+        log!("No lib.rs found for project: {}", project_path.display());
         return Ok(None);
     }
     
@@ -577,13 +659,13 @@ fn process_rust_project(project_path: &Path, api_dir: &Path) -> Result<Option<St
                 }
             },
             Err(e) => {
-                println!("Error collecting type definitions from {}: {}", file_path.display(), e);
+                log!("Error collecting type definitions from {}: {}", file_path.display(), e);
                 // Continue with other files
             }
         }
     }
     
-    println!("Collected {} total type definitions", all_type_defs.len());
+    log!("Collected {} total type definitions", all_type_defs.len());
     
     // Parse lib.rs to find the hyperprocess attribute and interface details
     let lib_content = fs::read_to_string(&lib_rs)
@@ -597,17 +679,17 @@ fn process_rust_project(project_path: &Path, api_dir: &Path) -> Result<Option<St
     let mut kebab_interface_name = None;
     let mut impl_item_with_hyperprocess = None;
     
-    println!("Scanning for impl blocks with hyperprocess attribute");
+    log!("Scanning for impl blocks with hyperprocess attribute");
     for item in &ast.items {
         if let Item::Impl(impl_item) = item {
             // Check if this impl block has a #[hyperprocess] attribute
             if let Some(attr) = impl_item.attrs.iter().find(|attr| attr.path().is_ident("hyperprocess")) {
-                println!("Found hyperprocess attribute");
+                log!("Found hyperprocess attribute");
                 
                 // Extract the wit_world name
-                match extract_wit_world(&[attr.clone()]) {
+                match extract_wit_world(std::slice::from_ref(attr)) {
                     Ok(world_name) => {
-                        println!("Extracted wit_world: {}", world_name);
+                        log!("Extracted wit_world: {}", world_name);
                         wit_world = Some(world_name);
                         
                         // Get the interface name from the impl type
@@ -627,7 +709,7 @@ fn process_rust_project(project_path: &Path, api_dir: &Path) -> Result<Option<St
                         if let Some(ref name) = interface_name {
                             // Validate the interface name
                             if let Err(e) = validate_name(name, "Interface") {
-                                println!("Interface name validation failed: {}", e);
+                                log!("Interface name validation failed: {}", e);
                                 continue;
                             }
                             
@@ -637,15 +719,15 @@ fn process_rust_project(project_path: &Path, api_dir: &Path) -> Result<Option<St
                             // Convert to kebab-case for file name and interface name
                             kebab_interface_name = Some(to_kebab_case(&base_name));
                             
-                            println!("Interface name: {:?}", interface_name);
-                            println!("Base name: {}", base_name);
-                            println!("Kebab interface name: {:?}", kebab_interface_name);
+                            log!("Interface name: {:?}", interface_name);
+                            log!("Base name: {}", base_name);
+                            log!("Kebab interface name: {:?}", kebab_interface_name);
                             
                             // Save the impl item for later processing
                             impl_item_with_hyperprocess = Some(impl_item.clone());
                         }
                     },
-                    Err(e) => println!("Failed to extract wit_world: {}", e),
+                    Err(e) => log!("Failed to extract wit_world: {}", e),
                 }
             }
         }
@@ -660,7 +742,7 @@ fn process_rust_project(project_path: &Path, api_dir: &Path) -> Result<Option<St
         for item in &impl_item.items {
             if let ImplItem::Fn(method) = item {
                 let method_name = method.sig.ident.to_string();
-                println!("  Examining method: {}", method_name);
+                log!("  Examining method: {}", method_name);
                 
                 // Check for attribute types
                 let has_remote = method.attrs.iter().any(|attr| attr.path().is_ident("remote"));
@@ -668,7 +750,7 @@ fn process_rust_project(project_path: &Path, api_dir: &Path) -> Result<Option<St
                 let has_http = method.attrs.iter().any(|attr| attr.path().is_ident("http"));
                 
                 if has_remote || has_local || has_http {
-                    println!("    Has relevant attributes: remote={}, local={}, http={}", 
+                    log!("    Has relevant attributes: remote={}, local={}, http={}", 
                         has_remote, has_local, has_http);
                     
                     // Validate function name
@@ -676,42 +758,42 @@ fn process_rust_project(project_path: &Path, api_dir: &Path) -> Result<Option<St
                         Ok(_) => {
                             // Convert function name to kebab-case
                             let kebab_name = to_kebab_case(&method_name);
-                            println!("    Processing method: {} -> {}", method_name, kebab_name);
+                            log!("    Processing method: {} -> {}", method_name, kebab_name);
                             
                             // Generate a signature struct for each attribute type
                             if has_remote {
                                 match generate_signature_struct(&kebab_name, "remote", method, &mut used_types) {
                                     Ok(remote_struct) => signature_structs.push(remote_struct),
-                                    Err(e) => println!("    Error generating remote signature struct: {}", e),
+                                    Err(e) => report::skip(SkipReason::ConversionError, format!("function {} (remote)", method_name), e),
                                 }
                             }
                             
                             if has_local {
                                 match generate_signature_struct(&kebab_name, "local", method, &mut used_types) {
                                     Ok(local_struct) => signature_structs.push(local_struct),
-                                    Err(e) => println!("    Error generating local signature struct: {}", e),
+                                    Err(e) => report::skip(SkipReason::ConversionError, format!("function {} (local)", method_name), e),
                                 }
                             }
                             
                             if has_http {
                                 match generate_signature_struct(&kebab_name, "http", method, &mut used_types) {
                                     Ok(http_struct) => signature_structs.push(http_struct),
-                                    Err(e) => println!("    Error generating HTTP signature struct: {}", e),
+                                    Err(e) => report::skip(SkipReason::ConversionError, format!("function {} (http)", method_name), e),
                                 }
                             }
                         },
                         Err(e) => {
-                            println!("    Skipping method with invalid name: {}", e);
+                            report::skip(SkipReason::InvalidName, format!("function {}", method_name), e);
                         }
                     }
                 } else {
-                    println!("    Skipping method without relevant attributes");
+                    log!("    Skipping method without relevant attributes");
                 }
             }
         }
         
         // Include all defined types, not just the ones used in interface functions
-        println!("Including all defined types ({})", all_type_defs.len());
+        log!("Including all defined types ({})", all_type_defs.len());
         
         // Convert all type definitions to a vector
         let mut type_defs: Vec<String> = all_type_defs.values().cloned().collect();
@@ -721,7 +803,7 @@ fn process_rust_project(project_path: &Path, api_dir: &Path) -> Result<Option<St
         
         // Generate the final WIT content
         if signature_structs.is_empty() {
-            println!("No functions found for interface {}", iface_name);
+            log!("No functions found for interface {}", iface_name);
         } else {
             // Start with the interface comment
             let mut content = "    // This interface contains function signature definitions that will be used\n    // by the hyper-bindgen macro to generate async function bindings.\n    //\n    // NOTE: This is currently a hacky workaround since WIT async functions are not\n    // available until WASI Preview 3. Once Preview 3 is integrated into Hyperware,\n    // we should switch to using proper async WIT function signatures instead of\n    // this struct-based approach with hyper-bindgen generating the async stubs.\n".to_string();
@@ -739,30 +821,58 @@ fn process_rust_project(project_path: &Path, api_dir: &Path) -> Result<Option<St
             content.push_str(&signature_structs.join("\n\n"));
             
             // Wrap in interface block
-            let final_content = format!("interface {} {{\n{}\n}}\n", kebab_name, content);
-            println!("Generated interface content for {} with {} signature structs", iface_name, signature_structs.len());
+            // Annotations on the hyperprocess impl block, like `@docs <url>`, describe the interface
+            let interface_annotations: String = doc_annotations(&impl_item.attrs)
+                .iter()
+                .map(|annotation| format!("/// @{}\n", annotation))
+                .collect();
+            let final_content = format!("{}interface {} {{\n{}\n}}\n", interface_annotations, kebab_name, content);
+            log!("Generated interface content for {} with {} signature structs", iface_name, signature_structs.len());
             
             // Write the interface file with kebab-case name
             let interface_file = api_dir.join(format!("{}.wit", kebab_name));
-            println!("Writing WIT file to {}", interface_file.display());
+            log!("Writing WIT file to {}", interface_file.display());
             
-            fs::write(&interface_file, &final_content)
-                .with_context(|| format!("Failed to write {}", interface_file.display()))?;
+            output::write_if_changed(&interface_file, wit_format::format(&final_content))?;
             
-            println!("Successfully wrote WIT file");
+            log!("Successfully wrote WIT file");
         }
     }
     
     if let (Some(_), Some(_), Some(kebab_iface)) = (wit_world, interface_name, kebab_interface_name) {
-        println!("Returning import statement for interface {}", kebab_iface);
+        log!("Returning import statement for interface {}", kebab_iface);
         // Use kebab-case interface name for import
         Ok(Some(format!("    import {};", kebab_iface)))
     } else {
-        println!("No valid interface found");
+        log!("No valid interface found");
         Ok(None)
     }
 }
 
+// Delete the interface files an earlier run generated from a process that no longer exists,
+// tracked by a manifest in the api directory since hand-written WIT lives alongside them.
+// Returns the names of the removed interfaces.
+fn remove_stale_interfaces(api_dir: &Path, interfaces: &[String]) -> Result<Vec<String>> {
+    let files: Vec<PathBuf> = interfaces
+        .iter()
+        .map(|name| api_dir.join(format!("{}.wit", name)))
+        .filter(|file| file.exists())
+        .collect();
+    if files.is_empty() && manifest::read(api_dir)?.is_none() {
+        return Ok(Vec::new());
+    }
+    
+    let removed: Vec<String> = manifest::replace_shared(api_dir, &files)?
+        .iter()
+        .filter_map(|file| file.file_stem())
+        .map(|stem| stem.to_string_lossy().to_string())
+        .collect();
+    for name in &removed {
+        log!("Removed interface {}, whose process no longer exists", name);
+    }
+    Ok(removed)
+}
+
 // Generate WIT files from Rust code
 pub fn generate_wit_files(base_dir: &Path, api_dir: &Path) -> Result<(Vec<PathBuf>, Vec<String>)> {
     // Find all relevant Rust projects
@@ -770,20 +880,22 @@ pub fn generate_wit_files(base_dir: &Path, api_dir: &Path) -> Result<(Vec<PathBu
     let mut processed_projects = Vec::new();
     
     if projects.is_empty() {
-        println!("No relevant Rust projects found.");
+        log!("No relevant Rust projects found.");
+        remove_stale_interfaces(api_dir, &[])?;
         return Ok((Vec::new(), Vec::new()));
     }
     
     // Process each project and collect world imports
     let mut new_imports = Vec::new();
     let mut interfaces = Vec::new();
+    let mut failed = false;
     
     for project_path in &projects {
-        println!("Processing project: {}", project_path.display());
+        log!("Processing project: {}", project_path.display());
         
         match process_rust_project(project_path, api_dir) {
             Ok(Some(import)) => {
-                println!("Got import statement: {}", import);
+                log!("Got import statement: {}", import);
                 new_imports.push(import.clone());
                 
                 // Extract interface name from import statement
@@ -795,105 +907,139 @@ pub fn generate_wit_files(base_dir: &Path, api_dir: &Path) -> Result<(Vec<PathBu
                 interfaces.push(interface_name);
                 processed_projects.push(project_path.clone());
             },
-            Ok(None) => println!("No import statement generated"),
-            Err(e) => println!("Error processing project: {}", e),
+            Ok(None) => log!("No import statement generated"),
+            Err(e) => {
+                log!("Error processing project: {}", e);
+                failed = true;
+            }
         }
     }
     
-    println!("Collected {} new imports", new_imports.len());
+    log!("Collected {} new imports", new_imports.len());
+    
+    // A project that failed to process still has an interface, so keep what the last run generated
+    let removed = if failed {
+        log!("Keeping the WIT files of earlier runs, since not every project was processed");
+        Vec::new()
+    } else {
+        remove_stale_interfaces(api_dir, &interfaces)?
+    };
     
     // Check for existing world definition files and update them
-    println!("Looking for existing world definition files");
+    log!("Looking for existing world definition files");
     let mut updated_world = false;
     
-    for entry in WalkDir::new(api_dir)
+    // Worlds other worlds include are building blocks: the imports go to the worlds including them
+    let world_files: Vec<PathBuf> = WalkDir::new(api_dir)
         .max_depth(1)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(Result::ok)
-    {
-        let path = entry.path();
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "wit"))
+        .collect();
+    let included: HashSet<String> = world_files
+        .iter()
+        .filter_map(|path| text::read_normalized(path).ok())
+        .flat_map(|content| wit_source::worlds(&content))
+        .flat_map(|world| world.includes)
+        .collect();
+    
+    for path in &world_files {
+        log!("Checking WIT file: {}", path.display());
         
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "wit") {
-            println!("Checking WIT file: {}", path.display());
-            
-            if let Ok(content) = fs::read_to_string(path) {
-                if content.contains("world ") {
-                    println!("Found world definition file");
+        if let Ok(content) = text::read_normalized(path) {
+            if wit_source::declares_world(&content) {
+                log!("Found world definition file");
+                
+                // Extract the world name, its package and existing imports
+                let lines: Vec<&str> = content.lines().collect();
+                let world_name = wit_source::world_name(&content);
+                let package = wit_source::package_declaration(&content);
+                let mut existing_imports = Vec::new();
+                let mut include_lines = Vec::new();
+                
+                for line in &lines {
+                    let trimmed = line.trim();
                     
-                    // Extract the world name and existing imports
-                    let lines: Vec<&str> = content.lines().collect();
-                    let mut world_name = None;
-                    let mut existing_imports = Vec::new();
-                    let mut include_line = "    include process-v1;".to_string();
+                    if trimmed.starts_with("import ") {
+                        existing_imports.push(trimmed.to_string());
+                    } else if trimmed.starts_with("include ") {
+                        include_lines.push(trimmed.to_string());
+                    }
+                }
+                
+                if let Some(world_name) = world_name {
+                    log!("Extracted world name: {}", world_name);
+                    if included.contains(&world_name) {
+                        log!("Leaving world {} as is: other worlds include it", world_name);
+                        continue;
+                    }
                     
-                    for line in &lines {
-                        let trimmed = line.trim();
-                        
-                        if trimmed.starts_with("world ") {
-                            if let Some(name) = trimmed.split_whitespace().nth(1) {
-                                world_name = Some(name.trim_end_matches(" {").to_string());
-                            }
-                        } else if trimmed.starts_with("import ") {
-                            existing_imports.push(trimmed.to_string());
-                        } else if trimmed.starts_with("include ") {
-                            include_line = trimmed.to_string();
-                        }
+                    // Determine the include lines based on world name
+                    // If world name starts with "types-", use "include lib;" instead of
+                    // process-v1, keeping the includes of other worlds
+                    if world_name.starts_with("types-") {
+                        include_lines.retain(|line| line != "include process-v1;" && line != "include lib;");
+                        include_lines.push("include lib;".to_string());
+                    } else if include_lines.is_empty() {
+                        // Keep existing includes or default to process-v1
+                        include_lines.push("include process-v1;".to_string());
                     }
                     
-                    if let Some(world_name) = world_name {
-                        println!("Extracted world name: {}", world_name);
-                        
-                        // Determine the include line based on world name
-                        // If world name starts with "types-", use "include lib;" instead
-                        if world_name.starts_with("types-") {
-                            include_line = "    include lib;".to_string();
-                        } else {
-                            // Keep existing include or default to process-v1
-                            if !include_line.contains("include ") {
-                                include_line = "    include process-v1;".to_string();
-                            }
+                    // Combine existing imports with new imports, dropping those of removed interfaces
+                    let mut all_imports: Vec<String> = existing_imports
+                        .iter()
+                        .filter(|import| {
+                            !removed.iter().any(|name| import.as_str() == format!("import {};", name))
+                        })
+                        .cloned()
+                        .collect();
+                    
+                    for import in &new_imports {
+                        let import_stmt = import.trim();
+                        if !all_imports.iter().any(|i| i.trim() == import_stmt) {
+                            all_imports.push(import_stmt.to_string());
                         }
-                        
-                        // Combine existing imports with new imports
-                        let mut all_imports = existing_imports.clone();
-                        
-                        for import in &new_imports {
-                            let import_stmt = import.trim();
-                            if !all_imports.iter().any(|i| i.trim() == import_stmt) {
-                                all_imports.push(import_stmt.to_string());
+                    }
+                    
+                    // Make sure all imports have proper indentation
+                    let all_imports_with_indent: Vec<String> = all_imports
+                        .iter()
+                        .map(|import| {
+                            if import.starts_with("    ") {
+                                import.clone()
+                            } else {
+                                format!("    {}", import.trim())
                             }
-                        }
-                        
-                        // Make sure all imports have proper indentation
-                        let all_imports_with_indent: Vec<String> = all_imports
-                            .iter()
-                            .map(|import| {
-                                if import.starts_with("    ") {
-                                    import.clone()
-                                } else {
-                                    format!("    {}", import.trim())
-                                }
-                            })
-                            .collect();
-                        
-                        let imports_section = all_imports_with_indent.join("\n");
-                        
-                        // Create updated world content with proper indentation
-                        let world_content = format!(
-                            "world {} {{\n{}\n    {}\n}}",
-                            world_name,
-                            imports_section,
-                            include_line.trim()
-                        );
-                        
-                        println!("Writing updated world definition to {}", path.display());
-                        // Write the updated world file
-                        fs::write(path, world_content)
-                            .with_context(|| format!("Failed to write updated world file: {}", path.display()))?;
-                        
-                        println!("Successfully updated world definition");
-                        updated_world = true;
+                        })
+                        .collect();
+                    
+                    let imports_section = all_imports_with_indent.join("\n");
+                    
+                    // Create updated world content with proper indentation, keeping the
+                    // package declaration and the doc comments and gates of the world
+                    let mut world_content = format!(
+                        "world {} {{\n{}\n    {}\n}}",
+                        world_name,
+                        imports_section,
+                        include_lines.join("\n    ")
+                    );
+                    let attributes = wit_source::world_attributes(&content);
+                    if !attributes.is_empty() {
+                        world_content = format!("{}\n{}", attributes.join("\n"), world_content);
+                    }
+                    if let Some(package) = package {
+                        world_content = format!("package {};\n\n{}", package, world_content);
                     }
+                    
+                    log!("Writing updated world definition to {}", path.display());
+                    // Write the updated world file
+                    output::write_if_changed(path, wit_format::format(&world_content))
+                        .with_context(|| format!("Failed to write updated world file: {}", path.display()))?;
+                    
+                    log!("Successfully updated world definition");
+                    updated_world = true;
                 }
             }
         }
@@ -903,7 +1049,7 @@ pub fn generate_wit_files(base_dir: &Path, api_dir: &Path) -> Result<(Vec<PathBu
     if !updated_world && !new_imports.is_empty() {
         // Define default world name
         let default_world = "async-app-template-dot-os-v0";
-        println!("No existing world definitions found, creating default with name: {}", default_world);
+        log!("No existing world definitions found, creating default with name: {}", default_world);
         
         // Create world content with process-v1 include and proper indentation for imports
         let imports_with_indent: Vec<String> = new_imports
@@ -932,14 +1078,14 @@ pub fn generate_wit_files(base_dir: &Path, api_dir: &Path) -> Result<(Vec<PathBu
         );
         
         let world_file = api_dir.join(format!("{}.wit", default_world));
-        println!("Writing default world definition to {}", world_file.display());
+        log!("Writing default world definition to {}", world_file.display());
         
-        fs::write(&world_file, world_content)
+        output::write_if_changed(&world_file, wit_format::format(&world_content))
             .with_context(|| format!("Failed to write default world file: {}", world_file.display()))?;
         
-        println!("Successfully created default world definition");
+        log!("Successfully created default world definition");
     }
     
-    println!("WIT files generated successfully in the 'api' directory.");
+    log!("WIT files generated successfully in the 'api' directory.");
     Ok((processed_projects, interfaces))
 }
\ No newline at end of file
@@ -0,0 +1,503 @@
+use anyhow::Result;
+use serde::Serialize;
+use wit_parser::{AstItem, FunctionKind, InterfaceId, Type, TypeDefKind, TypeOwner, UnresolvedPackage, UnresolvedPackageGroup};
+
+use hyper_bindgen::case::to_snake_case;
+
+use crate::component::{render_definition, render_type};
+
+// Package the interface files of the api directory are parsed as, as they don't declare one
+const API_PACKAGE: &str = "hyper-bindgen:api";
+// Package of the interfaces api files `use` without defining them, like `standard`, which
+// come from hyperware's process WIT
+const HOST_PACKAGE: &str = "hyperware:process";
+// Interface of the `unit` type signature records return, which no WIT package defines
+const UNIT_INTERFACE: &str = "hyper-bindgen:prelude/types";
+
+// WIT keywords, which have to be escaped with `%` when used as identifiers
+const WIT_KEYWORDS: &[&str] = &[
+    "as", "async", "bool", "borrow", "char", "constructor", "enum", "export", "f32", "f64",
+    "flags", "from", "func", "future", "import", "include", "interface", "list", "option", "own",
+    "package", "record", "resource", "result", "s16", "s32", "s64", "s8", "static", "stream",
+    "string", "tuple", "type", "u16", "u32", "u64", "u8", "use", "variant", "with", "world",
+];
+
+// Kinds of type definitions with their own keyword; any other definition is a `type` alias
+const DEFINITION_KEYWORDS: [&str; 5] = ["record", "variant", "enum", "flags", "resource"];
+
+// A type defined in an interface of an api file
+pub struct Definition {
+    pub name: String,
+    // Keyword the definition starts with, e.g. `record` or `type`
+    pub keyword: &'static str,
+    // 1-based line and column of the definition's name in the file
+    pub line: usize,
+    pub column: usize,
+    // Lines of its `///` doc comments
+    pub docs: Vec<String>,
+    // Fields of a record with their types as written in WIT
+    pub fields: Vec<(String, String)>,
+    // Cases of an enum
+    pub cases: Vec<String>,
+    // Names of the types the definition refers to
+    pub references: Vec<String>,
+}
+
+// A type an interface takes from another one with `use other.{name as local}`
+#[derive(Serialize)]
+pub struct Import {
+    pub interface: String,
+    pub name: String,
+    pub local: String,
+}
+
+// A function an interface declares with `name: func(..)`
+pub struct Function {
+    pub name: String,
+    // 1-based line and column of the declaration in the file
+    pub line: usize,
+    pub column: usize,
+    pub docs: Vec<String>,
+    // Parameters with their types as written in WIT
+    pub params: Vec<(String, String)>,
+    pub result: Option<String>,
+}
+
+// An interface of an api file with the types and functions it defines, in source order
+pub struct Interface {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub definitions: Vec<Definition>,
+    pub imports: Vec<Import>,
+    pub functions: Vec<Function>,
+}
+
+// A WIT package, as declared by `package namespace:name@version;`
+#[derive(Clone, PartialEq)]
+pub struct Package {
+    pub namespace: String,
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl Package {
+    // Package api files without a declaration belong to
+    pub fn host() -> Self {
+        let (namespace, name) = HOST_PACKAGE.split_once(':').unwrap_or_default();
+        Package {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            version: None,
+        }
+    }
+
+    // Name of an interface or world of the package as wit-bindgen takes it,
+    // e.g. `hyperware:my-app/chat@0.1.0`
+    pub fn qualify(&self, item: &str) -> String {
+        match &self.version {
+            Some(version) => format!("{}:{}/{}@{}", self.namespace, self.name, item, version),
+            None => format!("{}:{}/{}", self.namespace, self.name, item),
+        }
+    }
+
+    // Rust module wit-bindgen generates for an interface of the package,
+    // e.g. `crate::hyperware::my_app::chat`
+    pub fn module(&self, interface: &str) -> String {
+        format!(
+            "crate::{}::{}::{}",
+            to_snake_case(&self.namespace),
+            to_snake_case(&self.name),
+            to_snake_case(interface)
+        )
+    }
+}
+
+impl std::fmt::Display for Package {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, "@{}", version)?;
+        }
+        Ok(())
+    }
+}
+
+// Package declared at the top of a WIT file, before any other item
+pub fn package_declaration(content: &str) -> Option<Package> {
+    let code = content
+        .lines()
+        .map(strip_comment)
+        .find(|line| !line.is_empty())?;
+    let rest = code.strip_prefix("package ")?;
+    let end = rest.find(|c: char| c == ';' || c == '{' || c.is_whitespace()).unwrap_or(rest.len());
+    let (name, version) = match rest[..end].split_once('@') {
+        Some((name, version)) => (name, Some(version.to_string())),
+        None => (&rest[..end], None),
+    };
+    let (namespace, name) = name.split_once(':')?;
+    Some(Package {
+        namespace: namespace.trim_start_matches('%').to_string(),
+        name: name.trim_start_matches('%').to_string(),
+        version,
+    })
+}
+
+// Name of the first world a WIT file declares, also when it follows the package declaration
+// on the same line
+pub fn world_name(content: &str) -> Option<String> {
+    content.lines().find_map(|line| world_header(line).map(str::to_string))
+}
+
+// Whether a WIT file declares a world, as opposed to only interfaces
+pub fn declares_world(content: &str) -> bool {
+    world_name(content).is_some()
+}
+
+// Name of the world a header line declares, however it's spaced and whatever gates precede the
+// keyword: `app` for `world app {`, `world   app{` or `@since(version = 0.2.0) world app {`
+pub fn world_header(line: &str) -> Option<&str> {
+    declared_name(strip_gates(without_package(line)), "world")
+}
+
+// Doc comment and gate lines written before the first world of a WIT file, including gates on
+// the header line itself, so that rewriting the world can keep them
+pub fn world_attributes(content: &str) -> Vec<String> {
+    let lines: Vec<&str> = content.lines().map(str::trim).collect();
+    let Some(header) = lines.iter().position(|line| world_header(line).is_some()) else {
+        return Vec::new();
+    };
+    let start = lines[..header]
+        .iter()
+        .rposition(|line| !(line.starts_with("///") || line.starts_with('@')))
+        .map_or(0, |line| line + 1);
+    let mut attributes: Vec<String> = lines[start..header].iter().map(|line| line.to_string()).collect();
+    let header = without_package(lines[header]).trim_start();
+    let gates = header[..header.len() - strip_gates(header).len()].trim();
+    if !gates.is_empty() {
+        attributes.push(gates.to_string());
+    }
+    attributes
+}
+
+// A line without the package declaration it starts with, if any
+fn without_package(line: &str) -> &str {
+    let line = line.trim_start();
+    match line.strip_prefix("package ") {
+        Some(rest) => rest.split_once(';').map_or("", |(_, rest)| rest),
+        None => line,
+    }
+}
+
+// A line without the feature gates it starts with, like `@since(version = 0.2.0)` or
+// `@unstable(feature = foo)`
+fn strip_gates(line: &str) -> &str {
+    let mut rest = line.trim_start();
+    while let Some(gate) = rest.strip_prefix('@') {
+        let name_end = gate.find(|c: char| !(c.is_alphanumeric() || c == '-')).unwrap_or(gate.len());
+        let after = gate[name_end..].trim_start();
+        rest = match after.strip_prefix('(') {
+            Some(arguments) => arguments.split_once(')').map_or("", |(_, rest)| rest).trim_start(),
+            None => after,
+        };
+    }
+    rest
+}
+
+// A world of a WIT file with the interfaces it imports and the worlds it includes, as written
+pub struct World {
+    pub name: String,
+    pub imports: Vec<String>,
+    pub includes: Vec<String>,
+}
+
+// Worlds a WIT file declares, read line by line like the world files hyper-bindgen writes
+pub fn worlds(content: &str) -> Vec<World> {
+    let mut worlds = Vec::new();
+    let mut current: Option<World> = None;
+    // Braces open in the current world
+    let mut depth = 0;
+    for line in content.lines() {
+        let code = strip_comment(line);
+        if current.is_none() {
+            if let Some(name) = world_header(code) {
+                current = Some(World {
+                    name: name.to_string(),
+                    imports: Vec::new(),
+                    includes: Vec::new(),
+                });
+                depth = 0;
+            }
+        }
+        let Some(world) = current.as_mut() else {
+            continue;
+        };
+        if depth == 1 {
+            if let Some(import) = code.strip_prefix("import ").and_then(|rest| rest.strip_suffix(';')) {
+                world.imports.push(unescape(import.trim()).to_string());
+            } else if let Some(include) = code.strip_prefix("include ") {
+                let end = include.find(|c: char| c == ';' || c.is_whitespace()).unwrap_or(include.len());
+                world.includes.push(include[..end].trim_start_matches('%').to_string());
+            }
+        }
+        depth += code.matches('{').count() as i32 - code.matches('}').count() as i32;
+        if depth <= 0 && code.contains('}') {
+            worlds.extend(current.take());
+        }
+    }
+    worlds.extend(current);
+    worlds
+}
+
+// An identifier as written in WIT: `%`-escaped when it is a keyword, e.g. `%result`
+pub fn escape(name: &str) -> String {
+    if WIT_KEYWORDS.contains(&name) {
+        format!("%{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+// An identifier without the `%` escaping a keyword, e.g. `result` for `%result`
+pub fn unescape(name: &str) -> &str {
+    name.strip_prefix('%').unwrap_or(name)
+}
+
+// A line of WIT without its trailing `//` comment and the whitespace around its code
+pub fn strip_comment(line: &str) -> &str {
+    line.split("//").next().unwrap_or_default().trim()
+}
+
+// Name declared by a line starting with `keyword`, e.g. `chat` for `interface chat {`
+fn declared_name<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let rest = line.trim_start().strip_prefix(keyword)?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let rest = rest.trim_start().trim_start_matches('%');
+    let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '-')).unwrap_or(rest.len());
+    Some(&rest[..end]).filter(|name| !name.is_empty())
+}
+
+// Path and local names of a `use path.{a, b as c};` line, e.g. ("standard", ["a", "c"])
+fn use_statement(line: &str) -> Option<(&str, Vec<&str>)> {
+    let rest = line.trim_start().strip_prefix("use ")?;
+    let (path, names) = rest.split_once(".{")?;
+    let names = names.split('}').next().unwrap_or_default();
+    let names = names
+        .split(',')
+        .filter_map(|item| item.split_whitespace().last())
+        .map(|name| name.trim_start_matches('%'))
+        .collect();
+    Some((path.trim(), names))
+}
+
+// Make an interface file of the api directory parseable on its own: give it a package, point
+// `use` statements of interfaces it doesn't define at hyperware's process package, and declare
+// `unit` in interfaces that don't define it. Everything is added to existing lines, so that
+// line numbers of definitions and errors are those of the file.
+fn parseable_source(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let interfaces: Vec<&str> = lines.iter().filter_map(|line| declared_name(line, "interface")).collect();
+    let has_package = lines.iter().any(|line| line.trim_start().starts_with("package "));
+    let defines_unit = lines.iter().any(|line| {
+        DEFINITION_KEYWORDS.iter().chain(&["type"]).any(|keyword| declared_name(line, keyword) == Some("unit"))
+            || use_statement(line).is_some_and(|(_, names)| names.contains(&"unit"))
+    });
+
+    let mut source = String::new();
+    // Whether the `{` opening the last interface header is still to come
+    let mut in_header = false;
+    for (index, original) in lines.iter().enumerate() {
+        let mut line = original.to_string();
+        // wit-parser keeps every comment as docs; only `///` lines are documentation here
+        let comment = original.trim_start();
+        if comment.starts_with("//") && (!comment.starts_with("///") || comment.starts_with("////")) {
+            line.clear();
+        }
+        if index == 0 && !has_package {
+            line = format!("package {}; {}", API_PACKAGE, line);
+        }
+        if let Some((path, _)) = use_statement(original) {
+            let local = path.trim_start_matches('%');
+            if !path.contains(':') && !interfaces.contains(&local) {
+                let at = line.rfind("use ").unwrap_or(0) + "use ".len();
+                line.insert_str(at, &format!("{}/", HOST_PACKAGE));
+            }
+        }
+        if declared_name(original, "interface").is_some() {
+            in_header = !defines_unit;
+        }
+        if in_header {
+            let code = line.split("//").next().unwrap_or_default();
+            if let Some(brace) = code.find('{') {
+                line.insert_str(brace + 1, &format!(" use {}.{{unit}};", UNIT_INTERFACE));
+                in_header = false;
+            }
+        }
+        source.push_str(&line);
+        source.push('\n');
+    }
+    source
+}
+
+// 1-based line and column of the definition of `name` with `keyword`, found in the source as
+// wit-parser doesn't expose the spans of definitions
+fn definition_position(content: &str, keyword: &str, name: &str) -> (usize, usize) {
+    content
+        .lines()
+        .enumerate()
+        .find(|(_, line)| declared_name(line, keyword) == Some(name))
+        .map_or((1, 1), |(index, line)| {
+            let after_keyword = line.find(keyword).map_or(0, |at| at + keyword.len());
+            (index + 1, column(line, after_keyword + line[after_keyword..].find(name).unwrap_or(0)))
+        })
+}
+
+// 1-based line and column of the declaration of function `name`, e.g. `send-message: func(`
+fn function_position(content: &str, name: &str) -> (usize, usize) {
+    content
+        .lines()
+        .enumerate()
+        .find(|(_, line)| {
+            line.trim_start()
+                .trim_start_matches('%')
+                .strip_prefix(name)
+                .is_some_and(|rest| rest.trim_start().starts_with(':') && rest.contains("func"))
+        })
+        .map_or((1, 1), |(index, line)| (index + 1, column(line, line.find(name).unwrap_or(0))))
+}
+
+// 1-based column of a byte offset in a line, counted in characters
+pub fn column(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+// Name of an interface as written in the file, also for the interfaces of other packages
+fn interface_name(package: &UnresolvedPackage, id: InterfaceId) -> Option<String> {
+    package.interfaces[id].name.clone().or_else(|| {
+        package
+            .foreign_deps
+            .values()
+            .flat_map(|items| items.iter())
+            .find(|(_, item)| matches!(item, AstItem::Interface(foreign) if *foreign == id))
+            .map(|(name, _)| name.clone())
+    })
+}
+
+// Names of the types a piece of WIT type syntax mentions, e.g. `list<tuple<string, reply>>`
+pub fn type_names(wit_type: &str) -> Vec<String> {
+    wit_type
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '%'))
+        .filter(|word| !word.is_empty())
+        .map(|word| word.trim_start_matches('%').to_string())
+        .collect()
+}
+
+fn doc_lines(docs: &wit_parser::Docs) -> Vec<String> {
+    docs.contents
+        .as_deref()
+        .map(|contents| contents.lines().map(|line| line.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+// Parse an interface file of the api directory, named `source_name` in errors, into its
+// interfaces and the types and functions they define. Types an interface takes from another one
+// with `use` are listed as its imports instead. Resource methods aren't functions here.
+pub fn parse(source_name: &str, content: &str) -> Result<Vec<Interface>> {
+    let group = UnresolvedPackageGroup::parse(source_name, &parseable_source(content))?;
+    let package = &group.main;
+
+    let mut interfaces = Vec::new();
+    for (id, interface) in package.interfaces.iter() {
+        // Interfaces of other packages, which api files only refer to, are anonymous here
+        let Some(name) = &interface.name else {
+            continue;
+        };
+        let mut definitions = Vec::new();
+        let mut imports = Vec::new();
+        for (type_name, type_id) in &interface.types {
+            let def = &package.types[*type_id];
+            if let TypeDefKind::Type(Type::Id(source)) = def.kind {
+                let source = &package.types[source];
+                if source.owner != TypeOwner::Interface(id) {
+                    if let (TypeOwner::Interface(owner), Some(name)) = (source.owner, &source.name) {
+                        if let Some(interface) = interface_name(package, owner) {
+                            imports.push(Import {
+                                interface,
+                                name: name.clone(),
+                                local: type_name.clone(),
+                            });
+                        }
+                    }
+                    continue;
+                }
+            }
+            let keyword = DEFINITION_KEYWORDS
+                .into_iter()
+                .find(|keyword| *keyword == def.kind.as_str())
+                .unwrap_or("type");
+            let fields = match &def.kind {
+                TypeDefKind::Record(record) => record
+                    .fields
+                    .iter()
+                    .map(|field| (field.name.clone(), render_type(&package.types, &field.ty)))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let cases = match &def.kind {
+                TypeDefKind::Enum(enum_) => enum_.cases.iter().map(|case| case.name.clone()).collect(),
+                _ => Vec::new(),
+            };
+            let referenced: Vec<String> = match &def.kind {
+                TypeDefKind::Record(_) => fields.iter().map(|(_, wit_type)| wit_type.clone()).collect(),
+                TypeDefKind::Variant(variant) => variant
+                    .cases
+                    .iter()
+                    .filter_map(|case| case.ty.as_ref())
+                    .map(|ty| render_type(&package.types, ty))
+                    .collect(),
+                TypeDefKind::Enum(_) | TypeDefKind::Flags(_) | TypeDefKind::Resource => Vec::new(),
+                kind => vec![render_definition(&package.types, kind)],
+            };
+            let (line, column) = definition_position(content, keyword, type_name);
+            definitions.push(Definition {
+                name: type_name.clone(),
+                keyword,
+                line,
+                column,
+                docs: doc_lines(&def.docs),
+                fields,
+                cases,
+                references: referenced.iter().flat_map(|wit_type| type_names(wit_type)).collect(),
+            });
+        }
+        let functions = interface
+            .functions
+            .values()
+            .filter(|function| matches!(function.kind, FunctionKind::Freestanding | FunctionKind::AsyncFreestanding))
+            .map(|function| {
+                let (line, column) = function_position(content, &function.name);
+                Function {
+                    name: function.name.clone(),
+                    line,
+                    column,
+                    docs: doc_lines(&function.docs),
+                    params: function
+                        .params
+                        .iter()
+                        .map(|(name, ty)| (name.clone(), render_type(&package.types, ty)))
+                        .collect(),
+                    result: function.result.as_ref().map(|ty| render_type(&package.types, ty)),
+                }
+            })
+            .collect();
+        interfaces.push(Interface {
+            name: name.clone(),
+            docs: doc_lines(&interface.docs),
+            definitions,
+            imports,
+            functions,
+        });
+    }
+    Ok(interfaces)
+}